@@ -0,0 +1,124 @@
+-- SPDX-License-Identifier: GPL-3.0-or-later
+
+---Events.
+---
+---This module lets you run functions when things happen in Pinnacle, like a window opening
+---or a tag being switched to, without having to poll for changes.
+---@class EventModule
+local event_module = {}
+
+---@alias EventKind
+---| "WindowOpened" # A window opened. Handler: `fun(window: Window)`
+---| "WindowClosed" # A window closed. Handler: `fun(window: Window)`
+---| "FocusChanged" # Keyboard focus moved. Handler: `fun(window: Window|nil)`
+---| "TitleChanged" # A window's title changed. Handler: `fun(window: Window, title: string|nil)`
+---| "TagAdded" # A tag was added to an output. Handler: `fun(tag: Tag, output: Output)`
+---| "TagRemoved" # A tag was removed. Handler: `fun(tag: Tag)`
+---| "TagActivated" # A tag was activated or deactivated. Handler: `fun(tag: Tag, active: boolean)`
+---| "LayoutChanged" # A tag's layout changed. Handler: `fun(tag: Tag, layout: Layout)`
+---| "OutputConnected" # An output was connected, or was already connected when first subscribed to. Handler: `fun(output: Output)`
+---| "OutputDisconnected" # An output was disconnected. Handler: `fun(output_name: string)`
+---| "BindModeChanged" # The bind mode changed. Handler: `fun(mode: string)`
+
+---Functions that turn an event's data into the arguments for its handlers.
+---@type table<EventKind, fun(data: table): ...>
+local converters = {
+    WindowOpened = function(data)
+        return require("window").get_by_id(data.window_id)
+    end,
+    WindowClosed = function(data)
+        return require("window").get_by_id(data.window_id)
+    end,
+    FocusChanged = function(data)
+        if data.window_id == nil then
+            return nil
+        end
+        return require("window").get_by_id(data.window_id)
+    end,
+    TitleChanged = function(data)
+        return require("window").get_by_id(data.window_id), data.title
+    end,
+    TagAdded = function(data)
+        return require("tag").get_by_id(data.tag_id), require("output")._from_name(data.output_name)
+    end,
+    TagRemoved = function(data)
+        return require("tag").get_by_id(data.tag_id)
+    end,
+    TagActivated = function(data)
+        return require("tag").get_by_id(data.tag_id), data.active
+    end,
+    LayoutChanged = function(data)
+        return require("tag").get_by_id(data.tag_id), data.layout
+    end,
+    OutputConnected = function(data)
+        return require("output")._from_name(data.output_name)
+    end,
+    OutputDisconnected = function(data)
+        return data.output_name
+    end,
+    BindModeChanged = function(data)
+        return data.mode
+    end,
+}
+
+---@type table<EventKind, function[]>
+local handlers = {}
+
+---Run a function every time an event happens.
+---
+---The arguments passed to `handler` depend on the event; see `EventKind` for details.
+---
+---### Example
+---```lua
+---event.connect("WindowOpened", function(window)
+---    print("Opened " .. (window:class() or "a window"))
+---end)
+---
+---event.connect("TagActivated", function(tag, active)
+---    if active then
+---        print("Switched to tag " .. tag:name())
+---    end
+---end)
+---```
+---@param kind EventKind
+---@param handler function
+function event_module.connect(kind, handler)
+    if handlers[kind] == nil then
+        handlers[kind] = {}
+        SendMsg({
+            Subscribe = {
+                events = { kind },
+            },
+        })
+    end
+    table.insert(handlers[kind], handler)
+end
+
+---Stop running all functions connected to an event.
+---@param kind EventKind
+function event_module.disconnect_all(kind)
+    if handlers[kind] == nil then
+        return
+    end
+    handlers[kind] = nil
+    SendMsg({
+        Unsubscribe = {
+            events = { kind },
+        },
+    })
+end
+
+---@nodoc
+---Run the handlers for an event sent by Pinnacle.
+---@param event Event
+function event_module._dispatch(event)
+    for kind, data in pairs(event) do
+        if handlers[kind] then
+            for _, handler in pairs(handlers[kind]) do
+                handler(converters[kind](data))
+            end
+        end
+    end
+end
+
+return event_module
@@ -38,6 +38,69 @@ local input_module = {
     buttons = buttons,
 }
 
+---@param key Keys|string
+---@return { Int: Keys?, String: string? }
+local function key_int_or_string(key)
+    if type(key) == "string" then
+        return { String = key }
+    else
+        return { Int = key }
+    end
+end
+
+---What keybind and key sequence actions are called with.
+---@class KeybindArgs
+---@field key Keys The keysym that triggered the keybind.
+---@field modifiers Modifier[] The modifiers that were held.
+---@field window Window|nil The window with keyboard focus, if any.
+
+---What mousebind actions are called with.
+---@class MousebindArgs
+---@field pointer_loc { x: number, y: number } Where the pointer was, in global coordinates.
+---@field window Window|nil The window under the pointer, if any.
+---@field output Output|nil The output under the pointer, if any.
+
+---What gesture bind actions are called with.
+---@class GestureArgs
+---@field pointer_loc { x: number, y: number } Where the pointer was, in global coordinates.
+---@field window Window|nil The window under the pointer, if any.
+---@field output Output|nil The output under the pointer, if any.
+
+---@param args Args
+---@return KeybindArgs
+local function keybind_args(args)
+    local args = args.Keybind or {}
+    local window = nil
+    if args.window_id then
+        window = require("window").get_by_id(args.window_id)
+    end
+    return {
+        key = args.key,
+        modifiers = args.modifiers or {},
+        window = window,
+    }
+end
+
+---Turn the args of a mousebind or gesture bind into what its action is called with.
+---@param args { pointer_loc: number[]?, window_id: WindowId?, output_name: OutputName? }
+---@return MousebindArgs|GestureArgs
+local function pointer_bind_args(args)
+    local window = nil
+    if args.window_id then
+        window = require("window").get_by_id(args.window_id)
+    end
+    local output = nil
+    if args.output_name then
+        output = require("output")._from_name(args.output_name)
+    end
+    local loc = args.pointer_loc or {}
+    return {
+        pointer_loc = { x = loc[1], y = loc[2] },
+        window = window,
+        output = output,
+    }
+end
+
 ---Set a keybind. If called with an already existing keybind, it gets replaced.
 ---
 ---You must provide three arguments:
@@ -47,7 +110,8 @@ local input_module = {
 ---     - Something from the `Keys` table in `input.keys`, which lists every xkbcommon key. The naming pattern is the xkbcommon key without the `KEY_` prefix, unless that would make it start with a number or the reserved lua keyword `function`, in which case the `KEY_` prefix is included.
 ---     - A single character representing your key. This can be something like "g", "$", "~", "1", and so on.
 ---     - A string of the key's name. This is the name of the xkbcommon key without the `KEY_` prefix.
---- - `action`: The function that will be run when the keybind is pressed.
+--- - `action`: The function that will be run when the keybind is pressed. It's called with a `KeybindArgs`
+---   holding the key and modifiers that triggered it and the focused window.
 ---
 ---It is important to note that `"a"` is different than `"A"`. Similarly, `keys.a` is different than `keys.A`.
 ---Usually, it's best to use the non-modified key to prevent confusion and unintended behavior.
@@ -69,29 +133,391 @@ local input_module = {
 ---    process.spawn("Alacritty")
 ---end)
 ---```
+---
+---You can also provide an optional fourth argument, a table of options:
+---
+--- - `mode`: The bind mode this keybind works in. See `input.set_bind_mode`.
+--- - `edge`: "Press" or "Release". Whether to trigger `action` on key press or release. Defaults to "Press".
+---   Release keybinds match the modifiers that were held when the key was pressed.
+--- - `allow_repeat`: Whether to run `action` again on every key repeat while the key is held.
+---   Only applies to "Press" keybinds. Defaults to `false`.
+---
+---```lua
+--- -- Only works after entering the "resize" bind mode
+---input.keybind({}, "h", function() end, { mode = "resize" })
+---
+--- -- Keeps raising the volume while the key is held
+---input.keybind({}, input.keys.XF86AudioRaiseVolume, function()
+---    process.spawn({ "pactl", "set-sink-volume", "@DEFAULT_SINK@", "+5%" })
+---end, { allow_repeat = true })
+---```
 ---@param key Keys|string The key for the keybind.
 ---@param modifiers (Modifier)[] Which modifiers need to be pressed for the keybind to trigger.
----@param action fun() What to do.
-function input_module.keybind(modifiers, key, action)
-    table.insert(CallbackTable, action)
+---@param action fun(args: KeybindArgs) What to do.
+---@param opts { mode: string?, edge: "Press"|"Release"|nil, allow_repeat: boolean? }?
+function input_module.keybind(modifiers, key, action, opts)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        action(keybind_args(args))
+    end)
 
-    local k = {}
-
-    if type(key) == "string" then
-        k.String = key
-    else
-        k.Int = key
-    end
+    opts = opts or {}
 
     SendMsg({
         SetKeybind = {
             modifiers = modifiers,
-            key = k,
+            key = key_int_or_string(key),
             callback_id = #CallbackTable,
+            mode = opts.mode,
+            edge = opts.edge,
+            allow_repeat = opts.allow_repeat,
+        },
+    })
+end
+
+---Remove a keybind you set earlier.
+---
+---Keybinds set by other clients can't be removed.
+---
+---```lua
+---input.remove_keybind({ "Super" }, input.keys.Return)
+---```
+---@param modifiers (Modifier)[] The modifiers of the keybind.
+---@param key Keys|string The key of the keybind.
+---@param opts { mode: string?, edge: "Press"|"Release"|nil }? The `mode` and `edge` the keybind was set with.
+function input_module.remove_keybind(modifiers, key, opts)
+    opts = opts or {}
+
+    SendMsg({
+        RemoveKeybind = {
+            modifiers = modifiers,
+            key = key_int_or_string(key),
+            mode = opts.mode,
+            edge = opts.edge,
+        },
+    })
+end
+
+---@alias KeyCombo { [1]: (Modifier)[], [2]: Keys|string }
+
+---@param sequence KeyCombo[]
+---@return { modifiers: Modifier[], key: { Int: Keys?, String: string? } }[]
+local function key_combos(sequence)
+    local combos = {}
+    for _, combo in pairs(sequence) do
+        table.insert(combos, { modifiers = combo[1], key = key_int_or_string(combo[2]) })
+    end
+    return combos
+end
+
+---Set a key sequence: key combos that must be pressed one after another to run `action`.
+---
+---Each combo is a table of modifiers followed by a key, like in `input.keybind`.
+---Keys pressed partway through a sequence are not sent to windows. If the next key
+---doesn't continue the sequence or takes too long to be pressed, the sequence is cancelled.
+---See `input.set_key_sequence_timeout`.
+---
+---Keybinds take precedence over the first combo of a key sequence.
+---
+---```lua
+--- -- `Super + a`, then `t` opens Alacritty
+---input.key_sequence({ { { "Super" }, "a" }, { {}, "t" } }, function()
+---    process.spawn("Alacritty")
+---end)
+---```
+---@param sequence KeyCombo[] The key combos to press, in order.
+---@param action fun(args: KeybindArgs) What to do. `args` has the last key combo of the sequence.
+---@param opts { mode: string? }? The bind mode the sequence works in, like for `input.keybind`.
+function input_module.key_sequence(sequence, action, opts)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        action(keybind_args(args))
+    end)
+
+    opts = opts or {}
+
+    SendMsg({
+        SetKeySequence = {
+            sequence = key_combos(sequence),
+            callback_id = #CallbackTable,
+            mode = opts.mode,
+        },
+    })
+end
+
+---Remove a key sequence you set earlier.
+---@param sequence KeyCombo[] The key combos of the sequence.
+---@param mode string? The bind mode of the key sequence. Defaults to "default".
+function input_module.remove_key_sequence(sequence, mode)
+    SendMsg({
+        RemoveKeySequence = {
+            sequence = key_combos(sequence),
+            mode = mode,
+        },
+    })
+end
+
+---Set how long to wait for the next key of a key sequence before cancelling it.
+---
+---This defaults to 1000 milliseconds.
+---@param timeout_ms integer
+function input_module.set_key_sequence_timeout(timeout_ms)
+    SendMsg({
+        SetKeySequenceTimeout = {
+            timeout_ms = timeout_ms,
+        },
+    })
+end
+
+---Set the key combo that lets keybinds work again while a window that inhibits keyboard
+---shortcuts is focused, or unset it with `nil`.
+---
+---Remote desktop clients and virtual machines can ask for every key while they're focused,
+---so keybinds don't work in them. Pressing this combo turns that off for the focused window,
+---and pressing it again turns it back on. It's `Super+Escape` by default.
+---
+---```lua
+---input.set_shortcuts_inhibit_escape({ { "Super" }, input.keys.Escape })
+---```
+---@param combo KeyCombo?
+function input_module.set_shortcuts_inhibit_escape(combo)
+    SendMsg({
+        SetShortcutsInhibitEscape = {
+            combo = combo and { modifiers = combo[1], key = key_int_or_string(combo[2]) },
+        },
+    })
+end
+
+---Switch to a bind mode.
+---
+---While in a mode other than "default", only keybinds, mousebinds, and gesture binds set for
+---that mode work, and keys that don't match one are not sent to windows. Pressing `Escape` goes back to "default" unless
+---the mode binds `Escape` itself.
+---
+---```lua
+---input.keybind({ "Super" }, "r", function()
+---    input.set_bind_mode("resize")
+---end)
+---input.keybind({}, "Return", function()
+---    input.set_bind_mode("default")
+---end, { mode = "resize" })
+---```
+---@param mode string
+function input_module.set_bind_mode(mode)
+    SendMsg({
+        SetBindMode = {
+            mode = mode,
+        },
+    })
+end
+
+---Get the current bind mode.
+---@return string
+function input_module.bind_mode()
+    local response = Request("GetBindMode")
+    return response.RequestResponse.response.BindMode.mode
+end
+
+---@class XkbConfig
+---@field rules string?
+---@field model string?
+---@field layout string? A comma separated list of layouts, like "us,de".
+---@field variant string? A comma separated list of variants, one for each layout.
+---@field options string? Comma separated xkb options, like "caps:escape".
+
+---Set the keyboard's layouts and options. Unset fields use xkb's defaults.
+---
+---```lua
+---input.set_xkb_config({ layout = "us,de", options = "caps:escape" })
+---```
+---@param config XkbConfig
+function input_module.set_xkb_config(config)
+    SendMsg({
+        SetXkbConfig = {
+            rules = config.rules,
+            model = config.model,
+            layout = config.layout,
+            variant = config.variant,
+            options = config.options,
+        },
+    })
+end
+
+---Set how long a key must be held before it repeats, and how fast it repeats after that.
+---
+---These default to 200 milliseconds and 25 repeats per second. A rate of 0 turns key repeat off.
+---@param rate integer Repeats per second.
+---@param delay integer The delay in milliseconds.
+function input_module.set_repeat_rate(rate, delay)
+    SendMsg({
+        SetRepeatRate = {
+            rate = rate,
+            delay = delay,
+        },
+    })
+end
+
+---Switch to another of the layouts set with `input.set_xkb_config`.
+---
+---```lua
+---input.keybind({ "Super" }, "space", function()
+---    input.switch_xkb_layout("next")
+---end)
+---```
+---@param layout "next"|"prev"|integer The layout after or before the active one, or the index of one starting at 1.
+function input_module.switch_xkb_layout(layout)
+    local switch
+    if layout == "next" then
+        switch = "Next"
+    elseif layout == "prev" then
+        switch = "Prev"
+    else
+        switch = { Index = layout - 1 }
+    end
+
+    SendMsg({
+        SwitchXkbLayout = {
+            layout = switch,
         },
     })
 end
 
+---Get the active keyboard layout.
+---@return integer index The index of the layout, starting at 1.
+---@return string name The layout's name, like "English (US)".
+function input_module.xkb_layout()
+    local response = Request("GetXkbLayout")
+    local layout = response.RequestResponse.response.XkbLayout
+    return layout.index + 1, layout.name
+end
+
+---@alias InputDeviceType
+---| "Keyboard"
+---| "Pointer" # A mouse or anything else that moves the pointer, except touchpads.
+---| "Touchpad"
+---| "Touch" # A touchscreen.
+---| "Tablet" # A drawing tablet or its pad.
+
+---@class LibinputSettings
+---@field tap boolean? Tap the touchpad to click.
+---@field natural_scroll boolean?
+---@field accel_profile ("Flat"|"Adaptive")?
+---@field accel_speed number? The pointer acceleration speed, from -1.0 to 1.0.
+---@field left_handed boolean? Swap the left and right buttons.
+---@field disable_while_typing boolean? Disable the touchpad while typing.
+---@field scroll_method ("NoScroll"|"TwoFinger"|"Edge"|"OnButtonDown")?
+---@field click_method ("ButtonAreas"|"Clickfinger")?
+
+---@class InputDeviceFilter
+---@field name string? Only apply to devices with this name. See `input.input_devices`.
+---@field type InputDeviceType? Only apply to devices of this type.
+
+---The settings in `LibinputSettings`, along with their names in messages.
+local libinput_setting_names = {
+    { "tap", "Tap" },
+    { "natural_scroll", "NaturalScroll" },
+    { "accel_profile", "AccelProfile" },
+    { "accel_speed", "AccelSpeed" },
+    { "left_handed", "LeftHanded" },
+    { "disable_while_typing", "DisableWhileTyping" },
+    { "scroll_method", "ScrollMethod" },
+    { "click_method", "ClickMethod" },
+}
+
+---Set libinput settings for devices like touchpads and mice.
+---
+---Settings apply to matching devices now and whenever they're plugged in. Devices that don't
+---have a setting ignore it.
+---
+---```lua
+---input.set_libinput_settings({ tap = true, natural_scroll = true }, { type = "Touchpad" })
+---input.set_libinput_settings({ accel_profile = "Flat" })
+---```
+---@param settings LibinputSettings
+---@param devices InputDeviceFilter? Which devices to apply the settings to. Defaults to all of them.
+function input_module.set_libinput_settings(settings, devices)
+    devices = devices or {}
+
+    for _, names in pairs(libinput_setting_names) do
+        local value = settings[names[1]]
+        if value ~= nil then
+            SendMsg({
+                SetLibinputSetting = {
+                    setting = { [names[2]] = value },
+                    device_name = devices.name,
+                    device_type = devices.type,
+                },
+            })
+        end
+    end
+end
+
+---Get all input devices that are plugged in.
+---
+---This is only filled in when running Pinnacle from a TTY.
+---@return { name: string, types: InputDeviceType[] }[]
+function input_module.input_devices()
+    local response = Request("GetInputDevices")
+    return response.RequestResponse.response.InputDevices.devices
+end
+
+---Map touchscreens to an output.
+---
+---Touches on a touchscreen land on the output it's mapped to. Without a device name, this maps
+---all touchscreens that aren't mapped by name. Unmapped touchscreens are mapped to the first output.
+---
+---```lua
+---input.set_touch_output("eDP-1")
+---input.set_touch_output("HDMI-A-1", "Some Touchscreen")
+---input.set_touch_output(nil, "Some Touchscreen") -- Unmap it
+---```
+---@param output_name string? The output to map to, or `nil` to unmap.
+---@param device_name string? The touchscreen to map, from `input.input_devices()`.
+function input_module.set_touch_output(output_name, device_name)
+    SendMsg({
+        SetTouchOutput = {
+            output_name = output_name,
+            device_name = device_name,
+        },
+    })
+end
+
+---Get which outputs touchscreens are mapped to.
+---
+---A mapping without a `device_name` is the one for touchscreens without their own.
+---@return { device_name: string?, output_name: string }[]
+function input_module.touch_outputs()
+    local response = Request("GetTouchOutputs")
+    return response.RequestResponse.response.TouchOutputs.mappings
+end
+
+---Map drawing tablets to an output.
+---
+---This works like `input.set_touch_output`.
+---
+---```lua
+---input.set_tablet_output("DP-1", "Wacom Intuos S Pen")
+---```
+---@param output_name string? The output to map to, or `nil` to unmap.
+---@param device_name string? The tablet to map, from `input.input_devices()`.
+function input_module.set_tablet_output(output_name, device_name)
+    SendMsg({
+        SetTabletOutput = {
+            output_name = output_name,
+            device_name = device_name,
+        },
+    })
+end
+
+---Get which outputs drawing tablets are mapped to.
+---
+---This works like `input.touch_outputs`.
+---@return { device_name: string?, output_name: string }[]
+function input_module.tablet_outputs()
+    local response = Request("GetTabletOutputs")
+    return response.RequestResponse.response.TabletOutputs.mappings
+end
+
 ---Set a mousebind. If called with an already existing mousebind, it gets replaced.
 ---
 ---The mousebind can happen either on button press or release, so you must specify
@@ -100,9 +526,15 @@ end
 ---@param modifiers (Modifier)[] The modifiers that need to be held for the mousebind to trigger.
 ---@param button MouseButton The button that needs to be pressed or released.
 ---@param edge "Press"|"Release" Whether or not to trigger `action` on button press or release.
----@param action fun() The function to run.
-function input_module.mousebind(modifiers, button, edge, action)
-    table.insert(CallbackTable, action)
+---@param action fun(args: MousebindArgs) The function to run. It's called with where the pointer was and what's under it.
+---@param opts { mode: string? }? The bind mode the mousebind works in, like for `input.keybind`.
+function input_module.mousebind(modifiers, button, edge, action, opts)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        action(pointer_bind_args(args.Mousebind or {}))
+    end)
+
+    opts = opts or {}
 
     SendMsg({
         SetMousebind = {
@@ -110,8 +542,158 @@ function input_module.mousebind(modifiers, button, edge, action)
             button = button,
             edge = edge,
             callback_id = #CallbackTable,
+            mode = opts.mode,
         },
     })
 end
 
+---Remove a mousebind you set earlier.
+---
+---Mousebinds set by other clients can't be removed.
+---
+---@param modifiers (Modifier)[] The modifiers of the mousebind.
+---@param button MouseButton The button of the mousebind.
+---@param edge "Press"|"Release" The edge of the mousebind.
+---@param opts { mode: string? }? The `mode` the mousebind was set with.
+function input_module.remove_mousebind(modifiers, button, edge, opts)
+    opts = opts or {}
+
+    SendMsg({
+        RemoveMousebind = {
+            modifiers = modifiers,
+            button = button,
+            edge = edge,
+            mode = opts.mode,
+        },
+    })
+end
+
+---@alias GestureName
+---| "swipe_up"
+---| "swipe_down"
+---| "swipe_left"
+---| "swipe_right"
+---| "pinch_in" # Move fingers closer together.
+---| "pinch_out" # Spread fingers apart.
+---| "hold" # Rest fingers on the touchpad without moving them.
+
+---The gestures in `GestureName`, as they're sent in messages.
+local gestures = {
+    swipe_up = { Swipe = "Up" },
+    swipe_down = { Swipe = "Down" },
+    swipe_left = { Swipe = "Left" },
+    swipe_right = { Swipe = "Right" },
+    pinch_in = { Pinch = "In" },
+    pinch_out = { Pinch = "Out" },
+    hold = "Hold",
+}
+
+---Set a touchpad gesture bind. If called with an already existing gesture bind, it gets replaced.
+---
+---Once a gesture is bound, gestures of the same kind with the same number of fingers aren't
+---sent to windows anymore.
+---
+---```lua
+---input.gesture_bind({}, "swipe_left", 3, function(args)
+---    -- Switch to the next tag
+---end)
+---```
+---@param modifiers (Modifier)[] The modifiers that need to be held for the gesture bind to trigger.
+---@param gesture GestureName
+---@param fingers integer How many fingers the gesture is done with.
+---@param action fun(args: GestureArgs) The function to run. It's called with where the pointer was and what's under it.
+---@param opts { mode: string? }? The bind mode the gesture bind works in, like for `input.keybind`.
+function input_module.gesture_bind(modifiers, gesture, fingers, action, opts)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        action(pointer_bind_args(args.Gesture or {}))
+    end)
+
+    opts = opts or {}
+
+    SendMsg({
+        SetGestureBind = {
+            gesture = gestures[gesture],
+            fingers = fingers,
+            modifiers = modifiers,
+            callback_id = #CallbackTable,
+            mode = opts.mode,
+        },
+    })
+end
+
+---Remove a gesture bind you set earlier.
+---
+---Gesture binds set by other clients can't be removed.
+---
+---@param modifiers (Modifier)[] The modifiers of the gesture bind.
+---@param gesture GestureName
+---@param fingers integer
+---@param opts { mode: string? }? The `mode` the gesture bind was set with.
+function input_module.remove_gesture_bind(modifiers, gesture, fingers, opts)
+    opts = opts or {}
+
+    SendMsg({
+        RemoveGestureBind = {
+            gesture = gestures[gesture],
+            fingers = fingers,
+            modifiers = modifiers,
+            mode = opts.mode,
+        },
+    })
+end
+
+---Get all keybinds currently set, including ones set by other clients.
+---
+---Each keybind has its bind `mode`, its `modifiers`, its `key` as an integer keysym,
+---`key_name`, the name of the key like "Return" or "a", and its `edge` and `allow_repeat`.
+---
+---```lua
+---for _, bind in pairs(input.keybinds()) do
+---    print(table.concat(bind.modifiers, " + ") .. " + " .. bind.key_name)
+---end
+---```
+---@return { mode: string, modifiers: Modifier[], key: Keys, key_name: string, edge: "Press"|"Release", allow_repeat: boolean }[]
+function input_module.keybinds()
+    local response = Request("GetKeybinds")
+    local keybinds = response.RequestResponse.response.Keybinds.keybinds
+
+    ---@type { mode: string, modifiers: Modifier[], key: Keys, key_name: string, edge: "Press"|"Release", allow_repeat: boolean }[]
+    local ret = {}
+
+    for _, bind in pairs(keybinds) do
+        table.insert(ret, {
+            mode = bind.mode,
+            modifiers = bind.modifiers,
+            key = bind.key,
+            key_name = bind.key_name,
+            edge = bind.edge,
+            allow_repeat = bind.allow_repeat,
+        })
+    end
+
+    return ret
+end
+
+---Get all mousebinds currently set, including ones set by other clients.
+---@return { mode: string, modifiers: Modifier[], button: MouseButton, edge: "Press"|"Release" }[]
+function input_module.mousebinds()
+    local response = Request("GetMousebinds")
+    local mousebinds = response.RequestResponse.response.Mousebinds.mousebinds
+
+    ---@type { mode: string, modifiers: Modifier[], button: MouseButton, edge: "Press"|"Release" }[]
+    local ret = {}
+
+    for _, bind in pairs(mousebinds) do
+        table.insert(ret, {
+            mode = bind.mode,
+            modifiers = bind.modifiers,
+            button = bind.button,
+            edge = bind.edge,
+        })
+    end
+
+    return ret
+end
+
 return input_module
@@ -3,8 +3,24 @@
 ---@meta _
 
 ---@class _Msg
----@field SetKeybind { key: { Int: Keys?, String: string? }, modifiers: Modifier[], callback_id: integer }?
----@field SetMousebind { modifiers: (Modifier)[], button: integer, edge: "Press"|"Release", callback_id: integer }?
+---@field Hello { protocol_version: integer, config_token: string? }?
+---@field SetKeybind { key: { Int: Keys?, String: string? }, modifiers: Modifier[], callback_id: integer, mode: string?, edge: "Press"|"Release"|nil, allow_repeat: boolean? }?
+---@field SetMousebind { modifiers: (Modifier)[], button: integer, edge: "Press"|"Release", callback_id: integer, mode: string? }?
+---@field RemoveKeybind { key: { Int: Keys?, String: string? }, modifiers: Modifier[], mode: string?, edge: "Press"|"Release"|nil }?
+---@field RemoveMousebind { modifiers: (Modifier)[], button: integer, edge: "Press"|"Release", mode: string? }?
+---@field SetGestureBind { gesture: _Gesture, fingers: integer, modifiers: Modifier[], callback_id: integer, mode: string? }?
+---@field RemoveGestureBind { gesture: _Gesture, fingers: integer, modifiers: Modifier[], mode: string? }?
+---@field SetKeySequence { sequence: { modifiers: Modifier[], key: { Int: Keys?, String: string? } }[], callback_id: integer, mode: string? }?
+---@field RemoveKeySequence { sequence: { modifiers: Modifier[], key: { Int: Keys?, String: string? } }[], mode: string? }?
+---@field SetKeySequenceTimeout { timeout_ms: integer }?
+---@field SetShortcutsInhibitEscape { combo: { modifiers: Modifier[], key: { Int: Keys?, String: string? } }? }?
+---@field SetBindMode { mode: string }?
+---@field SetXkbConfig { rules: string?, model: string?, layout: string?, variant: string?, options: string? }?
+---@field SetRepeatRate { rate: integer, delay: integer }?
+---@field SwitchXkbLayout { layout: "Next"|"Prev"|{ Index: integer } }?
+---@field SetLibinputSetting { setting: table<string, boolean|number|string>, device_name: string?, device_type: InputDeviceType? }?
+---@field SetTouchOutput { output_name: string?, device_name: string? }?
+---@field SetTabletOutput { output_name: string?, device_name: string? }?
 --Windows
 ---@field CloseWindow { window_id: WindowId }?
 ---@field SetWindowSize { window_id: WindowId, width: integer?, height: integer? }?
@@ -29,14 +45,19 @@
 --Outputs
 ---@field ConnectForAllOutputs { callback_id: integer }?
 ---@field SetOutputLocation { output_name: OutputName, x: integer?, y: integer? }?
+--Events
+---@field Subscribe { events: EventKind[] }?
+---@field Unsubscribe { events: EventKind[] }?
 
----@alias Msg _Msg | "Quit"
+---@alias Msg _Msg | "Quit" | "ConfigReady"
 
 ---@alias FullscreenOrMaximized
 ---| "Neither"
 ---| "Fullscreen"
 ---| "Maximized"
 
+---@alias _Gesture { Swipe: "Up"|"Down"|"Left"|"Right" } | { Pinch: "In"|"Out" } | "Hold"
+
 --------------------------------------------------------------------------------------------
 
 ---@class __Request
@@ -47,16 +68,35 @@
 --Tags
 ---@field GetTagProps { tag_id: TagId }?
 
----@alias _Request __Request | "GetWindows" | "GetOutputs" | "GetTags"
+---@alias _Request __Request | "GetKeybinds" | "GetMousebinds" | "GetBindMode" | "GetXkbLayout" | "GetInputDevices" | "GetTouchOutputs" | "GetTabletOutputs" | "GetWindows" | "GetOutputs" | "GetTags"
 ---@alias Request { request_id: integer, request: _Request }
 
 ---@class IncomingMsg
+---@field Hello { protocol_version: integer, features: string[] }?
 ---@field CallCallback { callback_id: integer, args: Args? }?
 ---@field RequestResponse { request_id: integer, response: RequestResponse }?
+---@field Event { event: Event }?
+---@field Error { msg_kind: string, reason: string, callback_id: integer? }?
 
 ---@class Args
 ---@field Spawn { stdout: string?, stderr: string?, exit_code: integer?, exit_msg: string? }?
 ---@field ConnectForAllOutputs { output_name: string }?
+---@field Keybind { key: integer, modifiers: Modifier[], window_id: WindowId? }?
+---@field Mousebind { pointer_loc: number[], window_id: WindowId?, output_name: OutputName? }?
+---@field Gesture { pointer_loc: number[], window_id: WindowId?, output_name: OutputName? }?
+
+---@class Event
+---@field WindowOpened { window_id: WindowId }?
+---@field WindowClosed { window_id: WindowId }?
+---@field FocusChanged { window_id: WindowId? }?
+---@field TitleChanged { window_id: WindowId, title: string? }?
+---@field TagAdded { tag_id: TagId, output_name: OutputName }?
+---@field TagRemoved { tag_id: TagId }?
+---@field TagActivated { tag_id: TagId, active: boolean }?
+---@field LayoutChanged { tag_id: TagId, layout: Layout }?
+---@field OutputConnected { output_name: OutputName }?
+---@field OutputDisconnected { output_name: OutputName }?
+---@field BindModeChanged { mode: string }?
 
 ---@alias WindowId integer
 ---@alias TagId integer
@@ -64,6 +104,15 @@
 ---@alias OutputName string
 
 ---@class RequestResponse
+---@field Error { reason: string }?
+--Input
+---@field Keybinds { keybinds: { mode: string, modifiers: Modifier[], key: integer, key_name: string, edge: "Press"|"Release", allow_repeat: boolean, callback_id: integer? }[] }?
+---@field Mousebinds { mousebinds: { mode: string, modifiers: Modifier[], button: integer, edge: "Press"|"Release", callback_id: integer? }[] }?
+---@field BindMode { mode: string }?
+---@field XkbLayout { index: integer, name: string }?
+---@field InputDevices { devices: { name: string, types: InputDeviceType[] }[] }?
+---@field TouchOutputs { mappings: { device_name: string?, output_name: string }[] }?
+---@field TabletOutputs { mappings: { device_name: string?, output_name: string }[] }?
 --Windows
 ---@field Window { window_id: WindowId|nil }?
 ---@field Windows { window_ids: WindowId[] }?
@@ -45,6 +45,14 @@ local function create_output(name)
     return o
 end
 
+---@nodoc
+---Create an output object from a name Pinnacle sent, without asking Pinnacle whether it exists.
+---@param name string
+---@return Output
+function output_module._from_name(name)
+    return create_output(name)
+end
+
 ---Get this output's name. This is something like "eDP-1" or "HDMI-A-0".
 ---@return string
 function output:name()
@@ -4,6 +4,8 @@ local socket = require("posix.sys.socket")
 local msgpack = require("msgpack")
 
 local SOCKET_PATH = os.getenv("PINNACLE_SOCKET") or "/tmp/pinnacle_socket"
+---The protocol version this API speaks. This must match Pinnacle's.
+local PROTOCOL_VERSION = 2
 
 ---@nodoc
 ---From https://gist.github.com/stuby/5445834#file-rprint-lua
@@ -85,6 +87,8 @@ local pinnacle = {
     tag = require("tag"),
     ---Output management
     output = require("output"),
+    ---Events
+    event = require("event"),
 }
 
 ---Quit Pinnacle.
@@ -119,7 +123,7 @@ function pinnacle.setup(config_func)
         assert(encoded)
         -- print(encoded)
         local len = encoded:len()
-        socket.send(socket_fd, string.pack("=I4", len))
+        socket.send(socket_fd, string.pack("<I4", len))
         socket.send(socket_fd, encoded)
     end
 
@@ -134,8 +138,9 @@ function pinnacle.setup(config_func)
 
     ---@type table<integer, IncomingMsg>
     local unread_req_msgs = {}
-    ---@type table<integer, IncomingMsg>
-    local unread_cb_msgs = {}
+    ---Callbacks and events that arrived while waiting for a request response, in order.
+    ---@type IncomingMsg[]
+    local unread_msgs = {}
 
     ---This is an internal global function used to send requests to the Pinnacle server for information.
     ---@param data _Request
@@ -148,7 +153,17 @@ function pinnacle.setup(config_func)
                 request = data,
             },
         })
-        return ReadMsg(req_id)
+        local response = ReadMsg(req_id)
+        if response.RequestResponse.response.Error then
+            print("Pinnacle couldn't answer a request: " .. response.RequestResponse.response.Error.reason)
+            -- Callers index straight into the response they asked for, so give them empty fields
+            response.RequestResponse.response = setmetatable({}, {
+                __index = function()
+                    return {}
+                end,
+            })
+        end
+        return response
     end
 
     ---This is an internal global function used to read messages sent from the server.
@@ -171,7 +186,7 @@ function pinnacle.setup(config_func)
             -- TODO: break here if error in read_exact
 
             ---@type integer
-            local msg_len = string.unpack("=I4", msg_len_bytes)
+            local msg_len = string.unpack("<I4", msg_len_bytes)
             -- print(msg_len)
 
             local msg_bytes, err_msg2, err_num2 = read_exact(socket_fd, msg_len)
@@ -183,8 +198,8 @@ function pinnacle.setup(config_func)
             -- print(msg_bytes)
 
             if req_id then
-                if inc_msg.CallCallback then
-                    unread_cb_msgs[inc_msg.CallCallback.callback_id] = inc_msg
+                if inc_msg.RequestResponse == nil then
+                    table.insert(unread_msgs, inc_msg)
                 elseif inc_msg.RequestResponse.request_id ~= req_id then
                     unread_req_msgs[inc_msg.RequestResponse.request_id] =
                         inc_msg
@@ -197,29 +212,48 @@ function pinnacle.setup(config_func)
         end
     end
 
-    config_func(pinnacle)
-
-    while true do
-        for cb_id, inc_msg in pairs(unread_cb_msgs) do
+    ---Run the callback or event handlers for an incoming message.
+    ---@param inc_msg IncomingMsg
+    local function handle_msg(inc_msg)
+        if inc_msg.CallCallback and inc_msg.CallCallback.callback_id then
             CallbackTable[inc_msg.CallCallback.callback_id](
                 inc_msg.CallCallback.args
             )
-            unread_cb_msgs[cb_id] = nil -- INFO: does this shift the table and frick everything up?
+        elseif inc_msg.Event then
+            pinnacle.event._dispatch(inc_msg.Event.event)
+        elseif inc_msg.Error then
+            print(
+                "Pinnacle couldn't handle "
+                    .. inc_msg.Error.msg_kind
+                    .. ": "
+                    .. inc_msg.Error.reason
+            )
         end
+    end
 
-        local inc_msg = ReadMsg()
+    SendMsg({
+        Hello = {
+            protocol_version = PROTOCOL_VERSION,
+            -- Tells Pinnacle this is the config and not some other client
+            config_token = os.getenv("PINNACLE_CONFIG_TOKEN"),
+        },
+    })
+    local hello = ReadMsg()
+    if hello.Error then
+        error("Pinnacle rejected the connection: " .. hello.Error.reason)
+    end
 
-        assert(inc_msg.CallCallback) -- INFO: is this gucci or no
+    config_func(pinnacle)
 
-        if inc_msg.CallCallback and inc_msg.CallCallback.callback_id then
-            if inc_msg.CallCallback.args then -- TODO: can just inline
-                CallbackTable[inc_msg.CallCallback.callback_id](
-                    inc_msg.CallCallback.args
-                )
-            else
-                CallbackTable[inc_msg.CallCallback.callback_id](nil)
-            end
+    -- Until this is sent, Pinnacle keeps the old config's binds and tags around
+    SendMsg("ConfigReady")
+
+    while true do
+        while #unread_msgs > 0 do
+            handle_msg(table.remove(unread_msgs, 1))
         end
+
+        handle_msg(ReadMsg())
     end
 end
 
@@ -394,6 +394,16 @@ function tag_module.get_by_name(name)
     return tags
 end
 
+---Get a tag from its id.
+---
+---This doesn't check whether the tag still exists.
+---***You probably won't need to use this.***
+---@param tag_id TagId
+---@return Tag
+function tag_module.get_by_id(tag_id)
+    return create_tag(tag_id)
+end
+
 ---Get all tags across all outputs.
 ---
 ---### Example
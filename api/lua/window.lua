@@ -187,6 +187,16 @@ end
 
 -------------------------------------------------------------------
 
+---Get a window from its id.
+---
+---This doesn't check whether the window still exists.
+---***You will probably not need to use this.***
+---@param window_id WindowId
+---@return Window
+function window_module.get_by_id(window_id)
+    return create_window(window_id)
+end
+
 ---Get all windows with the specified class (usually the name of the application).
 ---@param class string The class. For example, Alacritty's class is "Alacritty".
 ---@return Window[]
@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: MIT
+
+use pinnacle_api::{
+    input::{self, keys, MouseButton},
+    output, process, tag, window, Layout, Modifier, MouseEdge,
+};
+
+fn main() -> Result<(), pinnacle_api::Error> {
+    pinnacle_api::setup(|| {
+        // This is set to `Ctrl` instead of `Super` to not conflict with your WM/DE keybinds
+        let mod_key = Modifier::Ctrl;
+
+        let terminal = "alacritty";
+
+        process::set_env("MOZ_ENABLE_WAYLAND", "1");
+
+        // Mousebinds --------------------------------------------------------------------
+
+        input::mousebind(&[mod_key], MouseButton::Left, MouseEdge::Press, |_| {
+            window::begin_move(MouseButton::Left);
+        });
+        input::mousebind(&[mod_key], MouseButton::Right, MouseEdge::Press, |_| {
+            window::begin_resize(MouseButton::Right);
+        });
+
+        // Keybinds ----------------------------------------------------------------------
+
+        // mod_key + Alt + q quits the compositor
+        input::keybind(&[mod_key, Modifier::Alt], 'q', pinnacle_api::quit);
+
+        // mod_key + Alt + c closes the focused window
+        input::keybind(&[mod_key, Modifier::Alt], 'c', || {
+            if let Some(window) = window::get_focused() {
+                window.close();
+            }
+        });
+
+        // mod_key + Return spawns a terminal
+        input::keybind(&[mod_key], keys::KEY_Return, move || {
+            process::spawn_with_callback([terminal], |_stdout, _stderr, _exit_code, _exit_msg| {
+                // do something with the output here
+            });
+        });
+
+        // mod_key + Alt + Space toggles floating on the focused window
+        input::keybind(&[mod_key, Modifier::Alt], keys::KEY_space, || {
+            if let Some(window) = window::get_focused() {
+                window.toggle_floating();
+            }
+        });
+
+        // mod_key + f toggles fullscreen on the focused window
+        input::keybind(&[mod_key], 'f', || {
+            if let Some(window) = window::get_focused() {
+                window.toggle_fullscreen();
+            }
+        });
+
+        // mod_key + m toggles maximized on the focused window
+        input::keybind(&[mod_key], 'm', || {
+            if let Some(window) = window::get_focused() {
+                window.toggle_maximized();
+            }
+        });
+
+        // Tags ---------------------------------------------------------------------------
+
+        let tags = ["1", "2", "3", "4", "5"];
+
+        output::connect_for_all(move |output| {
+            // Add tags 1, 2, 3, 4 and 5 on all monitors, and toggle tag 1 active by default
+            output.add_tags(&tags);
+            if let Some(tag) = tag::get_on_output("1", &output) {
+                tag.toggle();
+            }
+        });
+
+        // Layout cycling
+
+        let layout_cycler = tag::LayoutCycler::new(&[
+            Layout::MasterStack,
+            Layout::Dwindle,
+            Layout::Spiral,
+            Layout::CornerTopLeft,
+            Layout::CornerTopRight,
+            Layout::CornerBottomLeft,
+            Layout::CornerBottomRight,
+        ]);
+
+        let next = layout_cycler.clone();
+        input::keybind(&[mod_key], keys::KEY_space, move || next.next(None));
+        input::keybind(&[mod_key, Modifier::Shift], keys::KEY_space, move || {
+            layout_cycler.prev(None)
+        });
+
+        // Tag manipulation
+
+        for tag_name in tags {
+            // mod_key + 1-5 switches tags
+            input::keybind(&[mod_key], tag_name, move || {
+                if let Some(tag) = tag::get(tag_name) {
+                    tag.switch_to();
+                }
+            });
+            // mod_key + Shift + 1-5 toggles tags
+            input::keybind(&[mod_key, Modifier::Shift], tag_name, move || {
+                if let Some(tag) = tag::get(tag_name) {
+                    tag.toggle();
+                }
+            });
+            // mod_key + Alt + 1-5 moves windows to tags
+            input::keybind(&[mod_key, Modifier::Alt], tag_name, move || {
+                if let (Some(window), Some(tag)) = (window::get_focused(), tag::get(tag_name)) {
+                    window.move_to_tag(&tag);
+                }
+            });
+            // mod_key + Shift + Alt + 1-5 toggles tags on windows
+            input::keybind(&[mod_key, Modifier::Shift, Modifier::Alt], tag_name, move || {
+                if let (Some(window), Some(tag)) = (window::get_focused(), tag::get(tag_name)) {
+                    window.toggle_tag(&tag);
+                }
+            });
+        }
+    })
+}
@@ -0,0 +1,163 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Events.
+//!
+//! This module lets you run functions when things happen in Pinnacle, like a window opening
+//! or a tag being switched to, without having to poll for changes.
+
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::{msg::Msg, output::Output, tag::Tag, window::Window, EventKind, Layout};
+
+/// Something that happened in Pinnacle.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A window opened.
+    WindowOpened(Window),
+    /// A window closed.
+    WindowClosed(Window),
+    /// Keyboard focus moved to a window, or to something that isn't a window.
+    FocusChanged(Option<Window>),
+    /// A window's title changed.
+    TitleChanged {
+        window: Window,
+        title: Option<String>,
+    },
+    /// A tag was added to an output.
+    TagAdded { tag: Tag, output: Output },
+    /// A tag was removed.
+    TagRemoved(Tag),
+    /// A tag was activated or deactivated.
+    TagActivated { tag: Tag, active: bool },
+    /// A tag's layout changed.
+    LayoutChanged { tag: Tag, layout: Layout },
+    /// An output was connected.
+    ///
+    /// This is also sent for every output that was already connected when the first handler
+    /// was connected.
+    OutputConnected(Output),
+    /// An output was disconnected.
+    OutputDisconnected(Output),
+    /// The bind mode changed to the contained one.
+    BindModeChanged(String),
+}
+
+impl From<crate::msg::Event> for Event {
+    fn from(event: crate::msg::Event) -> Self {
+        use crate::msg::Event as RawEvent;
+
+        match event {
+            RawEvent::WindowOpened { window_id } => Event::WindowOpened(Window(window_id)),
+            RawEvent::WindowClosed { window_id } => Event::WindowClosed(Window(window_id)),
+            RawEvent::FocusChanged { window_id } => Event::FocusChanged(window_id.map(Window)),
+            RawEvent::TitleChanged { window_id, title } => Event::TitleChanged {
+                window: Window(window_id),
+                title,
+            },
+            RawEvent::TagAdded {
+                tag_id,
+                output_name,
+            } => Event::TagAdded {
+                tag: Tag(tag_id),
+                output: Output(output_name),
+            },
+            RawEvent::TagRemoved { tag_id } => Event::TagRemoved(Tag(tag_id)),
+            RawEvent::TagActivated { tag_id, active } => Event::TagActivated {
+                tag: Tag(tag_id),
+                active,
+            },
+            RawEvent::LayoutChanged { tag_id, layout } => Event::LayoutChanged {
+                tag: Tag(tag_id),
+                layout,
+            },
+            RawEvent::OutputConnected { output_name } => {
+                Event::OutputConnected(Output(output_name))
+            }
+            RawEvent::OutputDisconnected { output_name } => {
+                Event::OutputDisconnected(Output(output_name))
+            }
+            RawEvent::BindModeChanged { mode } => Event::BindModeChanged(mode),
+        }
+    }
+}
+
+impl Event {
+    /// The kind of this event.
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::WindowOpened(_) => EventKind::WindowOpened,
+            Event::WindowClosed(_) => EventKind::WindowClosed,
+            Event::FocusChanged(_) => EventKind::FocusChanged,
+            Event::TitleChanged { .. } => EventKind::TitleChanged,
+            Event::TagAdded { .. } => EventKind::TagAdded,
+            Event::TagRemoved(_) => EventKind::TagRemoved,
+            Event::TagActivated { .. } => EventKind::TagActivated,
+            Event::LayoutChanged { .. } => EventKind::LayoutChanged,
+            Event::OutputConnected(_) => EventKind::OutputConnected,
+            Event::OutputDisconnected(_) => EventKind::OutputDisconnected,
+            Event::BindModeChanged(_) => EventKind::BindModeChanged,
+        }
+    }
+}
+
+type Handler = Box<dyn FnMut(&Event)>;
+
+thread_local! {
+    static HANDLERS: RefCell<HashMap<EventKind, Vec<Handler>>> = RefCell::new(HashMap::new());
+}
+
+/// Run `handler` every time an event of the given kind happens.
+///
+/// ### Example
+/// ```no_run
+/// # use pinnacle_api::{event::{self, Event}, EventKind};
+/// event::connect(EventKind::WindowOpened, |event| {
+///     if let Event::WindowOpened(window) = event {
+///         println!("Opened {:?}", window.class());
+///     }
+/// });
+/// ```
+pub fn connect(kind: EventKind, handler: impl FnMut(&Event) + 'static) {
+    let first = HANDLERS.with(|handlers| {
+        let mut handlers = handlers.borrow_mut();
+        let handlers = handlers.entry(kind).or_default();
+        handlers.push(Box::new(handler));
+        handlers.len() == 1
+    });
+
+    if first {
+        crate::send_msg(Msg::Subscribe { events: vec![kind] });
+    }
+}
+
+/// Stop running all handlers connected to events of the given kind.
+pub fn disconnect_all(kind: EventKind) {
+    let removed = HANDLERS.with(|handlers| handlers.borrow_mut().remove(&kind).is_some());
+
+    if removed {
+        crate::send_msg(Msg::Unsubscribe { events: vec![kind] });
+    }
+}
+
+/// Run the handlers for an event sent by Pinnacle.
+pub(crate) fn dispatch(event: crate::msg::Event) {
+    let event = Event::from(event);
+    let kind = event.kind();
+
+    // Take the handlers out while they run so they can connect new ones
+    let Some(mut running) = HANDLERS.with(|handlers| handlers.borrow_mut().remove(&kind)) else {
+        return;
+    };
+
+    for handler in running.iter_mut() {
+        handler(&event);
+    }
+
+    HANDLERS.with(|handlers| {
+        let mut handlers = handlers.borrow_mut();
+        // Handlers connected while running go after the existing ones
+        let added = handlers.remove(&kind).unwrap_or_default();
+        running.extend(added);
+        handlers.insert(kind, running);
+    });
+}
@@ -0,0 +1,793 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Key and mouse binds.
+
+use crate::{
+    msg::{Args, CallbackId, KeyIntOrString, Msg, Request, RequestResponse},
+    output::Output,
+    window::Window,
+    Gesture, InputDeviceType, KeyEdge, LibinputSetting, Modifier, MouseEdge, XkbLayoutSwitch,
+};
+
+/// Every key supported by xkbcommon, as `KEY_<name>` keysyms.
+pub use xkbcommon::xkb::keysyms as keys;
+
+/// A key that triggers a keybind.
+///
+/// This can be created from:
+/// - A keysym from [`keys`], like `keys::KEY_Return`,
+/// - A single character, like `'g'` or `'$'`, or
+/// - The name of an xkbcommon key without the `KEY_` prefix, like `"Return"`.
+///
+/// Note that `'a'` is different from `'A'`. Usually, it's best to use the unmodified key;
+/// a keybind for `Shift` + `'A'` won't work because `Shift` + `a` is what you actually press.
+#[derive(Debug, Clone)]
+pub struct Key(KeyIntOrString);
+
+impl From<u32> for Key {
+    fn from(keysym: u32) -> Self {
+        Self(KeyIntOrString::Int(keysym))
+    }
+}
+
+impl From<char> for Key {
+    fn from(ch: char) -> Self {
+        Self(KeyIntOrString::String(ch.to_string()))
+    }
+}
+
+impl From<&str> for Key {
+    fn from(name: &str) -> Self {
+        Self(KeyIntOrString::String(name.to_string()))
+    }
+}
+
+impl From<String> for Key {
+    fn from(name: String) -> Self {
+        Self(KeyIntOrString::String(name))
+    }
+}
+
+/// Modifiers and a key pressed together, as one step of a key sequence.
+#[derive(Debug, Clone)]
+pub struct KeyCombo {
+    modifiers: Vec<Modifier>,
+    key: Key,
+}
+
+impl KeyCombo {
+    /// Create a combo of `key` pressed while holding `modifiers`.
+    pub fn new(modifiers: &[Modifier], key: impl Into<Key>) -> Self {
+        Self {
+            modifiers: modifiers.to_vec(),
+            key: key.into(),
+        }
+    }
+}
+
+impl From<KeyCombo> for crate::msg::KeyCombo {
+    fn from(combo: KeyCombo) -> Self {
+        Self {
+            modifiers: combo.modifiers,
+            key: combo.key.0,
+        }
+    }
+}
+
+/// A mouse button.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum MouseButton {
+    Left = 0x110,
+    Right = 0x111,
+    Middle = 0x112,
+    Side = 0x113,
+    Extra = 0x114,
+    Forward = 0x115,
+    Back = 0x116,
+}
+
+/// The bind mode keybinds are in unless another one is set with [`set_bind_mode`].
+pub const DEFAULT_BIND_MODE: &str = "default";
+
+/// Extra settings for a keybind, used with [`keybind_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct KeybindOptions {
+    /// The bind mode the keybind works in. `None` means [`DEFAULT_BIND_MODE`].
+    pub mode: Option<String>,
+    /// Whether the keybind triggers on key press or release.
+    ///
+    /// Release keybinds match the modifiers that were held when the key was pressed.
+    pub edge: KeyEdge,
+    /// Whether to run the action again on every key repeat while the key is held.
+    /// This only applies to keybinds on [`KeyEdge::Press`].
+    pub allow_repeat: bool,
+}
+
+/// A keybind that is currently set, as returned by [`keybinds`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keybind {
+    /// The bind mode the keybind works in.
+    pub mode: String,
+    /// Whether the keybind triggers on key press or release.
+    pub edge: KeyEdge,
+    /// Whether the keybind runs again on every key repeat.
+    pub allow_repeat: bool,
+    /// The modifiers that need to be held.
+    pub modifiers: Vec<Modifier>,
+    /// The keysym that triggers the keybind.
+    pub key: u32,
+    /// The xkbcommon name of the key, like `"Return"` or `"a"`.
+    pub key_name: String,
+}
+
+/// A mousebind that is currently set, as returned by [`mousebinds`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mousebind {
+    /// The bind mode the mousebind works in.
+    pub mode: String,
+    /// The modifiers that need to be held.
+    pub modifiers: Vec<Modifier>,
+    /// The button code that triggers the mousebind.
+    pub button: u32,
+    /// Whether the mousebind triggers on press or release.
+    pub edge: MouseEdge,
+}
+
+/// What a keybind's action is called with, when set with [`keybind_with_options`].
+#[derive(Debug, Clone)]
+pub struct KeybindArgs {
+    /// The keysym that triggered the keybind.
+    pub key: u32,
+    /// The modifiers that were held.
+    pub modifiers: Vec<Modifier>,
+    /// The window with keyboard focus when the keybind triggered, if any.
+    pub window: Option<Window>,
+}
+
+/// What a mousebind's action is called with.
+#[derive(Debug, Clone)]
+pub struct MousebindArgs {
+    /// Where the pointer was, in global coordinates.
+    pub pointer_loc: (f64, f64),
+    /// The window under the pointer, if any.
+    pub window: Option<Window>,
+    /// The output under the pointer, if any.
+    pub output: Option<Output>,
+}
+
+/// What a gesture bind's action is called with.
+#[derive(Debug, Clone)]
+pub struct GestureArgs {
+    /// Where the pointer was, in global coordinates.
+    pub pointer_loc: (f64, f64),
+    /// The window under the pointer, if any.
+    pub window: Option<Window>,
+    /// The output under the pointer, if any.
+    pub output: Option<Output>,
+}
+
+/// Set a keybind. If called with an already existing keybind, it gets replaced.
+///
+/// To find out which window had focus when the keybind triggered, use
+/// [`keybind_with_options`] instead.
+///
+/// ### Example
+/// ```no_run
+/// # use pinnacle_api::{input::{self, keys}, process, Modifier};
+/// // Set `Super + Return` to open Alacritty
+/// input::keybind(&[Modifier::Super], keys::KEY_Return, || {
+///     process::spawn(["alacritty"]);
+/// });
+/// ```
+pub fn keybind(modifiers: &[Modifier], key: impl Into<Key>, mut action: impl FnMut() + 'static) {
+    let callback_id = crate::register_callback(move |_| action());
+    set_keybind(modifiers, key, KeybindOptions::default(), callback_id);
+}
+
+/// Set a keybind with extra [`KeybindOptions`].
+///
+/// `action` is called with [`KeybindArgs`] describing what triggered it.
+///
+/// ### Example
+/// ```no_run
+/// # use pinnacle_api::{input::{self, KeybindOptions}, process, Modifier};
+/// // `Super + r` enters the "resize" mode, and `Return` leaves it
+/// input::keybind(&[Modifier::Super], 'r', || input::set_bind_mode("resize"));
+/// input::keybind_with_options(
+///     &[],
+///     "Return",
+///     KeybindOptions {
+///         mode: Some("resize".to_string()),
+///         ..Default::default()
+///     },
+///     |_| input::set_bind_mode(input::DEFAULT_BIND_MODE),
+/// );
+///
+/// // Keep raising the volume while `XF86AudioRaiseVolume` is held
+/// input::keybind_with_options(
+///     &[],
+///     input::keys::KEY_XF86AudioRaiseVolume,
+///     KeybindOptions {
+///         allow_repeat: true,
+///         ..Default::default()
+///     },
+///     |_| process::spawn(["pactl", "set-sink-volume", "@DEFAULT_SINK@", "+5%"]),
+/// );
+///
+/// // Close the focused window, even if focus changes before the config gets to it
+/// input::keybind_with_options(&[Modifier::Super], 'q', KeybindOptions::default(), |args| {
+///     if let Some(window) = args.window {
+///         window.close();
+///     }
+/// });
+/// ```
+pub fn keybind_with_options(
+    modifiers: &[Modifier],
+    key: impl Into<Key>,
+    options: KeybindOptions,
+    mut action: impl FnMut(KeybindArgs) + 'static,
+) {
+    let callback_id = crate::register_callback(move |args| {
+        if let Some(Args::Keybind {
+            key,
+            modifiers,
+            window_id,
+        }) = args
+        {
+            action(KeybindArgs {
+                key,
+                modifiers,
+                window: window_id.map(Window),
+            });
+        } else {
+            eprintln!("Pinnacle called a keybind without keybind args: {args:?}");
+        }
+    });
+    set_keybind(modifiers, key, options, callback_id);
+}
+
+fn set_keybind(
+    modifiers: &[Modifier],
+    key: impl Into<Key>,
+    options: KeybindOptions,
+    callback_id: CallbackId,
+) {
+    crate::send_msg(Msg::SetKeybind {
+        key: key.into().0,
+        modifiers: modifiers.to_vec(),
+        callback_id,
+        mode: options.mode,
+        edge: options.edge,
+        allow_repeat: options.allow_repeat,
+    });
+}
+
+/// Set a mousebind. If called with an already existing mousebind, it gets replaced.
+///
+/// The mousebind can happen either on button press or release, so you must specify
+/// which edge you desire. `action` is called with [`MousebindArgs`] describing where the
+/// pointer was.
+pub fn mousebind(
+    modifiers: &[Modifier],
+    button: MouseButton,
+    edge: MouseEdge,
+    action: impl FnMut(MousebindArgs) + 'static,
+) {
+    set_mousebind(modifiers, button, edge, None, action);
+}
+
+/// Set a mousebind that works in the given bind mode.
+pub fn mousebind_in_mode(
+    mode: &str,
+    modifiers: &[Modifier],
+    button: MouseButton,
+    edge: MouseEdge,
+    action: impl FnMut(MousebindArgs) + 'static,
+) {
+    set_mousebind(modifiers, button, edge, Some(mode.to_string()), action);
+}
+
+fn set_mousebind(
+    modifiers: &[Modifier],
+    button: MouseButton,
+    edge: MouseEdge,
+    mode: Option<String>,
+    mut action: impl FnMut(MousebindArgs) + 'static,
+) {
+    let callback_id = crate::register_callback(move |args| {
+        if let Some(Args::Mousebind {
+            pointer_loc,
+            window_id,
+            output_name,
+        }) = args
+        {
+            action(MousebindArgs {
+                pointer_loc,
+                window: window_id.map(Window),
+                output: output_name.map(Output),
+            });
+        } else {
+            eprintln!("Pinnacle called a mousebind without mousebind args: {args:?}");
+        }
+    });
+
+    crate::send_msg(Msg::SetMousebind {
+        modifiers: modifiers.to_vec(),
+        button: button as u32,
+        edge,
+        callback_id,
+        mode,
+    });
+}
+
+/// Set a touchpad gesture bind. If called with an already existing gesture bind, it gets
+/// replaced.
+///
+/// Once a gesture is bound, gestures of the same kind with the same number of fingers aren't
+/// sent to windows anymore. `action` is called with [`GestureArgs`] describing where the
+/// pointer was.
+///
+/// ### Example
+/// ```no_run
+/// # use pinnacle_api::{input, Gesture, SwipeDirection};
+/// input::gesture_bind(&[], Gesture::Swipe(SwipeDirection::Left), 3, |_| {
+///     // Switch to the next tag
+/// });
+/// ```
+pub fn gesture_bind(
+    modifiers: &[Modifier],
+    gesture: Gesture,
+    fingers: u32,
+    action: impl FnMut(GestureArgs) + 'static,
+) {
+    set_gesture_bind(modifiers, gesture, fingers, None, action);
+}
+
+/// Set a touchpad gesture bind that works in the given bind mode.
+pub fn gesture_bind_in_mode(
+    mode: &str,
+    modifiers: &[Modifier],
+    gesture: Gesture,
+    fingers: u32,
+    action: impl FnMut(GestureArgs) + 'static,
+) {
+    set_gesture_bind(modifiers, gesture, fingers, Some(mode.to_string()), action);
+}
+
+fn set_gesture_bind(
+    modifiers: &[Modifier],
+    gesture: Gesture,
+    fingers: u32,
+    mode: Option<String>,
+    mut action: impl FnMut(GestureArgs) + 'static,
+) {
+    let callback_id = crate::register_callback(move |args| {
+        if let Some(Args::Gesture {
+            pointer_loc,
+            window_id,
+            output_name,
+        }) = args
+        {
+            action(GestureArgs {
+                pointer_loc,
+                window: window_id.map(Window),
+                output: output_name.map(Output),
+            });
+        } else {
+            eprintln!("Pinnacle called a gesture bind without gesture args: {args:?}");
+        }
+    });
+
+    crate::send_msg(Msg::SetGestureBind {
+        gesture,
+        fingers,
+        modifiers: modifiers.to_vec(),
+        callback_id,
+        mode,
+    });
+}
+
+/// Remove a gesture bind set by this config.
+///
+/// Gesture binds set by other clients can't be removed.
+pub fn remove_gesture_bind(modifiers: &[Modifier], gesture: Gesture, fingers: u32) {
+    remove_gesture_bind_in_mode(DEFAULT_BIND_MODE, modifiers, gesture, fingers);
+}
+
+/// Remove a gesture bind set by this config in the given bind mode.
+pub fn remove_gesture_bind_in_mode(
+    mode: &str,
+    modifiers: &[Modifier],
+    gesture: Gesture,
+    fingers: u32,
+) {
+    crate::send_msg(Msg::RemoveGestureBind {
+        gesture,
+        fingers,
+        modifiers: modifiers.to_vec(),
+        mode: Some(mode.to_string()),
+    });
+}
+
+/// Remove a keybind set by this config.
+///
+/// Keybinds set by other clients can't be removed.
+pub fn remove_keybind(modifiers: &[Modifier], key: impl Into<Key>) {
+    crate::send_msg(Msg::RemoveKeybind {
+        key: key.into().0,
+        modifiers: modifiers.to_vec(),
+        mode: None,
+        edge: KeyEdge::Press,
+    });
+}
+
+/// Remove a keybind set by this config with [`keybind_with_options`].
+///
+/// The keybind's bind mode and edge are taken from `options`.
+pub fn remove_keybind_with_options(
+    modifiers: &[Modifier],
+    key: impl Into<Key>,
+    options: &KeybindOptions,
+) {
+    crate::send_msg(Msg::RemoveKeybind {
+        key: key.into().0,
+        modifiers: modifiers.to_vec(),
+        mode: options.mode.clone(),
+        edge: options.edge,
+    });
+}
+
+/// Set a key sequence: key combos that must be pressed one after another to run `action`.
+///
+/// Keys pressed partway through a sequence aren't sent to windows. If the next key doesn't
+/// continue the sequence or takes too long to be pressed, the sequence is cancelled.
+/// See [`set_key_sequence_timeout`].
+///
+/// Keybinds take precedence over the first combo of a key sequence.
+///
+/// ### Example
+/// ```no_run
+/// # use pinnacle_api::{input::{self, KeyCombo}, process, Modifier};
+/// // `Super + a`, then `t` opens Alacritty
+/// input::key_sequence(
+///     &[KeyCombo::new(&[Modifier::Super], 'a'), KeyCombo::new(&[], 't')],
+///     || process::spawn(["alacritty"]),
+/// );
+/// ```
+pub fn key_sequence(sequence: &[KeyCombo], action: impl FnMut() + 'static) {
+    set_key_sequence(sequence, None, action);
+}
+
+/// Set a key sequence that works in the given bind mode.
+pub fn key_sequence_in_mode(mode: &str, sequence: &[KeyCombo], action: impl FnMut() + 'static) {
+    set_key_sequence(sequence, Some(mode.to_string()), action);
+}
+
+fn set_key_sequence(
+    sequence: &[KeyCombo],
+    mode: Option<String>,
+    mut action: impl FnMut(GestureArgs) + 'static,
+) {
+    let callback_id = crate::register_callback(move |args| {
+        if let Some(Args::Gesture {
+            pointer_loc,
+            window_id,
+            output_name,
+        }) = args
+        {
+            action(GestureArgs {
+                pointer_loc,
+                window: window_id.map(Window),
+                output: output_name.map(Output),
+            });
+        } else {
+            eprintln!("Pinnacle called a gesture bind without gesture args: {args:?}");
+        }
+    });
+
+    crate::send_msg(Msg::SetKeySequence {
+        sequence: sequence.iter().cloned().map(Into::into).collect(),
+        callback_id,
+        mode,
+    });
+}
+
+/// Remove a key sequence set by this config in the given bind mode, or the default one
+/// if `mode` is `None`.
+pub fn remove_key_sequence(mode: Option<&str>, sequence: &[KeyCombo]) {
+    crate::send_msg(Msg::RemoveKeySequence {
+        sequence: sequence.iter().cloned().map(Into::into).collect(),
+        mode: mode.map(ToString::to_string),
+    });
+}
+
+/// Set how long to wait for the next key of a key sequence before cancelling it.
+///
+/// This defaults to one second.
+pub fn set_key_sequence_timeout(timeout: std::time::Duration) {
+    crate::send_msg(Msg::SetKeySequenceTimeout {
+        timeout_ms: timeout.as_millis() as u64,
+    });
+}
+
+/// Set the key combo that lets keybinds work again while a window that inhibits keyboard
+/// shortcuts is focused, or unset it with `None`.
+///
+/// Remote desktop clients and virtual machines can ask for every key while they're focused,
+/// so keybinds don't work in them. Pressing this combo turns that off for the focused window,
+/// and pressing it again turns it back on. It's `Super+Escape` by default.
+///
+/// ### Example
+/// ```no_run
+/// # use pinnacle_api::{input::{self, keys, KeyCombo}, Modifier};
+/// input::set_shortcuts_inhibit_escape(Some(KeyCombo::new(&[Modifier::Super], keys::KEY_Escape)));
+/// ```
+pub fn set_shortcuts_inhibit_escape(combo: Option<KeyCombo>) {
+    crate::send_msg(Msg::SetShortcutsInhibitEscape {
+        combo: combo.map(Into::into),
+    });
+}
+
+/// Switch to a bind mode.
+///
+/// While in a mode other than [`DEFAULT_BIND_MODE`], only keybinds, mousebinds, and gesture
+/// binds set for that mode work, and keys that don't match one aren't sent to windows. Pressing `Escape` goes back to the
+/// default mode unless the mode binds `Escape` itself.
+pub fn set_bind_mode(mode: &str) {
+    crate::send_msg(Msg::SetBindMode {
+        mode: mode.to_string(),
+    });
+}
+
+/// Get the current bind mode.
+pub fn bind_mode() -> String {
+    let RequestResponse::BindMode { mode } = crate::request(Request::GetBindMode) else {
+        return DEFAULT_BIND_MODE.to_string();
+    };
+    mode
+}
+
+/// The names xkb builds the keymap from. Unset names use xkb's defaults.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct XkbConfig {
+    pub rules: Option<String>,
+    pub model: Option<String>,
+    /// A comma separated list of layouts, like `"us,de"`.
+    pub layout: Option<String>,
+    /// A comma separated list of variants, one for each layout.
+    pub variant: Option<String>,
+    /// Comma separated xkb options, like `"caps:escape"`.
+    pub options: Option<String>,
+}
+
+/// Set the keyboard's layouts and options.
+///
+/// ### Example
+/// ```no_run
+/// # use pinnacle_api::input::{self, XkbConfig};
+/// input::set_xkb_config(XkbConfig {
+///     layout: Some("us,de".to_string()),
+///     options: Some("caps:escape".to_string()),
+///     ..Default::default()
+/// });
+/// ```
+pub fn set_xkb_config(config: XkbConfig) {
+    crate::send_msg(Msg::SetXkbConfig {
+        rules: config.rules,
+        model: config.model,
+        layout: config.layout,
+        variant: config.variant,
+        options: config.options,
+    });
+}
+
+/// Set how many times per second a held key repeats, and how long it must be held first.
+///
+/// These default to 25 and 200 milliseconds. A rate of 0 turns key repeat off.
+pub fn set_repeat_rate(rate: u32, delay: std::time::Duration) {
+    crate::send_msg(Msg::SetRepeatRate {
+        rate: rate as i32,
+        delay: delay.as_millis() as i32,
+    });
+}
+
+/// Switch to another of the layouts set with [`set_xkb_config`].
+///
+/// ### Example
+/// ```no_run
+/// # use pinnacle_api::{input, Modifier, XkbLayoutSwitch};
+/// input::keybind(&[Modifier::Super], input::keys::KEY_space, || {
+///     input::switch_xkb_layout(XkbLayoutSwitch::Next);
+/// });
+/// ```
+pub fn switch_xkb_layout(layout: XkbLayoutSwitch) {
+    crate::send_msg(Msg::SwitchXkbLayout { layout });
+}
+
+/// The active keyboard layout.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct XkbLayout {
+    /// The index of the layout in the ones set with [`set_xkb_config`], starting at 0.
+    pub index: u32,
+    /// The layout's name, like `"English (US)"`.
+    pub name: String,
+}
+
+/// Get the active keyboard layout.
+pub fn xkb_layout() -> XkbLayout {
+    let RequestResponse::XkbLayout { index, name } = crate::request(Request::GetXkbLayout) else {
+        return XkbLayout::default();
+    };
+    XkbLayout { index, name }
+}
+
+/// Set a libinput setting for devices like touchpads and mice.
+///
+/// The setting applies to matching devices now and whenever they're plugged in. Only devices
+/// named `device_name` and of type `device_type` match; `None` matches all devices. Devices
+/// that don't have the setting ignore it.
+///
+/// ### Example
+/// ```no_run
+/// # use pinnacle_api::{input, InputDeviceType, LibinputSetting};
+/// input::set_libinput_setting(LibinputSetting::Tap(true), None, Some(InputDeviceType::Touchpad));
+/// ```
+pub fn set_libinput_setting(
+    setting: LibinputSetting,
+    device_name: Option<&str>,
+    device_type: Option<InputDeviceType>,
+) {
+    crate::send_msg(Msg::SetLibinputSetting {
+        setting,
+        device_name: device_name.map(ToString::to_string),
+        device_type,
+    });
+}
+
+/// Map touchscreens to an output, or unmap them if `output_name` is `None`.
+///
+/// Touches on a touchscreen land on the output it's mapped to. With no `device_name`, this maps
+/// all touchscreens that aren't mapped by name. Unmapped touchscreens are mapped to the first
+/// output.
+///
+/// ### Example
+/// ```no_run
+/// # use pinnacle_api::input;
+/// input::set_touch_output(Some("eDP-1"), None);
+/// ```
+pub fn set_touch_output(output_name: Option<&str>, device_name: Option<&str>) {
+    crate::send_msg(Msg::SetTouchOutput {
+        output_name: output_name.map(ToString::to_string),
+        device_name: device_name.map(ToString::to_string),
+    });
+}
+
+/// Get which outputs touchscreens are mapped to, as touchscreen and output names.
+///
+/// The touchscreen name is `None` for the output that touchscreens without their own mapping
+/// are mapped to.
+pub fn touch_outputs() -> Vec<(Option<String>, String)> {
+    let response = crate::request(Request::GetTouchOutputs);
+    let RequestResponse::TouchOutputs { mappings } = response else {
+        return Vec::new();
+    };
+    mappings
+        .into_iter()
+        .map(|mapping| (mapping.device_name, mapping.output_name))
+        .collect()
+}
+
+/// Map drawing tablets to an output, or unmap them if `output_name` is `None`.
+///
+/// This works like [`set_touch_output`].
+pub fn set_tablet_output(output_name: Option<&str>, device_name: Option<&str>) {
+    crate::send_msg(Msg::SetTabletOutput {
+        output_name: output_name.map(ToString::to_string),
+        device_name: device_name.map(ToString::to_string),
+    });
+}
+
+/// Get which outputs drawing tablets are mapped to, as tablet and output names.
+///
+/// This works like [`touch_outputs`].
+pub fn tablet_outputs() -> Vec<(Option<String>, String)> {
+    let response = crate::request(Request::GetTabletOutputs);
+    let RequestResponse::TabletOutputs { mappings } = response else {
+        return Vec::new();
+    };
+    mappings
+        .into_iter()
+        .map(|mapping| (mapping.device_name, mapping.output_name))
+        .collect()
+}
+
+/// An input device that is plugged in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputDevice {
+    pub name: String,
+    /// The kinds of device this is.
+    pub types: Vec<InputDeviceType>,
+}
+
+/// Get all input devices that are plugged in.
+///
+/// This is only filled in when running Pinnacle from a TTY.
+pub fn input_devices() -> Vec<InputDevice> {
+    let response = crate::request(Request::GetInputDevices);
+    let RequestResponse::InputDevices { devices } = response else {
+        return Vec::new();
+    };
+    devices
+        .into_iter()
+        .map(|device| InputDevice {
+            name: device.name,
+            types: device.types,
+        })
+        .collect()
+}
+
+/// Remove a mousebind set by this config.
+///
+/// Mousebinds set by other clients can't be removed.
+pub fn remove_mousebind(modifiers: &[Modifier], button: MouseButton, edge: MouseEdge) {
+    remove_mousebind_in_mode(DEFAULT_BIND_MODE, modifiers, button, edge);
+}
+
+/// Remove a mousebind set by this config in the given bind mode.
+pub fn remove_mousebind_in_mode(
+    mode: &str,
+    modifiers: &[Modifier],
+    button: MouseButton,
+    edge: MouseEdge,
+) {
+    crate::send_msg(Msg::RemoveMousebind {
+        modifiers: modifiers.to_vec(),
+        button: button as u32,
+        edge,
+        mode: Some(mode.to_string()),
+    });
+}
+
+/// Get all keybinds currently set, including ones set by other clients.
+///
+/// ### Example
+/// ```no_run
+/// # use pinnacle_api::input;
+/// for bind in input::keybinds() {
+///     println!("{:?} + {}", bind.modifiers, bind.key_name);
+/// }
+/// ```
+pub fn keybinds() -> Vec<Keybind> {
+    let RequestResponse::Keybinds { keybinds } = crate::request(Request::GetKeybinds) else {
+        return Vec::new();
+    };
+
+    keybinds
+        .into_iter()
+        .map(|bind| Keybind {
+            mode: bind.mode,
+            edge: bind.edge,
+            allow_repeat: bind.allow_repeat,
+            modifiers: bind.modifiers,
+            key: bind.key,
+            key_name: bind.key_name,
+        })
+        .collect()
+}
+
+/// Get all mousebinds currently set, including ones set by other clients.
+pub fn mousebinds() -> Vec<Mousebind> {
+    let RequestResponse::Mousebinds { mousebinds } = crate::request(Request::GetMousebinds) else {
+        return Vec::new();
+    };
+
+    mousebinds
+        .into_iter()
+        .map(|bind| Mousebind {
+            mode: bind.mode,
+            modifiers: bind.modifiers,
+            button: bind.button,
+            edge: bind.edge,
+        })
+        .collect()
+}
@@ -0,0 +1,306 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! The Rust library for configuring Pinnacle.
+//!
+//! A Rust config is a normal binary that calls [`setup`]. `setup` connects to Pinnacle, runs
+//! your config function, then listens for Pinnacle to call your keybinds and other callbacks
+//! until the compositor closes the connection.
+//!
+//! To use it, create a binary crate depending on `pinnacle-api` and set `command` in your
+//! `metaconfig.toml` to something that runs it, like `cargo run`.
+//!
+//! ```no_run
+//! use pinnacle_api::{input, process, window, Modifier};
+//!
+//! fn main() -> Result<(), pinnacle_api::Error> {
+//!     pinnacle_api::setup(|| {
+//!         input::keybind(&[Modifier::Ctrl], input::keys::KEY_Return, || {
+//!             process::spawn(["alacritty"]);
+//!         });
+//!
+//!         input::keybind(&[Modifier::Ctrl, Modifier::Alt], 'c', || {
+//!             if let Some(window) = window::get_focused() {
+//!                 window.close();
+//!             }
+//!         });
+//!     })
+//! }
+//! ```
+//!
+//! All functions in this library talk to Pinnacle through the connection made by [`setup`], so
+//! they must be called from inside it, on the same thread. They panic if the connection to
+//! Pinnacle is lost.
+
+pub mod event;
+pub mod input;
+mod msg;
+pub mod output;
+pub mod process;
+pub mod tag;
+pub mod window;
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io::{self, Read, Write},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+};
+
+use msg::{
+    Args, CallbackId, IncomingMsg, Msg, Request, RequestId, RequestResponse, PROTOCOL_VERSION,
+};
+
+pub use msg::{
+    AccelProfile, ClickMethod, EventKind, FloatingOrTiled, FullscreenOrMaximized, Gesture,
+    InputDeviceType, KeyEdge, Layout, LibinputSetting, Modifier, MouseEdge, PinchDirection,
+    ScrollMethod, SwipeDirection, TagId, WindowId, WindowRule, WindowRuleCondition,
+    XkbLayoutSwitch,
+};
+
+/// Where to connect if `PINNACLE_SOCKET` isn't set.
+///
+/// This is the same fallback the Lua API and `pinnacle msg` use.
+const DEFAULT_SOCKET_PATH: &str = "/tmp/pinnacle_socket";
+
+/// Errors that stop [`setup`].
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to connect to Pinnacle at {path:?}: {source}")]
+    Connect { path: PathBuf, source: io::Error },
+    #[error("lost connection to Pinnacle: {0}")]
+    Io(#[from] io::Error),
+    #[error("Pinnacle closed the connection")]
+    Closed,
+    #[error("Pinnacle rejected the connection: {0}")]
+    Handshake(String),
+    #[error("failed to encode message: {0}")]
+    Encode(#[from] rmp_serde::encode::Error),
+    #[error("failed to decode message from Pinnacle: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+}
+
+type Callback = Box<dyn FnMut(Option<Args>)>;
+
+thread_local! {
+    static CONNECTION: RefCell<Option<Connection>> = RefCell::new(None);
+    /// Callbacks indexed by their [`CallbackId`].
+    static CALLBACKS: RefCell<Vec<Option<Callback>>> = RefCell::new(Vec::new());
+}
+
+struct Connection {
+    stream: UnixStream,
+    next_request_id: u32,
+    /// Callbacks and events that arrived while waiting for a request response, in order.
+    unread_msgs: VecDeque<IncomingMsg>,
+}
+
+impl Connection {
+    fn send(&mut self, msg: &Msg) -> Result<(), Error> {
+        let bytes = rmp_serde::to_vec_named(msg)?;
+        self.stream.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        self.stream.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Read the next message from the socket. This returns `None` once Pinnacle closes it.
+    fn read(&mut self) -> Result<Option<IncomingMsg>, Error> {
+        let mut len_bytes = [0u8; 4];
+        match self.stream.read_exact(&mut len_bytes) {
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            res => res?,
+        }
+
+        let mut msg_bytes = vec![0u8; u32::from_le_bytes(len_bytes) as usize];
+        self.stream.read_exact(&mut msg_bytes)?;
+
+        Ok(Some(rmp_serde::from_slice(&msg_bytes)?))
+    }
+
+    /// Send the [`Msg::Hello`] handshake and return the features Pinnacle supports.
+    ///
+    /// This passes on `PINNACLE_CONFIG_TOKEN` so Pinnacle knows this is the config.
+    fn handshake(&mut self) -> Result<Vec<String>, Error> {
+        self.send(&Msg::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            config_token: std::env::var("PINNACLE_CONFIG_TOKEN").ok(),
+        })?;
+
+        match self.read()?.ok_or(Error::Closed)? {
+            IncomingMsg::Hello {
+                protocol_version,
+                features,
+            } if protocol_version == PROTOCOL_VERSION => Ok(features),
+            IncomingMsg::Hello {
+                protocol_version, ..
+            } => Err(Error::Handshake(format!(
+                "Pinnacle speaks protocol version {protocol_version}, \
+                but this library speaks version {PROTOCOL_VERSION}"
+            ))),
+            IncomingMsg::Error { reason, .. } => Err(Error::Handshake(reason)),
+            msg => Err(Error::Handshake(format!("unexpected reply {msg:?}"))),
+        }
+    }
+
+    fn request(&mut self, request: Request) -> Result<RequestResponse, Error> {
+        let request_id = RequestId(self.next_request_id);
+        self.next_request_id += 1;
+
+        self.send(&Msg::Request {
+            request_id,
+            request,
+        })?;
+
+        loop {
+            match self.read()?.ok_or(Error::Closed)? {
+                IncomingMsg::RequestResponse {
+                    request_id: id,
+                    response: RequestResponse::Error { reason },
+                } if id == request_id => {
+                    eprintln!("Pinnacle couldn't answer a request: {reason}");
+                    return Ok(RequestResponse::Error { reason });
+                }
+                IncomingMsg::RequestResponse {
+                    request_id: id,
+                    response,
+                } if id == request_id => return Ok(response),
+                // A response to a request nobody is waiting on anymore
+                IncomingMsg::RequestResponse { .. } => (),
+                msg => self.unread_msgs.push_back(msg),
+            }
+        }
+    }
+
+    fn next_msg(&mut self) -> Result<Option<IncomingMsg>, Error> {
+        if let Some(msg) = self.unread_msgs.pop_front() {
+            return Ok(Some(msg));
+        }
+        self.read()
+    }
+}
+
+fn with_connection<T>(f: impl FnOnce(&mut Connection) -> Result<T, Error>) -> T {
+    CONNECTION.with(|connection| {
+        let mut connection = connection.borrow_mut();
+        let connection = connection
+            .as_mut()
+            .expect("pinnacle_api functions must be called inside `setup`, on its thread");
+        f(connection).unwrap_or_else(|err| panic!("{err}"))
+    })
+}
+
+/// Send a message to Pinnacle.
+pub(crate) fn send_msg(msg: Msg) {
+    with_connection(|connection| connection.send(&msg))
+}
+
+/// Send a request to Pinnacle and wait for the response.
+pub(crate) fn request(request: Request) -> RequestResponse {
+    with_connection(|connection| connection.request(request))
+}
+
+/// Store a callback and return the id Pinnacle should use to call it.
+pub(crate) fn register_callback(callback: impl FnMut(Option<Args>) + 'static) -> CallbackId {
+    CALLBACKS.with(|callbacks| {
+        let mut callbacks = callbacks.borrow_mut();
+        callbacks.push(Some(Box::new(callback)));
+        CallbackId(callbacks.len() as u32 - 1)
+    })
+}
+
+/// Forget a callback Pinnacle will never call.
+fn drop_callback(callback_id: CallbackId) {
+    CALLBACKS.with(|callbacks| {
+        if let Some(callback) = callbacks.borrow_mut().get_mut(callback_id.0 as usize) {
+            *callback = None;
+        }
+    });
+}
+
+fn call_callback(callback_id: CallbackId, args: Option<Args>) {
+    let index = callback_id.0 as usize;
+
+    // Take the callback out while it runs so it can register new callbacks
+    let callback = CALLBACKS.with(|callbacks| {
+        callbacks
+            .borrow_mut()
+            .get_mut(index)
+            .and_then(Option::take)
+    });
+    let Some(mut callback) = callback else { return };
+
+    callback(args);
+
+    CALLBACKS.with(|callbacks| callbacks.borrow_mut()[index] = Some(callback));
+}
+
+/// Connect to Pinnacle, run `config`, then run callbacks until Pinnacle closes the connection.
+///
+/// The socket path is taken from `PINNACLE_SOCKET`, which Pinnacle sets when it runs your config.
+pub fn setup(config: impl FnOnce()) -> Result<(), Error> {
+    let path = std::env::var_os("PINNACLE_SOCKET")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SOCKET_PATH));
+
+    let stream =
+        UnixStream::connect(&path).map_err(|source| Error::Connect { path, source })?;
+
+    let mut connection = Connection {
+        stream,
+        next_request_id: 0,
+        unread_msgs: VecDeque::new(),
+    };
+    connection.handshake()?;
+
+    CONNECTION.with(|cell| *cell.borrow_mut() = Some(connection));
+
+    config();
+
+    // Until this is sent, Pinnacle keeps the old config's binds and tags around
+    send_msg(Msg::ConfigReady);
+
+    let ret = loop {
+        let msg = CONNECTION.with(|connection| {
+            connection
+                .borrow_mut()
+                .as_mut()
+                .expect("connection was removed")
+                .next_msg()
+        });
+
+        match msg {
+            Ok(Some(msg)) => handle_msg(msg),
+            Ok(None) => break Ok(()),
+            Err(err) => break Err(err),
+        }
+    };
+
+    CONNECTION.with(|connection| connection.borrow_mut().take());
+    CALLBACKS.with(|callbacks| callbacks.borrow_mut().clear());
+
+    ret
+}
+
+fn handle_msg(msg: IncomingMsg) {
+    match msg {
+        IncomingMsg::CallCallback { callback_id, args } => call_callback(callback_id, args),
+        IncomingMsg::Event { event } => event::dispatch(event),
+        IncomingMsg::Error {
+            msg_kind,
+            reason,
+            callback_id,
+        } => {
+            eprintln!("Pinnacle couldn't handle {msg_kind}: {reason}");
+            // The message failed, so its callback will never be called
+            if let Some(callback_id) = callback_id {
+                drop_callback(callback_id);
+            }
+        }
+        IncomingMsg::RequestResponse { .. } | IncomingMsg::Hello { .. } => (),
+    }
+}
+
+/// Quit Pinnacle.
+pub fn quit() {
+    send_msg(Msg::Quit);
+}
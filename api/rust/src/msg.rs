@@ -0,0 +1,817 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// These mirror the types in Pinnacle's `api::msg` module and must serialize the same way.
+// The fixtures in `tests/fixtures` record the wire format, and both sides are tested against them.
+// The MessagePack format for these is a one-element map where the element's key is the enum name and its
+// value is a map of the enum's values
+
+use std::num::NonZeroU32;
+
+/// The protocol version this library speaks. Must match Pinnacle's `PROTOCOL_VERSION`.
+pub(crate) const PROTOCOL_VERSION: u32 = 2;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub(crate) struct CallbackId(pub u32);
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub(crate) struct RequestId(pub u32);
+
+/// The unique id of a window.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct WindowId(u32);
+
+/// The unique id of a tag.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct TagId(u32);
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub(crate) enum KeyIntOrString {
+    Int(u32),
+    String(String),
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub(crate) struct KeyCombo {
+    pub modifiers: Vec<Modifier>,
+    pub key: KeyIntOrString,
+}
+
+/// A modifier key.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum Modifier {
+    Shift,
+    Ctrl,
+    Alt,
+    Super,
+}
+
+/// Whether a mousebind triggers on button press or release.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum MouseEdge {
+    Press,
+    Release,
+}
+
+/// Whether a keybind triggers on key press or release.
+#[derive(
+    Debug, Default, Hash, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize,
+)]
+pub enum KeyEdge {
+    #[default]
+    Press,
+    Release,
+}
+
+/// Which keyboard layout to switch to with
+/// [`input::switch_xkb_layout`](crate::input::switch_xkb_layout).
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum XkbLayoutSwitch {
+    /// The layout after the active one, wrapping around to the first.
+    Next,
+    /// The layout before the active one, wrapping around to the last.
+    Prev,
+    /// The layout at this index, starting at 0.
+    Index(u32),
+}
+
+/// A touchpad gesture that can be bound with [`input::gesture_bind`](crate::input::gesture_bind).
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum Gesture {
+    Swipe(SwipeDirection),
+    Pinch(PinchDirection),
+    /// Rest fingers on the touchpad without moving them.
+    Hold,
+}
+
+/// Which way a swipe [`Gesture`] goes.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum SwipeDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Which way a pinch [`Gesture`] goes.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum PinchDirection {
+    /// Move fingers closer together.
+    In,
+    /// Spread fingers apart.
+    Out,
+}
+
+/// A kind of input device. A device can be more than one kind, like a keyboard with a touchpad.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum InputDeviceType {
+    Keyboard,
+    /// A mouse or anything else that moves the pointer, except touchpads.
+    Pointer,
+    Touchpad,
+    /// A touchscreen.
+    Touch,
+    /// A drawing tablet or its pad.
+    Tablet,
+}
+
+/// A libinput device setting.
+#[derive(Debug, PartialEq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum LibinputSetting {
+    /// Tap the touchpad to click.
+    Tap(bool),
+    NaturalScroll(bool),
+    AccelProfile(AccelProfile),
+    /// The pointer acceleration speed, from -1.0 to 1.0.
+    AccelSpeed(f64),
+    /// Swap the left and right buttons.
+    LeftHanded(bool),
+    /// Disable the touchpad while typing.
+    DisableWhileTyping(bool),
+    ScrollMethod(ScrollMethod),
+    ClickMethod(ClickMethod),
+}
+
+/// How the pointer accelerates.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum AccelProfile {
+    /// No acceleration.
+    Flat,
+    /// Pointer speed changes with how fast the device moves.
+    Adaptive,
+}
+
+/// How a device scrolls.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum ScrollMethod {
+    NoScroll,
+    TwoFinger,
+    Edge,
+    /// Scroll by moving the device while holding a button.
+    OnButtonDown,
+}
+
+/// How a touchpad decides which button is clicked.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum ClickMethod {
+    /// Which button is clicked depends on where the touchpad is pressed.
+    ButtonAreas,
+    /// Which button is clicked depends on how many fingers press the touchpad.
+    Clickfinger,
+}
+
+/// A way to tile windows on a tag.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum Layout {
+    MasterStack,
+    Dwindle,
+    Spiral,
+    CornerTopLeft,
+    CornerTopRight,
+    CornerBottomLeft,
+    CornerBottomRight,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum FullscreenOrMaximized {
+    Neither,
+    Fullscreen,
+    Maximized,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum FloatingOrTiled {
+    Floating,
+    Tiled,
+}
+
+/// Conditions a window must meet for a [`WindowRule`] to apply.
+///
+/// All set fields must match. Unset fields are ignored.
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WindowRuleCondition {
+    /// This condition is met when any of the conditions provided is met.
+    pub cond_any: Option<Vec<WindowRuleCondition>>,
+    /// This condition is met when all of the conditions provided are met.
+    pub cond_all: Option<Vec<WindowRuleCondition>>,
+    /// This condition is met when the class matches.
+    pub class: Option<Vec<String>>,
+    /// This condition is met when the title matches.
+    pub title: Option<Vec<String>>,
+    /// This condition is met when the tag matches.
+    pub tag: Option<Vec<TagId>>,
+}
+
+/// What happens to a window that meets a [`WindowRuleCondition`] when it opens.
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WindowRule {
+    /// Set the output the window will open on.
+    pub output: Option<String>,
+    /// Set the tags the output will have on open.
+    pub tags: Option<Vec<TagId>>,
+    /// Set the window to floating or tiled on open.
+    pub floating_or_tiled: Option<FloatingOrTiled>,
+    /// Set the window to fullscreen, maximized, or force it to neither.
+    pub fullscreen_or_maximized: Option<FullscreenOrMaximized>,
+    /// Set the window's initial size.
+    pub size: Option<(NonZeroU32, NonZeroU32)>,
+    /// Set the window's initial location. If the window is tiled, it will snap to this position
+    /// when set to floating.
+    pub location: Option<(i32, i32)>,
+}
+
+/// The kinds of events that can be connected to with [`event::connect`](crate::event::connect).
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum EventKind {
+    WindowOpened,
+    WindowClosed,
+    FocusChanged,
+    TitleChanged,
+    TagAdded,
+    TagRemoved,
+    TagActivated,
+    LayoutChanged,
+    OutputConnected,
+    OutputDisconnected,
+    BindModeChanged,
+}
+
+/// Messages sent to Pinnacle.
+#[derive(Debug, serde::Serialize)]
+pub(crate) enum Msg {
+    Hello {
+        protocol_version: u32,
+        config_token: Option<String>,
+    },
+
+    // Input
+    SetKeybind {
+        key: KeyIntOrString,
+        modifiers: Vec<Modifier>,
+        callback_id: CallbackId,
+        mode: Option<String>,
+        edge: KeyEdge,
+        allow_repeat: bool,
+    },
+    SetMousebind {
+        modifiers: Vec<Modifier>,
+        button: u32,
+        edge: MouseEdge,
+        callback_id: CallbackId,
+        mode: Option<String>,
+    },
+    SetGestureBind {
+        gesture: Gesture,
+        fingers: u32,
+        modifiers: Vec<Modifier>,
+        callback_id: CallbackId,
+        mode: Option<String>,
+    },
+    RemoveKeybind {
+        key: KeyIntOrString,
+        modifiers: Vec<Modifier>,
+        mode: Option<String>,
+        edge: KeyEdge,
+    },
+    RemoveMousebind {
+        modifiers: Vec<Modifier>,
+        button: u32,
+        edge: MouseEdge,
+        mode: Option<String>,
+    },
+    RemoveGestureBind {
+        gesture: Gesture,
+        fingers: u32,
+        modifiers: Vec<Modifier>,
+        mode: Option<String>,
+    },
+    SetKeySequence {
+        sequence: Vec<KeyCombo>,
+        callback_id: CallbackId,
+        mode: Option<String>,
+    },
+    RemoveKeySequence {
+        sequence: Vec<KeyCombo>,
+        mode: Option<String>,
+    },
+    SetKeySequenceTimeout {
+        timeout_ms: u64,
+    },
+    SetShortcutsInhibitEscape {
+        combo: Option<KeyCombo>,
+    },
+    SetBindMode {
+        mode: String,
+    },
+    SetXkbConfig {
+        rules: Option<String>,
+        model: Option<String>,
+        layout: Option<String>,
+        variant: Option<String>,
+        options: Option<String>,
+    },
+    SetRepeatRate {
+        rate: i32,
+        delay: i32,
+    },
+    SwitchXkbLayout {
+        layout: XkbLayoutSwitch,
+    },
+    SetLibinputSetting {
+        setting: LibinputSetting,
+        device_name: Option<String>,
+        device_type: Option<InputDeviceType>,
+    },
+    SetTouchOutput {
+        output_name: Option<String>,
+        device_name: Option<String>,
+    },
+    SetTabletOutput {
+        output_name: Option<String>,
+        device_name: Option<String>,
+    },
+
+    // Window management
+    CloseWindow {
+        window_id: WindowId,
+    },
+    SetWindowSize {
+        window_id: WindowId,
+        width: Option<i32>,
+        height: Option<i32>,
+    },
+    MoveWindowToTag {
+        window_id: WindowId,
+        tag_id: TagId,
+    },
+    ToggleTagOnWindow {
+        window_id: WindowId,
+        tag_id: TagId,
+    },
+    ToggleFloating {
+        window_id: WindowId,
+    },
+    ToggleFullscreen {
+        window_id: WindowId,
+    },
+    ToggleMaximized {
+        window_id: WindowId,
+    },
+    AddWindowRule {
+        cond: WindowRuleCondition,
+        rule: WindowRule,
+    },
+    WindowMoveGrab {
+        button: u32,
+    },
+    WindowResizeGrab {
+        button: u32,
+    },
+
+    // Tag management
+    ToggleTag {
+        tag_id: TagId,
+    },
+    SwitchToTag {
+        tag_id: TagId,
+    },
+    AddTags {
+        output_name: String,
+        tag_names: Vec<String>,
+    },
+    RemoveTags {
+        tag_ids: Vec<TagId>,
+    },
+    SetLayout {
+        tag_id: TagId,
+        layout: Layout,
+    },
+
+    // Output management
+    ConnectForAllOutputs {
+        callback_id: CallbackId,
+    },
+    SetOutputLocation {
+        output_name: String,
+        x: Option<i32>,
+        y: Option<i32>,
+    },
+
+    // Process management
+    Spawn {
+        command: Vec<String>,
+        callback_id: Option<CallbackId>,
+    },
+    SetEnv {
+        key: String,
+        value: String,
+    },
+
+    // Events
+    Subscribe {
+        events: Vec<EventKind>,
+    },
+    Unsubscribe {
+        events: Vec<EventKind>,
+    },
+
+    // Pinnacle management
+    Quit,
+    ConfigReady,
+
+    Request {
+        request_id: RequestId,
+        request: Request,
+    },
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, serde::Serialize)]
+pub(crate) enum Request {
+    // Input
+    GetKeybinds,
+    GetMousebinds,
+    GetBindMode,
+    GetXkbLayout,
+    GetInputDevices,
+    GetTouchOutputs,
+    GetTabletOutputs,
+    // Windows
+    GetWindows,
+    GetWindowProps { window_id: WindowId },
+    // Outputs
+    GetOutputs,
+    GetOutputProps { output_name: String },
+    // Tags
+    GetTags,
+    GetTagProps { tag_id: TagId },
+}
+
+/// Messages sent from Pinnacle.
+#[derive(Debug, serde::Deserialize)]
+pub(crate) enum IncomingMsg {
+    Hello {
+        protocol_version: u32,
+        features: Vec<String>,
+    },
+    CallCallback {
+        callback_id: CallbackId,
+        #[serde(default)]
+        args: Option<Args>,
+    },
+    RequestResponse {
+        request_id: RequestId,
+        response: RequestResponse,
+    },
+    Event {
+        event: Event,
+    },
+    Error {
+        msg_kind: String,
+        reason: String,
+        #[serde(default)]
+        callback_id: Option<CallbackId>,
+    },
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub(crate) enum Args {
+    Spawn {
+        #[serde(default)]
+        stdout: Option<String>,
+        #[serde(default)]
+        stderr: Option<String>,
+        #[serde(default)]
+        exit_code: Option<i32>,
+        #[serde(default)]
+        exit_msg: Option<String>,
+    },
+    ConnectForAllOutputs {
+        output_name: String,
+    },
+    Keybind {
+        key: u32,
+        modifiers: Vec<Modifier>,
+        #[serde(default)]
+        window_id: Option<WindowId>,
+    },
+    Mousebind {
+        pointer_loc: (f64, f64),
+        #[serde(default)]
+        window_id: Option<WindowId>,
+        #[serde(default)]
+        output_name: Option<String>,
+    },
+    Gesture {
+        pointer_loc: (f64, f64),
+        #[serde(default)]
+        window_id: Option<WindowId>,
+        #[serde(default)]
+        output_name: Option<String>,
+    },
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub(crate) enum Event {
+    WindowOpened {
+        window_id: WindowId,
+    },
+    WindowClosed {
+        window_id: WindowId,
+    },
+    FocusChanged {
+        #[serde(default)]
+        window_id: Option<WindowId>,
+    },
+    TitleChanged {
+        window_id: WindowId,
+        #[serde(default)]
+        title: Option<String>,
+    },
+    TagAdded {
+        tag_id: TagId,
+        output_name: String,
+    },
+    TagRemoved {
+        tag_id: TagId,
+    },
+    TagActivated {
+        tag_id: TagId,
+        active: bool,
+    },
+    LayoutChanged {
+        tag_id: TagId,
+        layout: Layout,
+    },
+    OutputConnected {
+        output_name: String,
+    },
+    OutputDisconnected {
+        output_name: String,
+    },
+    BindModeChanged {
+        mode: String,
+    },
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub(crate) enum RequestResponse {
+    Error {
+        reason: String,
+    },
+    Keybinds {
+        keybinds: Vec<KeybindInfo>,
+    },
+    Mousebinds {
+        mousebinds: Vec<MousebindInfo>,
+    },
+    BindMode {
+        mode: String,
+    },
+    XkbLayout {
+        index: u32,
+        name: String,
+    },
+    InputDevices {
+        devices: Vec<InputDeviceInfo>,
+    },
+    TouchOutputs {
+        mappings: Vec<DeviceOutputInfo>,
+    },
+    TabletOutputs {
+        mappings: Vec<DeviceOutputInfo>,
+    },
+    Windows {
+        window_ids: Vec<WindowId>,
+    },
+    WindowProps {
+        size: Option<(i32, i32)>,
+        loc: Option<(i32, i32)>,
+        class: Option<String>,
+        title: Option<String>,
+        focused: Option<bool>,
+        floating: Option<bool>,
+        fullscreen_or_maximized: Option<FullscreenOrMaximized>,
+    },
+    Outputs {
+        output_names: Vec<String>,
+    },
+    OutputProps {
+        make: Option<String>,
+        model: Option<String>,
+        loc: Option<(i32, i32)>,
+        res: Option<(i32, i32)>,
+        refresh_rate: Option<i32>,
+        physical_size: Option<(i32, i32)>,
+        focused: Option<bool>,
+        tag_ids: Option<Vec<TagId>>,
+    },
+    Tags {
+        tag_ids: Vec<TagId>,
+    },
+    TagProps {
+        active: Option<bool>,
+        name: Option<String>,
+        output_name: Option<String>,
+    },
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub(crate) struct KeybindInfo {
+    pub mode: String,
+    pub modifiers: Vec<Modifier>,
+    pub key: u32,
+    pub key_name: String,
+    pub edge: KeyEdge,
+    pub allow_repeat: bool,
+    #[serde(default)]
+    #[allow(dead_code)]
+    pub callback_id: Option<CallbackId>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub(crate) struct InputDeviceInfo {
+    pub name: String,
+    pub types: Vec<InputDeviceType>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub(crate) struct DeviceOutputInfo {
+    pub device_name: Option<String>,
+    pub output_name: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub(crate) struct MousebindInfo {
+    pub mode: String,
+    pub modifiers: Vec<Modifier>,
+    pub button: u32,
+    pub edge: MouseEdge,
+    #[serde(default)]
+    #[allow(dead_code)]
+    pub callback_id: Option<CallbackId>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::{
+        CallbackId, EventKind, FloatingOrTiled, Gesture, IncomingMsg, InputDeviceType, KeyCombo,
+        KeyEdge, KeyIntOrString, Layout, LibinputSetting, Modifier, MouseEdge, Msg, Request,
+        RequestId, SwipeDirection, TagId, WindowId, WindowRule, WindowRuleCondition,
+        XkbLayoutSwitch, PROTOCOL_VERSION,
+    };
+
+    /// Encode `msg` the way it's sent over the socket, then decode it as a `T`.
+    fn send_as<T: serde::de::DeserializeOwned>(msg: &impl serde::Serialize) -> T {
+        let bytes = rmp_serde::to_vec_named(msg).expect("failed to encode msg");
+        rmp_serde::from_slice(&bytes)
+            .unwrap_or_else(|err| panic!("failed to decode {}: {err}", std::any::type_name::<T>()))
+    }
+
+    fn fixtures(json: &str) -> Vec<serde_json::Value> {
+        serde_json::from_str(json).expect("fixture file isn't a JSON array")
+    }
+
+    #[test]
+    fn msgs_match_fixtures() {
+        let window_id = WindowId(1);
+        let tag_id = TagId(2);
+        let callback_id = CallbackId(3);
+        let combo = KeyCombo {
+            modifiers: vec![Modifier::Super],
+            key: KeyIntOrString::String("a".to_string()),
+        };
+
+        let msgs = [
+            Msg::Hello {
+                protocol_version: PROTOCOL_VERSION,
+                config_token: Some("token".to_string()),
+            },
+            Msg::SetKeybind {
+                key: KeyIntOrString::Int(0xff0d),
+                modifiers: vec![Modifier::Ctrl, Modifier::Alt],
+                callback_id,
+                mode: Some("resize".to_string()),
+                edge: KeyEdge::Release,
+                allow_repeat: true,
+            },
+            Msg::SetMousebind {
+                modifiers: vec![],
+                button: 0x110,
+                edge: MouseEdge::Press,
+                callback_id,
+                mode: None,
+            },
+            Msg::SetGestureBind {
+                gesture: Gesture::Swipe(SwipeDirection::Left),
+                fingers: 3,
+                modifiers: vec![Modifier::Shift],
+                callback_id,
+                mode: Some("resize".to_string()),
+            },
+            Msg::SetKeySequence {
+                sequence: vec![combo.clone(), combo.clone()],
+                callback_id,
+                mode: None,
+            },
+            Msg::SetShortcutsInhibitEscape { combo: Some(combo) },
+            Msg::SetXkbConfig {
+                rules: None,
+                model: None,
+                layout: Some("us,de".to_string()),
+                variant: None,
+                options: Some("caps:escape".to_string()),
+            },
+            Msg::SwitchXkbLayout {
+                layout: XkbLayoutSwitch::Index(1),
+            },
+            Msg::SetLibinputSetting {
+                setting: LibinputSetting::AccelSpeed(0.5),
+                device_name: None,
+                device_type: Some(InputDeviceType::Touchpad),
+            },
+            Msg::SetTouchOutput {
+                output_name: Some("HEADLESS-1".to_string()),
+                device_name: None,
+            },
+            Msg::AddWindowRule {
+                cond: WindowRuleCondition {
+                    class: Some(vec!["firefox".to_string()]),
+                    tag: Some(vec![tag_id]),
+                    ..Default::default()
+                },
+                rule: WindowRule {
+                    floating_or_tiled: Some(FloatingOrTiled::Floating),
+                    size: NonZeroU32::new(100).zip(NonZeroU32::new(200)),
+                    ..Default::default()
+                },
+            },
+            Msg::SetWindowSize {
+                window_id,
+                width: Some(100),
+                height: None,
+            },
+            Msg::MoveWindowToTag { window_id, tag_id },
+            Msg::RemoveTags {
+                tag_ids: vec![tag_id],
+            },
+            Msg::SetLayout {
+                tag_id,
+                layout: Layout::Dwindle,
+            },
+            Msg::SetOutputLocation {
+                output_name: "HEADLESS-1".to_string(),
+                x: Some(1920),
+                y: None,
+            },
+            Msg::Spawn {
+                command: vec!["alacritty".to_string()],
+                callback_id: Some(callback_id),
+            },
+            Msg::Subscribe {
+                events: vec![EventKind::WindowOpened, EventKind::BindModeChanged],
+            },
+            Msg::Quit,
+            Msg::ConfigReady,
+            Msg::Request {
+                request_id: RequestId(4),
+                request: Request::GetWindowProps { window_id },
+            },
+            Msg::Request {
+                request_id: RequestId(5),
+                request: Request::GetTagProps { tag_id },
+            },
+            Msg::Request {
+                request_id: RequestId(6),
+                request: Request::GetTouchOutputs,
+            },
+            Msg::Request {
+                request_id: RequestId(7),
+                request: Request::GetTabletOutputs,
+            },
+        ];
+
+        let fixtures = fixtures(include_str!("../tests/fixtures/msgs.json"));
+        assert_eq!(msgs.len(), fixtures.len(), "every fixture needs a msg");
+
+        for (msg, fixture) in msgs.iter().zip(fixtures) {
+            let sent: serde_json::Value = send_as(msg);
+            assert_eq!(sent, fixture, "{msg:?} doesn't match its fixture");
+        }
+    }
+
+    #[test]
+    fn incoming_msg_fixtures_are_understood() {
+        for fixture in fixtures(include_str!("../tests/fixtures/incoming_msgs.json")) {
+            let msg: IncomingMsg = send_as(&fixture);
+
+            if let IncomingMsg::Error { callback_id, .. } = msg {
+                assert_eq!(
+                    callback_id.map(|id| u64::from(id.0)),
+                    fixture["Error"]["callback_id"].as_u64()
+                );
+            }
+        }
+    }
+}
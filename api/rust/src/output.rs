@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Output management.
+//!
+//! An output is what you would call a monitor. Outputs are uniquely identified by their name,
+//! which is the name of the connector they're plugged in to.
+
+use crate::{
+    msg::{Args, Msg, Request, RequestResponse},
+    tag::{self, Tag},
+};
+
+/// A handle to an output.
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub struct Output(pub(crate) String);
+
+/// The properties of an output at the time they were requested.
+#[derive(Debug, Clone, Default)]
+pub struct OutputProperties {
+    /// The make of the output.
+    pub make: Option<String>,
+    /// The model of the output.
+    pub model: Option<String>,
+    /// The location of the output in the global space.
+    pub loc: Option<(i32, i32)>,
+    /// The resolution of the output, in pixels.
+    pub res: Option<(i32, i32)>,
+    /// The refresh rate of the output, in millihertz.
+    pub refresh_rate: Option<i32>,
+    /// The size of the output, in millimeters.
+    pub physical_size: Option<(i32, i32)>,
+    /// Whether the output is focused.
+    pub focused: Option<bool>,
+    /// The tags on the output.
+    pub tags: Vec<Tag>,
+}
+
+impl Output {
+    /// This output's name, like "eDP-1" or "HDMI-A-0".
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    /// Get all of this output's properties at once.
+    pub fn props(&self) -> OutputProperties {
+        let RequestResponse::OutputProps {
+            make,
+            model,
+            loc,
+            res,
+            refresh_rate,
+            physical_size,
+            focused,
+            tag_ids,
+        } = crate::request(Request::GetOutputProps {
+            output_name: self.0.clone(),
+        })
+        else {
+            return OutputProperties::default();
+        };
+
+        OutputProperties {
+            make,
+            model,
+            loc,
+            res,
+            refresh_rate,
+            physical_size,
+            focused,
+            tags: tag_ids.unwrap_or_default().into_iter().map(Tag).collect(),
+        }
+    }
+
+    /// Get this output's make.
+    pub fn make(&self) -> Option<String> {
+        self.props().make
+    }
+
+    /// Get this output's model.
+    pub fn model(&self) -> Option<String> {
+        self.props().model
+    }
+
+    /// Get this output's location in the global space as `(x, y)`.
+    pub fn loc(&self) -> Option<(i32, i32)> {
+        self.props().loc
+    }
+
+    /// Get this output's resolution as `(width, height)`.
+    pub fn res(&self) -> Option<(i32, i32)> {
+        self.props().res
+    }
+
+    /// Get this output's refresh rate in millihertz.
+    pub fn refresh_rate(&self) -> Option<i32> {
+        self.props().refresh_rate
+    }
+
+    /// Get this output's physical size in millimeters.
+    pub fn physical_size(&self) -> Option<(i32, i32)> {
+        self.props().physical_size
+    }
+
+    /// Get whether this output is focused.
+    pub fn focused(&self) -> Option<bool> {
+        self.props().focused
+    }
+
+    /// Get all tags on this output.
+    pub fn tags(&self) -> Vec<Tag> {
+        self.props().tags
+    }
+
+    /// Add tags with the given names to this output.
+    pub fn add_tags(&self, names: &[&str]) {
+        tag::add(self, names);
+    }
+
+    /// Set this output's location in the global space. `None` leaves that coordinate unchanged.
+    pub fn set_loc(&self, x: Option<i32>, y: Option<i32>) {
+        crate::send_msg(Msg::SetOutputLocation {
+            output_name: self.0.clone(),
+            x,
+            y,
+        });
+    }
+}
+
+/// Get all outputs.
+pub fn get_all() -> Vec<Output> {
+    let RequestResponse::Outputs { output_names } = crate::request(Request::GetOutputs) else {
+        return Vec::new();
+    };
+    output_names.into_iter().map(Output).collect()
+}
+
+/// Get the output with the given name.
+pub fn get_by_name(name: &str) -> Option<Output> {
+    get_all().into_iter().find(|output| output.name() == name)
+}
+
+/// Get the focused output.
+pub fn get_focused() -> Option<Output> {
+    get_all()
+        .into_iter()
+        .find(|output| output.focused() == Some(true))
+}
+
+/// Run `func` on all current and future outputs.
+///
+/// This is a good place to add tags to your outputs.
+pub fn connect_for_all(mut func: impl FnMut(Output) + 'static) {
+    let callback_id = crate::register_callback(move |args| {
+        if let Some(Args::ConnectForAllOutputs { output_name }) = args {
+            func(Output(output_name));
+        }
+    });
+
+    crate::send_msg(Msg::ConnectForAllOutputs { callback_id });
+}
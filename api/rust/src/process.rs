@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Process management.
+
+use crate::msg::{Args, Msg};
+
+/// Spawn a program. The first item of `command` is the program and the rest are its arguments.
+///
+/// ### Example
+/// ```no_run
+/// # use pinnacle_api::process;
+/// process::spawn(["alacritty"]);
+/// process::spawn(["bash", "-c", "echo hello"]);
+/// ```
+pub fn spawn(command: impl IntoIterator<Item = impl Into<String>>) {
+    crate::send_msg(Msg::Spawn {
+        command: command.into_iter().map(Into::into).collect(),
+        callback_id: None,
+    });
+}
+
+/// Spawn a program and run `callback` with its output.
+///
+/// `callback` is called with `(stdout, stderr, exit_code, exit_msg)` every time the process
+/// prints a line to stdout or stderr, and once more when it exits. Only the arguments relevant
+/// to each call are `Some`.
+pub fn spawn_with_callback(
+    command: impl IntoIterator<Item = impl Into<String>>,
+    mut callback: impl FnMut(Option<String>, Option<String>, Option<i32>, Option<String>) + 'static,
+) {
+    let callback_id = crate::register_callback(move |args| {
+        if let Some(Args::Spawn {
+            stdout,
+            stderr,
+            exit_code,
+            exit_msg,
+        }) = args
+        {
+            callback(stdout, stderr, exit_code, exit_msg);
+        }
+    });
+
+    crate::send_msg(Msg::Spawn {
+        command: command.into_iter().map(Into::into).collect(),
+        callback_id: Some(callback_id),
+    });
+}
+
+/// Set an environment variable in Pinnacle. All future processes spawned by Pinnacle will have it.
+pub fn set_env(key: &str, value: &str) {
+    crate::send_msg(Msg::SetEnv {
+        key: key.to_string(),
+        value: value.to_string(),
+    });
+}
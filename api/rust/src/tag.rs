@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tag management.
+//!
+//! Tags are Pinnacle's take on workspaces. Every window has one or more tags, and every tag
+//! belongs to an output. An output shows all windows whose tags are active on it.
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{
+    msg::{Msg, Request, RequestResponse},
+    output::{self, Output},
+    Layout, TagId,
+};
+
+/// A handle to a tag.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub struct Tag(pub(crate) TagId);
+
+/// The properties of a tag at the time they were requested.
+#[derive(Debug, Clone, Default)]
+pub struct TagProperties {
+    /// Whether the tag is active.
+    pub active: Option<bool>,
+    /// The tag's name.
+    pub name: Option<String>,
+    /// The output the tag is on.
+    pub output: Option<Output>,
+}
+
+impl Tag {
+    /// This tag's unique id.
+    pub fn id(&self) -> TagId {
+        self.0
+    }
+
+    /// Get all of this tag's properties at once.
+    pub fn props(&self) -> TagProperties {
+        let RequestResponse::TagProps {
+            active,
+            name,
+            output_name,
+        } = crate::request(Request::GetTagProps { tag_id: self.0 })
+        else {
+            return TagProperties::default();
+        };
+
+        TagProperties {
+            active,
+            name,
+            output: output_name.map(Output),
+        }
+    }
+
+    /// Get whether this tag is active.
+    pub fn active(&self) -> Option<bool> {
+        self.props().active
+    }
+
+    /// Get this tag's name.
+    pub fn name(&self) -> Option<String> {
+        self.props().name
+    }
+
+    /// Get the output this tag is on.
+    pub fn output(&self) -> Option<Output> {
+        self.props().output
+    }
+
+    /// Activate this tag and deactivate all others on its output.
+    pub fn switch_to(&self) {
+        crate::send_msg(Msg::SwitchToTag { tag_id: self.0 });
+    }
+
+    /// Toggle this tag between active and inactive.
+    pub fn toggle(&self) {
+        crate::send_msg(Msg::ToggleTag { tag_id: self.0 });
+    }
+
+    /// Set this tag's layout.
+    pub fn set_layout(&self, layout: Layout) {
+        crate::send_msg(Msg::SetLayout {
+            tag_id: self.0,
+            layout,
+        });
+    }
+
+    /// Remove this tag.
+    pub fn remove(&self) {
+        crate::send_msg(Msg::RemoveTags {
+            tag_ids: vec![self.0],
+        });
+    }
+}
+
+/// Add tags with the given names to `output`.
+pub fn add(output: &Output, names: &[&str]) {
+    crate::send_msg(Msg::AddTags {
+        output_name: output.name().to_string(),
+        tag_names: names.iter().map(|name| name.to_string()).collect(),
+    });
+}
+
+/// Get all tags.
+pub fn get_all() -> Vec<Tag> {
+    let RequestResponse::Tags { tag_ids } = crate::request(Request::GetTags) else {
+        return Vec::new();
+    };
+    tag_ids.into_iter().map(Tag).collect()
+}
+
+/// Get all tags with the given name, across all outputs.
+pub fn get_by_name(name: &str) -> Vec<Tag> {
+    get_all()
+        .into_iter()
+        .filter(|tag| tag.name().as_deref() == Some(name))
+        .collect()
+}
+
+/// Get the tag with the given name on the focused output.
+pub fn get(name: &str) -> Option<Tag> {
+    get_on_output(name, &output::get_focused()?)
+}
+
+/// Get the tag with the given name on `output`.
+pub fn get_on_output(name: &str, output: &Output) -> Option<Tag> {
+    output
+        .tags()
+        .into_iter()
+        .find(|tag| tag.name().as_deref() == Some(name))
+}
+
+/// Cycles through layouts on the first active tag of an output.
+///
+/// This keeps track of the layout each tag is on. Clones share the same state, so
+/// one cycler can be moved into multiple keybinds.
+///
+/// ### Example
+/// ```no_run
+/// # use pinnacle_api::{input::{self, keys}, tag, Layout, Modifier};
+/// let cycler = tag::LayoutCycler::new(&[Layout::MasterStack, Layout::Dwindle, Layout::Spiral]);
+///
+/// let next = cycler.clone();
+/// input::keybind(&[Modifier::Ctrl], keys::KEY_space, move || next.next(None));
+/// input::keybind(&[Modifier::Ctrl, Modifier::Shift], keys::KEY_space, move || {
+///     cycler.prev(None)
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct LayoutCycler {
+    layouts: Rc<Vec<Layout>>,
+    indices: Rc<RefCell<HashMap<TagId, usize>>>,
+}
+
+impl LayoutCycler {
+    pub fn new(layouts: &[Layout]) -> Self {
+        Self {
+            layouts: Rc::new(layouts.to_vec()),
+            indices: Default::default(),
+        }
+    }
+
+    /// Switch to the next layout on `output`, or the focused output if `None`.
+    pub fn next(&self, output: Option<&Output>) {
+        self.cycle(output, |index, len| (index + 1) % len);
+    }
+
+    /// Switch to the previous layout on `output`, or the focused output if `None`.
+    pub fn prev(&self, output: Option<&Output>) {
+        self.cycle(output, |index, len| (index + len - 1) % len);
+    }
+
+    fn cycle(&self, output: Option<&Output>, step: impl FnOnce(usize, usize) -> usize) {
+        if self.layouts.is_empty() {
+            return;
+        }
+
+        let focused;
+        let output = match output {
+            Some(output) => output,
+            None => {
+                let Some(output) = output::get_focused() else { return };
+                focused = output;
+                &focused
+            }
+        };
+
+        let Some(tag) = output
+            .tags()
+            .into_iter()
+            .find(|tag| tag.active() == Some(true))
+        else {
+            return;
+        };
+
+        let mut indices = self.indices.borrow_mut();
+        let index = indices.entry(tag.id()).or_insert(0);
+        *index = step(*index, self.layouts.len());
+        tag.set_layout(self.layouts[*index]);
+    }
+}
@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Window management.
+
+use crate::{
+    input::MouseButton,
+    msg::{Msg, Request, RequestResponse},
+    tag::Tag,
+    FullscreenOrMaximized, WindowId, WindowRule, WindowRuleCondition,
+};
+
+/// A handle to a window.
+///
+/// The window may be closed at any time, in which case its properties will all be `None`
+/// and messages sent with it will fail.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub struct Window(pub(crate) WindowId);
+
+/// The properties of a window at the time they were requested.
+#[derive(Debug, Clone, Default)]
+pub struct WindowProperties {
+    /// The size of the window.
+    pub size: Option<(i32, i32)>,
+    /// The location of the window, or `None` if it's not on screen.
+    pub loc: Option<(i32, i32)>,
+    /// The window's class. For example, Alacritty's class is "Alacritty".
+    pub class: Option<String>,
+    /// The window's title.
+    pub title: Option<String>,
+    /// Whether the window has keyboard focus.
+    pub focused: Option<bool>,
+    /// Whether the window is floating.
+    pub floating: Option<bool>,
+    /// Whether the window is fullscreen, maximized, or neither.
+    pub fullscreen_or_maximized: Option<FullscreenOrMaximized>,
+}
+
+impl Window {
+    /// This window's unique id.
+    pub fn id(&self) -> WindowId {
+        self.0
+    }
+
+    /// Get all of this window's properties at once.
+    pub fn props(&self) -> WindowProperties {
+        let RequestResponse::WindowProps {
+            size,
+            loc,
+            class,
+            title,
+            focused,
+            floating,
+            fullscreen_or_maximized,
+        } = crate::request(Request::GetWindowProps { window_id: self.0 })
+        else {
+            return WindowProperties::default();
+        };
+
+        WindowProperties {
+            size,
+            loc,
+            class,
+            title,
+            focused,
+            floating,
+            fullscreen_or_maximized,
+        }
+    }
+
+    /// Get this window's size as `(width, height)`.
+    pub fn size(&self) -> Option<(i32, i32)> {
+        self.props().size
+    }
+
+    /// Get this window's location as `(x, y)`, or `None` if it's not on screen.
+    pub fn loc(&self) -> Option<(i32, i32)> {
+        self.props().loc
+    }
+
+    /// Get this window's class.
+    pub fn class(&self) -> Option<String> {
+        self.props().class
+    }
+
+    /// Get this window's title.
+    pub fn title(&self) -> Option<String> {
+        self.props().title
+    }
+
+    /// Get whether this window has keyboard focus.
+    pub fn focused(&self) -> Option<bool> {
+        self.props().focused
+    }
+
+    /// Get whether this window is floating.
+    pub fn floating(&self) -> Option<bool> {
+        self.props().floating
+    }
+
+    /// Get whether this window is fullscreen.
+    pub fn fullscreen(&self) -> Option<bool> {
+        self.props()
+            .fullscreen_or_maximized
+            .map(|state| state == FullscreenOrMaximized::Fullscreen)
+    }
+
+    /// Get whether this window is maximized.
+    pub fn maximized(&self) -> Option<bool> {
+        self.props()
+            .fullscreen_or_maximized
+            .map(|state| state == FullscreenOrMaximized::Maximized)
+    }
+
+    /// Set this window's size. `None` leaves that dimension unchanged.
+    pub fn set_size(&self, width: Option<i32>, height: Option<i32>) {
+        crate::send_msg(Msg::SetWindowSize {
+            window_id: self.0,
+            width,
+            height,
+        });
+    }
+
+    /// Move this window to `tag`, removing all of its other tags.
+    pub fn move_to_tag(&self, tag: &Tag) {
+        crate::send_msg(Msg::MoveWindowToTag {
+            window_id: self.0,
+            tag_id: tag.id(),
+        });
+    }
+
+    /// Toggle `tag` on this window.
+    pub fn toggle_tag(&self, tag: &Tag) {
+        crate::send_msg(Msg::ToggleTagOnWindow {
+            window_id: self.0,
+            tag_id: tag.id(),
+        });
+    }
+
+    /// Close this window.
+    pub fn close(&self) {
+        crate::send_msg(Msg::CloseWindow { window_id: self.0 });
+    }
+
+    /// Toggle this window between floating and tiled.
+    pub fn toggle_floating(&self) {
+        crate::send_msg(Msg::ToggleFloating { window_id: self.0 });
+    }
+
+    /// Toggle this window's fullscreen status.
+    pub fn toggle_fullscreen(&self) {
+        crate::send_msg(Msg::ToggleFullscreen { window_id: self.0 });
+    }
+
+    /// Toggle this window's maximized status.
+    pub fn toggle_maximized(&self) {
+        crate::send_msg(Msg::ToggleMaximized { window_id: self.0 });
+    }
+}
+
+/// Get all windows.
+pub fn get_all() -> Vec<Window> {
+    let RequestResponse::Windows { window_ids } = crate::request(Request::GetWindows) else {
+        return Vec::new();
+    };
+    window_ids.into_iter().map(Window).collect()
+}
+
+/// Get the window with keyboard focus, if any.
+pub fn get_focused() -> Option<Window> {
+    get_all()
+        .into_iter()
+        .find(|window| window.focused() == Some(true))
+}
+
+/// Get all windows with the given class.
+pub fn get_by_class(class: &str) -> Vec<Window> {
+    get_all()
+        .into_iter()
+        .filter(|window| window.class().as_deref() == Some(class))
+        .collect()
+}
+
+/// Get all windows with the given title.
+pub fn get_by_title(title: &str) -> Vec<Window> {
+    get_all()
+        .into_iter()
+        .filter(|window| window.title().as_deref() == Some(title))
+        .collect()
+}
+
+/// Begin moving the window under the pointer with the mouse, until `button` is released.
+///
+/// This is intended to be used in a mousebind.
+pub fn begin_move(button: MouseButton) {
+    crate::send_msg(Msg::WindowMoveGrab {
+        button: button as u32,
+    });
+}
+
+/// Begin resizing the window under the pointer with the mouse, until `button` is released.
+///
+/// This is intended to be used in a mousebind.
+pub fn begin_resize(button: MouseButton) {
+    crate::send_msg(Msg::WindowResizeGrab {
+        button: button as u32,
+    });
+}
+
+/// Add a rule that applies to windows meeting `cond` when they open.
+///
+/// ### Example
+/// ```no_run
+/// # use pinnacle_api::{window, FloatingOrTiled, WindowRule, WindowRuleCondition};
+/// // Make all XTerm windows open floating
+/// window::add_rule(
+///     WindowRuleCondition {
+///         class: Some(vec!["XTerm".to_string()]),
+///         ..Default::default()
+///     },
+///     WindowRule {
+///         floating_or_tiled: Some(FloatingOrTiled::Floating),
+///         ..Default::default()
+///     },
+/// );
+/// ```
+pub fn add_rule(cond: WindowRuleCondition, rule: WindowRule) {
+    crate::send_msg(Msg::AddWindowRule { cond, rule });
+}
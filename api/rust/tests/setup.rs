@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::{
+    cell::Cell,
+    io::{Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    rc::Rc,
+};
+
+use pinnacle_api::{input, output, window::Window, Modifier};
+use serde_json::{json, Value};
+
+fn send(stream: &mut UnixStream, msg: Value) {
+    let bytes = rmp_serde::to_vec_named(&msg).expect("failed to encode msg");
+    stream
+        .write_all(&(bytes.len() as u32).to_le_bytes())
+        .expect("failed to write to socket");
+    stream.write_all(&bytes).expect("failed to write to socket");
+}
+
+fn recv(stream: &mut UnixStream) -> Value {
+    let mut len_bytes = [0u8; 4];
+    stream
+        .read_exact(&mut len_bytes)
+        .expect("failed to read from socket");
+    let mut msg_bytes = vec![0u8; u32::from_le_bytes(len_bytes) as usize];
+    stream
+        .read_exact(&mut msg_bytes)
+        .expect("failed to read from socket");
+    rmp_serde::from_slice(&msg_bytes).expect("failed to decode msg")
+}
+
+/// Answer the handshake the way Pinnacle does when the protocol versions match.
+fn accept_hello(stream: &mut UnixStream) {
+    let hello = recv(stream);
+    assert_eq!(hello["Hello"]["protocol_version"], 2);
+    send(
+        stream,
+        json!({ "Hello": { "protocol_version": 2, "features": ["events"] } }),
+    );
+}
+
+/// Run `setup` against a fake Pinnacle that follows `server`, then closes the connection.
+fn with_fake_pinnacle(
+    name: &str,
+    config: impl FnOnce(),
+    server: impl FnOnce(&mut UnixStream) + Send + 'static,
+) -> Result<(), pinnacle_api::Error> {
+    let dir = std::env::temp_dir().join(format!(
+        "pinnacle-api-test-{}-{name}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).expect("failed to create test dir");
+    let socket_path = dir.join("pinnacle_socket");
+    let _ = std::fs::remove_file(&socket_path);
+    let listener = UnixListener::bind(&socket_path).expect("failed to bind socket");
+
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().expect("failed to accept");
+        server(&mut stream);
+    });
+
+    // Tests run in parallel, so don't let them race on the global env
+    static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let ret = {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        std::env::set_var("PINNACLE_SOCKET", &socket_path);
+        pinnacle_api::setup(config)
+    };
+
+    server.join().expect("server panicked");
+    let _ = std::fs::remove_dir_all(&dir);
+    ret
+}
+
+#[test]
+fn callbacks_are_dispatched_until_disconnect() {
+    let pressed = Rc::new(Cell::new(0));
+    let pressed_in_config = pressed.clone();
+
+    with_fake_pinnacle(
+        "callbacks",
+        move || {
+            input::keybind(&[Modifier::Ctrl], 'a', move || {
+                pressed_in_config.set(pressed_in_config.get() + 1);
+                // Requests made from callbacks get their responses
+                let outputs = output::get_all();
+                assert_eq!(outputs.len(), 1);
+                assert_eq!(outputs[0].name(), "HEADLESS-1");
+            });
+        },
+        |stream| {
+            accept_hello(stream);
+
+            let keybind = recv(stream);
+            assert_eq!(keybind["SetKeybind"]["key"], json!({ "String": "a" }));
+            assert_eq!(keybind["SetKeybind"]["modifiers"], json!(["Ctrl"]));
+            let callback_id = keybind["SetKeybind"]["callback_id"].clone();
+
+            assert_eq!(recv(stream), json!("ConfigReady"));
+
+            for _ in 0..2 {
+                send(
+                    stream,
+                    json!({ "CallCallback": { "callback_id": callback_id, "args": null } }),
+                );
+
+                let request = recv(stream);
+                assert_eq!(request["Request"]["request"], "GetOutputs");
+                send(
+                    stream,
+                    json!({
+                        "RequestResponse": {
+                            "request_id": request["Request"]["request_id"],
+                            "response": { "Outputs": { "output_names": ["HEADLESS-1"] } },
+                        }
+                    }),
+                );
+            }
+        },
+    )
+    .expect("setup failed");
+
+    assert_eq!(pressed.get(), 2);
+}
+
+#[test]
+fn keybind_args_are_passed_to_actions() {
+    let focused = Rc::new(Cell::new(None));
+    let focused_in_config = focused.clone();
+
+    with_fake_pinnacle(
+        "keybind-args",
+        move || {
+            input::keybind_with_options(
+                &[Modifier::Super],
+                'q',
+                input::KeybindOptions::default(),
+                move |args| {
+                    assert_eq!(args.key, 'q' as u32);
+                    assert_eq!(args.modifiers, vec![Modifier::Super]);
+                    focused_in_config.set(args.window.map(|window: Window| window.id()));
+                },
+            );
+        },
+        |stream| {
+            accept_hello(stream);
+
+            let keybind = recv(stream);
+            let callback_id = keybind["SetKeybind"]["callback_id"].clone();
+            assert_eq!(recv(stream), json!("ConfigReady"));
+
+            send(
+                stream,
+                json!({
+                    "CallCallback": {
+                        "callback_id": callback_id,
+                        "args": {
+                            "Keybind": { "key": 'q' as u32, "modifiers": ["Super"], "window_id": 4 }
+                        },
+                    }
+                }),
+            );
+        },
+    )
+    .expect("setup failed");
+
+    assert!(focused.get().is_some());
+}
+
+#[test]
+fn rejected_handshake_is_an_error() {
+    let ret = with_fake_pinnacle(
+        "rejected",
+        || panic!("config ran after a rejected handshake"),
+        |stream| {
+            recv(stream);
+            send(
+                stream,
+                json!({
+                    "Error": {
+                        "msg_kind": "Hello",
+                        "reason": "update your API library",
+                        "callback_id": null,
+                    }
+                }),
+            );
+        },
+    );
+
+    match ret {
+        Err(pinnacle_api::Error::Handshake(reason)) => {
+            assert_eq!(reason, "update your API library")
+        }
+        ret => panic!("expected a handshake error, got {ret:?}"),
+    }
+}
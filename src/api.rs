@@ -20,6 +20,15 @@
 //!
 //! Pinnacle uses [MessagePack](https://msgpack.org/index.html) as the message format.
 //! Messages should be serialized into MessagePack according to the [defined structs](msg::Msg).
+//! Each message is sent as a frame: its length as a little-endian `u32`, followed by the message.
+//! Frames longer than [`MAX_FRAME_SIZE`] are skipped. If a frame can't be decoded into a message,
+//! Pinnacle replies with an [Error](msg::OutgoingMsg::Error) and keeps reading from the client.
+//!
+//! The first message a client sends must be a [Hello](msg::Msg::Hello) with the
+//! [protocol version](msg::PROTOCOL_VERSION) it speaks. If Pinnacle speaks the same version, it
+//! replies with a [Hello](msg::OutgoingMsg::Hello) listing the optional [features](msg::FEATURES)
+//! it supports. Otherwise, it replies with an [Error](msg::OutgoingMsg::Error) explaining why and
+//! closes the connection.
 //!
 //! When Pinnacle needs to call a user-defined callback, for example from a keybind setting, it
 //! sends a [CallCallback](msg::OutgoingMsg::CallCallback) message to the client. This message
@@ -30,6 +39,24 @@
 //! This could be an array and its indices or a hashmap and its keys (keep in mind the id needs to
 //! be an unsigned 32 bit int).
 //!
+//! Multiple clients can be connected at once, so tools like bars or scripts can run alongside
+//! your config. Each client has its own callback ids, subscriptions, and responses; callbacks
+//! and responses are only ever sent to the client that set them up. When a client disconnects,
+//! all of its keybinds, mousebinds, and callbacks are removed.
+//!
+//! Pinnacle starts the config with a random token in the `PINNACLE_CONFIG_TOKEN` environment
+//! variable. The config should send it back in its [Hello](msg::Msg::Hello) so Pinnacle can
+//! tell it apart from other clients.
+//!
+//! A config should send [ConfigReady](msg::Msg::ConfigReady) once it has finished setting up.
+//! Until then, its keybinds, mousebinds, window rules, and tags are kept apart from the ones
+//! of the config it's replacing, so a config that fails halfway through doesn't leave the
+//! compositor without them. Other clients don't need to send it.
+//!
+//! Clients can also subscribe to things happening in the compositor, like windows opening or tags
+//! being switched to, by sending a [Subscribe](msg::Msg::Subscribe) message. Pinnacle will then
+//! send an [Event](msg::OutgoingMsg::Event) message every time one of those things happens.
+//!
 //! TODO: expand
 //!
 //! For an example, look at the Lua implementation in the repository.
@@ -37,9 +64,11 @@
 pub mod msg;
 
 use std::{
+    collections::HashMap,
     io::{self, Read, Write},
     os::unix::net::{UnixListener, UnixStream},
     path::Path,
+    sync::atomic::{AtomicU32, Ordering},
 };
 
 use anyhow::Context;
@@ -49,50 +78,218 @@ use smithay::reexports::calloop::{
 use sysinfo::{ProcessRefreshKind, RefreshKind, SystemExt};
 use walkdir::WalkDir;
 
-use self::msg::{Msg, OutgoingMsg};
+use self::msg::{Msg, OutgoingMsg, PROTOCOL_VERSION};
 
 pub const DEFAULT_SOCKET_DIR: &str = "/tmp";
 pub const SOCKET_NAME: &str = "pinnacle_socket";
 
-fn handle_client(
-    mut stream: UnixStream,
-    sender: Sender<Msg>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    loop {
-        let mut len_marker_bytes = [0u8; 4];
-        if let Err(err) = stream.read_exact(&mut len_marker_bytes) {
-            if err.kind() == io::ErrorKind::UnexpectedEof {
-                tracing::warn!("stream closed: {}", err);
-                stream.shutdown(std::net::Shutdown::Both)?;
-                break Ok(());
-            }
-        };
+/// The largest message a client can send, in bytes.
+///
+/// Anything bigger is almost certainly a client bug, and allocating for it would let one bad
+/// length marker eat all of Pinnacle's memory.
+pub const MAX_FRAME_SIZE: u32 = 1024 * 1024;
 
-        let len_marker = u32::from_ne_bytes(len_marker_bytes);
-        let mut msg_bytes = vec![0u8; len_marker as usize];
+/// An id for a client connected to the Pinnacle socket.
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq)]
+pub struct ApiClientId(u32);
 
-        if let Err(err) = stream.read_exact(msg_bytes.as_mut_slice()) {
-            if err.kind() == io::ErrorKind::UnexpectedEof {
-                tracing::warn!("stream closed: {}", err);
-                stream.shutdown(std::net::Shutdown::Both)?;
-                break Ok(());
-            }
+static API_CLIENT_ID_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+impl ApiClientId {
+    pub fn next() -> Self {
+        Self(API_CLIENT_ID_COUNTER.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Something that happened on a client's connection.
+#[derive(Debug)]
+pub enum ClientEvent {
+    /// The client finished the handshake.
+    Hello {
+        client_id: ApiClientId,
+        /// The config token it sent, if any.
+        config_token: Option<String>,
+    },
+    /// The client sent a message.
+    Msg { client_id: ApiClientId, msg: Msg },
+    /// The client sent something that couldn't be read as a message.
+    InvalidMsg {
+        client_id: ApiClientId,
+        /// The name of the message, if it could be found.
+        msg_kind: String,
+        reason: String,
+    },
+    /// The client's connection closed.
+    Disconnected { client_id: ApiClientId },
+}
+
+/// A frame read from a client.
+enum Frame {
+    Msg(Vec<u8>),
+    /// A frame longer than [`MAX_FRAME_SIZE`]. Its contents were skipped.
+    TooLarge(u32),
+}
+
+/// Whether a read error means the client went away.
+fn is_disconnect(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::UnexpectedEof | io::ErrorKind::ConnectionReset
+    )
+}
+
+/// Read one frame from the client. This returns `None` if the client closed the stream,
+/// including in the middle of a frame.
+fn read_frame(stream: &mut UnixStream) -> io::Result<Option<Frame>> {
+    let mut len_marker_bytes = [0u8; 4];
+    match stream.read_exact(&mut len_marker_bytes) {
+        Err(err) if is_disconnect(&err) => return Ok(None),
+        res => res?,
+    }
+
+    let len_marker = u32::from_le_bytes(len_marker_bytes);
+
+    if len_marker > MAX_FRAME_SIZE {
+        // Throw the contents away so the next frame can still be read
+        let skipped = match io::copy(&mut (&mut *stream).take(len_marker.into()), &mut io::sink()) {
+            Err(err) if is_disconnect(&err) => return Ok(None),
+            res => res?,
         };
-        let msg: Msg = rmp_serde::from_slice(msg_bytes.as_slice())?; // TODO: handle error
+        if skipped < u64::from(len_marker) {
+            return Ok(None);
+        }
+        return Ok(Some(Frame::TooLarge(len_marker)));
+    }
 
-        sender.send(msg)?;
+    let mut msg_bytes = vec![0u8; len_marker as usize];
+    match stream.read_exact(msg_bytes.as_mut_slice()) {
+        Err(err) if is_disconnect(&err) => {
+            tracing::warn!("API client disconnected in the middle of a message");
+            return Ok(None);
+        }
+        res => res?,
     }
+
+    Ok(Some(Frame::Msg(msg_bytes)))
+}
+
+/// Find the name of a message that couldn't be decoded.
+///
+/// Messages without fields are serialized as their name and the rest as a map from their name
+/// to their fields, so this works even if the fields are wrong.
+fn msg_kind(msg_bytes: &[u8]) -> String {
+    rmp_serde::from_slice::<String>(msg_bytes)
+        .ok()
+        .or_else(|| {
+            rmp_serde::from_slice::<HashMap<String, serde::de::IgnoredAny>>(msg_bytes)
+                .ok()
+                .and_then(|map| map.into_keys().next())
+        })
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
+/// Check that the client's first message is a [`Msg::Hello`] with the protocol version
+/// Pinnacle speaks, and reply to it.
+///
+/// This returns whether the client passed. If it did, a [`ClientEvent::Hello`] is sent.
+fn handshake(
+    client_id: ApiClientId,
+    stream: &mut UnixStream,
+    sender: &Sender<ClientEvent>,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let Some(frame) = read_frame(stream)? else { return Ok(false) };
+    let Frame::Msg(msg_bytes) = frame else {
+        return Err("the first message was too large".into());
+    };
+
+    let reason = match rmp_serde::from_slice::<Msg>(&msg_bytes) {
+        Ok(Msg::Hello {
+            protocol_version,
+            config_token,
+        }) if protocol_version == PROTOCOL_VERSION => {
+            send_to_client(stream, &OutgoingMsg::hello())?;
+            sender.send(ClientEvent::Hello {
+                client_id,
+                config_token,
+            })?;
+            return Ok(true);
+        }
+        Ok(Msg::Hello {
+            protocol_version, ..
+        }) => format!(
+            "the client speaks protocol version {protocol_version}, but Pinnacle speaks version \
+            {PROTOCOL_VERSION}; update your API library to match the compositor"
+        ),
+        _ => "the first message must be Hello; your API library is probably older than the \
+            compositor and needs to be updated"
+            .to_string(),
+    };
+
+    tracing::warn!("Rejected API client: {reason}");
+    send_to_client(
+        stream,
+        &OutgoingMsg::Error {
+            msg_kind: "Hello".to_string(),
+            reason,
+            callback_id: None,
+        },
+    )?;
+
+    Ok(false)
+}
+
+fn handle_client(
+    client_id: ApiClientId,
+    mut stream: UnixStream,
+    sender: &Sender<ClientEvent>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if handshake(client_id, &mut stream, sender)? {
+        while let Some(frame) = read_frame(&mut stream)? {
+            let event = match frame {
+                Frame::Msg(msg_bytes) => match rmp_serde::from_slice(&msg_bytes) {
+                    Ok(msg) => ClientEvent::Msg { client_id, msg },
+                    Err(err) => {
+                        let msg_kind = msg_kind(&msg_bytes);
+                        tracing::warn!(
+                            "Failed to decode {msg_kind} message from API client: {err}"
+                        );
+                        ClientEvent::InvalidMsg {
+                            client_id,
+                            msg_kind,
+                            reason: format!("failed to decode message: {err}"),
+                        }
+                    }
+                },
+                Frame::TooLarge(len) => {
+                    tracing::warn!("Skipped a {len} byte message from API client");
+                    ClientEvent::InvalidMsg {
+                        client_id,
+                        msg_kind: "Unknown".to_string(),
+                        reason: format!(
+                            "message is {len} bytes, but the maximum is {MAX_FRAME_SIZE} bytes"
+                        ),
+                    }
+                }
+            };
+
+            sender.send(event)?;
+        }
+        tracing::warn!("stream closed");
+    }
+
+    stream.shutdown(std::net::Shutdown::Both)?;
+    Ok(())
 }
 
 pub struct PinnacleSocketSource {
     socket: Generic<UnixListener>,
-    sender: Sender<Msg>,
+    sender: Sender<ClientEvent>,
 }
 
 impl PinnacleSocketSource {
     /// Create a loop source that listens for connections to the provided socket_dir.
     /// This will also set PINNACLE_SOCKET for use in API implementations.
-    pub fn new(sender: Sender<Msg>, socket_dir: &Path) -> anyhow::Result<Self> {
+    pub fn new(sender: Sender<ClientEvent>, socket_dir: &Path) -> anyhow::Result<Self> {
         let system = sysinfo::System::new_with_specifics(
             RefreshKind::new().with_processes(ProcessRefreshKind::new()),
         );
@@ -164,7 +361,7 @@ pub fn send_to_client(
     // tracing::debug!("Sending {msg:?}");
     let msg = rmp_serde::to_vec_named(msg)?;
     let msg_len = msg.len() as u32;
-    let bytes = msg_len.to_ne_bytes();
+    let bytes = msg_len.to_le_bytes();
 
     if let Err(err) = stream.write_all(&bytes) {
         if err.kind() == io::ErrorKind::BrokenPipe {
@@ -182,7 +379,7 @@ pub fn send_to_client(
 }
 
 impl EventSource for PinnacleSocketSource {
-    type Event = UnixStream;
+    type Event = (ApiClientId, UnixStream);
 
     type Metadata = ();
 
@@ -207,11 +404,14 @@ impl EventSource for PinnacleSocketSource {
                         Ok(callback_stream) => callback_stream,
                         Err(err) => return Err(err),
                     };
-                    callback(callback_stream, &mut ());
+                    let client_id = ApiClientId::next();
+                    callback((client_id, callback_stream), &mut ());
                     std::thread::spawn(move || {
-                        if let Err(err) = handle_client(stream, sender) {
+                        if let Err(err) = handle_client(client_id, stream, &sender) {
                             tracing::error!("handle_client errored: {err}");
                         }
+                        // The compositor may already be gone, so don't bother with the error
+                        let _ = sender.send(ClientEvent::Disconnected { client_id });
                     });
                 }
 
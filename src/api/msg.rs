@@ -16,7 +16,34 @@ use crate::{
 
 use self::window_rules::{WindowRule, WindowRuleCondition};
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy)]
+/// The version of the message protocol.
+///
+/// This must be bumped whenever messages change in a way old clients can't handle.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Optional parts of the protocol that this compositor supports.
+///
+/// These are sent in the handshake so clients can check for them. Add one for every new
+/// group of messages.
+pub const FEATURES: &[&str] = &[
+    "events",
+    "errors",
+    "multiple_clients",
+    "config_ready",
+    "bind_introspection",
+    "bind_modes",
+    "key_sequences",
+    "key_edges",
+    "bind_args",
+    "xkb",
+    "libinput",
+    "gestures",
+    "touch",
+    "tablet",
+    "shortcuts_inhibit",
+];
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
 pub struct CallbackId(pub u32);
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
@@ -31,19 +58,185 @@ pub enum MouseEdge {
     Release,
 }
 
+/// Whether a keybind triggers on key press or release.
+#[derive(
+    Debug, Default, Hash, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq,
+)]
+pub enum KeyEdge {
+    #[default]
+    Press,
+    Release,
+}
+
+/// Which layout to switch to with [`Msg::SwitchXkbLayout`].
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum XkbLayoutSwitch {
+    /// The layout after the active one, wrapping around to the first.
+    Next,
+    /// The layout before the active one, wrapping around to the last.
+    Prev,
+    /// The layout at this index.
+    Index(u32),
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub enum Msg {
+    /// The handshake. This must be the first message a client sends.
+    Hello {
+        protocol_version: u32,
+        /// The value of `PINNACLE_CONFIG_TOKEN`, which Pinnacle sets for the config process.
+        ///
+        /// This is how Pinnacle tells the config apart from other clients.
+        #[serde(default)]
+        config_token: Option<String>,
+    },
+
     // Input
     SetKeybind {
         key: KeyIntOrString,
         modifiers: Vec<Modifier>,
         callback_id: CallbackId,
+        /// The bind mode this keybind works in. `None` means the default mode.
+        #[serde(default)]
+        mode: Option<String>,
+        #[serde(default)]
+        edge: KeyEdge,
+        /// Whether to call the callback again on every key repeat while the key is held.
+        /// This only applies to keybinds on [`KeyEdge::Press`].
+        #[serde(default)]
+        allow_repeat: bool,
     },
     SetMousebind {
         modifiers: Vec<Modifier>,
         button: u32,
         edge: MouseEdge,
         callback_id: CallbackId,
+        /// The bind mode this mousebind works in. `None` means the default mode.
+        #[serde(default)]
+        mode: Option<String>,
+    },
+    /// Call the callback when a touchpad gesture is done with this many fingers while holding
+    /// `modifiers`.
+    ///
+    /// Gestures of the same kind and finger count aren't sent to windows anymore.
+    SetGestureBind {
+        gesture: Gesture,
+        fingers: u32,
+        #[serde(default)]
+        modifiers: Vec<Modifier>,
+        callback_id: CallbackId,
+        /// The bind mode this gesture bind works in. `None` means the default mode.
+        #[serde(default)]
+        mode: Option<String>,
+    },
+    /// Remove a keybind the client set.
+    RemoveKeybind {
+        key: KeyIntOrString,
+        modifiers: Vec<Modifier>,
+        #[serde(default)]
+        mode: Option<String>,
+        #[serde(default)]
+        edge: KeyEdge,
+    },
+    /// Remove a mousebind the client set.
+    RemoveMousebind {
+        modifiers: Vec<Modifier>,
+        button: u32,
+        edge: MouseEdge,
+        #[serde(default)]
+        mode: Option<String>,
+    },
+    /// Remove a gesture bind the client set.
+    RemoveGestureBind {
+        gesture: Gesture,
+        fingers: u32,
+        #[serde(default)]
+        modifiers: Vec<Modifier>,
+        #[serde(default)]
+        mode: Option<String>,
+    },
+    /// Set a sequence of key combos that must be pressed one after another to call the callback.
+    SetKeySequence {
+        sequence: Vec<KeyCombo>,
+        callback_id: CallbackId,
+        /// The bind mode this key sequence works in. `None` means the default mode.
+        #[serde(default)]
+        mode: Option<String>,
+    },
+    /// Remove a key sequence the client set.
+    RemoveKeySequence {
+        sequence: Vec<KeyCombo>,
+        #[serde(default)]
+        mode: Option<String>,
+    },
+    /// Set how long to wait for the next key of a key sequence before giving up on it.
+    SetKeySequenceTimeout {
+        timeout_ms: u64,
+    },
+    /// Set the key combo that turns a focused window's keyboard shortcuts inhibitor off and
+    /// back on, or unset it if `combo` is `None`. It's `Super+Escape` until this is sent.
+    SetShortcutsInhibitEscape {
+        #[serde(default)]
+        combo: Option<KeyCombo>,
+    },
+    /// Switch to the given bind mode. Only binds set for this mode will work until it's left.
+    SetBindMode {
+        mode: String,
+    },
+    /// Rebuild the keymap from the given xkb names. Unset names use xkb's defaults.
+    ///
+    /// `layout` and `variant` can be comma separated lists to set up more than one layout.
+    SetXkbConfig {
+        #[serde(default)]
+        rules: Option<String>,
+        #[serde(default)]
+        model: Option<String>,
+        #[serde(default)]
+        layout: Option<String>,
+        #[serde(default)]
+        variant: Option<String>,
+        #[serde(default)]
+        options: Option<String>,
+    },
+    /// Set how long a key must be held before it repeats, and how fast it repeats after that.
+    SetRepeatRate {
+        /// Repeats per second
+        rate: i32,
+        /// The delay in milliseconds
+        delay: i32,
+    },
+    /// Switch to another of the layouts set with [`Msg::SetXkbConfig`].
+    SwitchXkbLayout {
+        layout: XkbLayoutSwitch,
+    },
+    /// Apply a libinput setting to matching devices, now and whenever they're plugged in.
+    ///
+    /// Only devices with the given name and of the given type match. Unset filters match all
+    /// devices.
+    SetLibinputSetting {
+        setting: LibinputSetting,
+        #[serde(default)]
+        device_name: Option<String>,
+        #[serde(default)]
+        device_type: Option<InputDeviceType>,
+    },
+    /// Map touch devices to an output, or unmap them if `output_name` is `None`.
+    ///
+    /// Without a `device_name`, this sets the output for touch devices that aren't mapped to one
+    /// by name. Unmapped devices are mapped to the first output. Clients can't change mappings
+    /// other clients set.
+    SetTouchOutput {
+        output_name: Option<String>,
+        #[serde(default)]
+        device_name: Option<String>,
+    },
+    /// Map drawing tablets to an output, or unmap them if `output_name` is `None`.
+    ///
+    /// This works like [`Msg::SetTouchOutput`].
+    SetTabletOutput {
+        output_name: Option<String>,
+        #[serde(default)]
+        device_name: Option<String>,
     },
 
     // Window management
@@ -130,9 +323,28 @@ pub enum Msg {
         value: String,
     },
 
+    // Events
+    /// Start receiving [`OutgoingMsg::Event`]s of the given kinds.
+    ///
+    /// Subscribing to [`EventKind::OutputConnected`] also sends the event once for every output
+    /// that is already connected.
+    Subscribe {
+        events: Vec<EventKind>,
+    },
+    /// Stop receiving [`OutgoingMsg::Event`]s of the given kinds.
+    Unsubscribe {
+        events: Vec<EventKind>,
+    },
+
     // Pinnacle management
     /// Quit the compositor.
     Quit,
+    /// The config finished setting itself up.
+    ///
+    /// Until a config sends this, its binds, rules, and tags are kept apart from the live ones,
+    /// and it replaces the old config only once this is received. Compositors that wait for this
+    /// list the `config_ready` feature.
+    ConfigReady,
 
     Request {
         request_id: RequestId,
@@ -140,6 +352,71 @@ pub enum Msg {
     },
 }
 
+impl Msg {
+    /// The name of this message's variant, used to identify it in error replies.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Msg::Hello { .. } => "Hello",
+            Msg::SetKeybind { .. } => "SetKeybind",
+            Msg::SetMousebind { .. } => "SetMousebind",
+            Msg::SetGestureBind { .. } => "SetGestureBind",
+            Msg::RemoveKeybind { .. } => "RemoveKeybind",
+            Msg::RemoveMousebind { .. } => "RemoveMousebind",
+            Msg::RemoveGestureBind { .. } => "RemoveGestureBind",
+            Msg::SetKeySequence { .. } => "SetKeySequence",
+            Msg::RemoveKeySequence { .. } => "RemoveKeySequence",
+            Msg::SetKeySequenceTimeout { .. } => "SetKeySequenceTimeout",
+            Msg::SetShortcutsInhibitEscape { .. } => "SetShortcutsInhibitEscape",
+            Msg::SetBindMode { .. } => "SetBindMode",
+            Msg::SetXkbConfig { .. } => "SetXkbConfig",
+            Msg::SetRepeatRate { .. } => "SetRepeatRate",
+            Msg::SwitchXkbLayout { .. } => "SwitchXkbLayout",
+            Msg::SetLibinputSetting { .. } => "SetLibinputSetting",
+            Msg::SetTouchOutput { .. } => "SetTouchOutput",
+            Msg::SetTabletOutput { .. } => "SetTabletOutput",
+            Msg::CloseWindow { .. } => "CloseWindow",
+            Msg::SetWindowSize { .. } => "SetWindowSize",
+            Msg::MoveWindowToTag { .. } => "MoveWindowToTag",
+            Msg::ToggleTagOnWindow { .. } => "ToggleTagOnWindow",
+            Msg::ToggleFloating { .. } => "ToggleFloating",
+            Msg::ToggleFullscreen { .. } => "ToggleFullscreen",
+            Msg::ToggleMaximized { .. } => "ToggleMaximized",
+            Msg::AddWindowRule { .. } => "AddWindowRule",
+            Msg::WindowMoveGrab { .. } => "WindowMoveGrab",
+            Msg::WindowResizeGrab { .. } => "WindowResizeGrab",
+            Msg::ToggleTag { .. } => "ToggleTag",
+            Msg::SwitchToTag { .. } => "SwitchToTag",
+            Msg::AddTags { .. } => "AddTags",
+            Msg::RemoveTags { .. } => "RemoveTags",
+            Msg::SetLayout { .. } => "SetLayout",
+            Msg::ConnectForAllOutputs { .. } => "ConnectForAllOutputs",
+            Msg::SetOutputLocation { .. } => "SetOutputLocation",
+            Msg::Spawn { .. } => "Spawn",
+            Msg::SetEnv { .. } => "SetEnv",
+            Msg::Subscribe { .. } => "Subscribe",
+            Msg::Unsubscribe { .. } => "Unsubscribe",
+            Msg::Quit => "Quit",
+            Msg::ConfigReady => "ConfigReady",
+            Msg::Request { .. } => "Request",
+        }
+    }
+
+    /// The callback id this message registers, if any.
+    ///
+    /// This is sent back in error replies so clients can tell which call failed.
+    pub fn callback_id(&self) -> Option<CallbackId> {
+        match self {
+            Msg::SetKeybind { callback_id, .. }
+            | Msg::SetMousebind { callback_id, .. }
+            | Msg::SetGestureBind { callback_id, .. }
+            | Msg::SetKeySequence { callback_id, .. }
+            | Msg::ConnectForAllOutputs { callback_id } => Some(*callback_id),
+            Msg::Spawn { callback_id, .. } => *callback_id,
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct RequestId(u32);
 
@@ -147,6 +424,14 @@ pub struct RequestId(u32);
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 /// Messages that require a server response, usually to provide some data.
 pub enum Request {
+    // Input
+    GetKeybinds,
+    GetMousebinds,
+    GetBindMode,
+    GetXkbLayout,
+    GetInputDevices,
+    GetTouchOutputs,
+    GetTabletOutputs,
     // Windows
     GetWindows,
     GetWindowProps { window_id: WindowId },
@@ -158,6 +443,138 @@ pub enum Request {
     GetTagProps { tag_id: TagId },
 }
 
+/// One step of a key sequence.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct KeyCombo {
+    pub modifiers: Vec<Modifier>,
+    pub key: KeyIntOrString,
+}
+
+/// A keybind, as returned by [`Request::GetKeybinds`].
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct KeybindInfo {
+    /// The bind mode this keybind works in.
+    pub mode: String,
+    pub modifiers: Vec<Modifier>,
+    /// The keysym that triggers this keybind.
+    pub key: u32,
+    /// The name of the keysym, like `"Return"` or `"a"`.
+    pub key_name: String,
+    pub edge: KeyEdge,
+    pub allow_repeat: bool,
+    /// The callback this keybind calls.
+    ///
+    /// This is only sent to the client that set the keybind, as it means nothing to others.
+    pub callback_id: Option<CallbackId>,
+}
+
+/// A mousebind, as returned by [`Request::GetMousebinds`].
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct MousebindInfo {
+    /// The bind mode this mousebind works in.
+    pub mode: String,
+    pub modifiers: Vec<Modifier>,
+    pub button: u32,
+    pub edge: MouseEdge,
+    /// The callback this mousebind calls.
+    ///
+    /// This is only sent to the client that set the mousebind.
+    pub callback_id: Option<CallbackId>,
+}
+
+/// A touchpad gesture that can be bound with [`Msg::SetGestureBind`].
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum Gesture {
+    Swipe(SwipeDirection),
+    Pinch(PinchDirection),
+    /// Rest fingers on the touchpad without moving them.
+    Hold,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum SwipeDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum PinchDirection {
+    /// Move fingers closer together.
+    In,
+    /// Spread fingers apart.
+    Out,
+}
+
+/// A kind of input device. A device can be more than one kind, like a keyboard with a touchpad.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum InputDeviceType {
+    Keyboard,
+    Pointer,
+    Touchpad,
+    Touch,
+    Tablet,
+}
+
+/// A libinput device setting, set with [`Msg::SetLibinputSetting`].
+#[derive(Debug, PartialEq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum LibinputSetting {
+    /// Tap the touchpad to click.
+    Tap(bool),
+    NaturalScroll(bool),
+    AccelProfile(AccelProfile),
+    /// The pointer acceleration speed, from -1.0 to 1.0.
+    AccelSpeed(f64),
+    /// Swap the left and right buttons.
+    LeftHanded(bool),
+    /// Disable the touchpad while typing.
+    DisableWhileTyping(bool),
+    ScrollMethod(ScrollMethod),
+    ClickMethod(ClickMethod),
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum AccelProfile {
+    /// No acceleration.
+    Flat,
+    /// Pointer speed changes with how fast the device moves.
+    Adaptive,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum ScrollMethod {
+    NoScroll,
+    TwoFinger,
+    Edge,
+    /// Scroll by moving the device while holding a button.
+    OnButtonDown,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+pub enum ClickMethod {
+    /// Which button is clicked depends on where the touchpad is pressed.
+    ButtonAreas,
+    /// Which button is clicked depends on how many fingers press the touchpad.
+    Clickfinger,
+}
+
+/// An input device, as returned by [`Request::GetInputDevices`].
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct InputDeviceInfo {
+    pub name: String,
+    pub types: Vec<InputDeviceType>,
+}
+
+/// A device mapped to an output, as returned by [`Request::GetTouchOutputs`] and
+/// [`Request::GetTabletOutputs`].
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct DeviceOutputInfo {
+    /// The device's name, or `None` for the mapping of devices without their own.
+    pub device_name: Option<String>,
+    pub output_name: String,
+}
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
 pub enum Modifier {
     Shift = 0b0000_0001,
@@ -212,7 +629,6 @@ impl From<ModifiersState> for ModifierMask {
 }
 
 impl ModifierMask {
-    #[allow(dead_code)]
     pub fn values(self) -> Vec<Modifier> {
         let mut res = Vec::<Modifier>::new();
         if self.0 & Modifier::Shift as u8 == Modifier::Shift as u8 {
@@ -234,6 +650,12 @@ impl ModifierMask {
 /// Messages sent from the server to the client.
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub enum OutgoingMsg {
+    /// The reply to a successful [`Msg::Hello`].
+    Hello {
+        protocol_version: u32,
+        /// See [`FEATURES`].
+        features: Vec<String>,
+    },
     CallCallback {
         callback_id: CallbackId,
         #[serde(default)]
@@ -243,6 +665,111 @@ pub enum OutgoingMsg {
         request_id: RequestId,
         response: RequestResponse,
     },
+    /// Something happened that the client subscribed to with [`Msg::Subscribe`].
+    Event {
+        event: Event,
+    },
+    /// A message from the client couldn't be handled.
+    Error {
+        /// The kind of message that failed, like `"MoveWindowToTag"`.
+        msg_kind: String,
+        /// Why it failed.
+        reason: String,
+        /// The callback id of the failed message, if it had one.
+        #[serde(default)]
+        callback_id: Option<CallbackId>,
+    },
+}
+
+impl OutgoingMsg {
+    /// The reply to a successful handshake.
+    pub fn hello() -> Self {
+        OutgoingMsg::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            features: FEATURES.iter().map(|feature| feature.to_string()).collect(),
+        }
+    }
+}
+
+/// The kinds of [`Event`]s a client can subscribe to.
+#[derive(Debug, Hash, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    WindowOpened,
+    WindowClosed,
+    FocusChanged,
+    TitleChanged,
+    TagAdded,
+    TagRemoved,
+    TagActivated,
+    LayoutChanged,
+    OutputConnected,
+    OutputDisconnected,
+    BindModeChanged,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub enum Event {
+    WindowOpened {
+        window_id: WindowId,
+    },
+    WindowClosed {
+        window_id: WindowId,
+    },
+    /// Keyboard focus moved. `window_id` is `None` if focus moved to something that isn't a window,
+    /// or to nothing at all.
+    FocusChanged {
+        #[serde(default)]
+        window_id: Option<WindowId>,
+    },
+    TitleChanged {
+        window_id: WindowId,
+        #[serde(default)]
+        title: Option<String>,
+    },
+    TagAdded {
+        tag_id: TagId,
+        output_name: String,
+    },
+    TagRemoved {
+        tag_id: TagId,
+    },
+    /// A tag was activated or deactivated.
+    TagActivated {
+        tag_id: TagId,
+        active: bool,
+    },
+    LayoutChanged {
+        tag_id: TagId,
+        layout: Layout,
+    },
+    OutputConnected {
+        output_name: String,
+    },
+    OutputDisconnected {
+        output_name: String,
+    },
+    /// The bind mode changed.
+    BindModeChanged {
+        mode: String,
+    },
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::WindowOpened { .. } => EventKind::WindowOpened,
+            Event::WindowClosed { .. } => EventKind::WindowClosed,
+            Event::FocusChanged { .. } => EventKind::FocusChanged,
+            Event::TitleChanged { .. } => EventKind::TitleChanged,
+            Event::TagAdded { .. } => EventKind::TagAdded,
+            Event::TagRemoved { .. } => EventKind::TagRemoved,
+            Event::TagActivated { .. } => EventKind::TagActivated,
+            Event::LayoutChanged { .. } => EventKind::LayoutChanged,
+            Event::OutputConnected { .. } => EventKind::OutputConnected,
+            Event::OutputDisconnected { .. } => EventKind::OutputDisconnected,
+            Event::BindModeChanged { .. } => EventKind::BindModeChanged,
+        }
+    }
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -261,10 +788,69 @@ pub enum Args {
     ConnectForAllOutputs {
         output_name: String,
     },
+    /// Sent to keybind and key sequence callbacks.
+    Keybind {
+        /// The keysym that triggered the keybind.
+        key: u32,
+        /// The modifiers that were held.
+        modifiers: Vec<Modifier>,
+        /// The window with keyboard focus, if any.
+        #[serde(default)]
+        window_id: Option<WindowId>,
+    },
+    /// Sent to mousebind callbacks.
+    Mousebind {
+        /// Where the pointer was, in global coordinates.
+        pointer_loc: (f64, f64),
+        /// The window under the pointer, if any.
+        #[serde(default)]
+        window_id: Option<WindowId>,
+        /// The output under the pointer, if any.
+        #[serde(default)]
+        output_name: Option<String>,
+    },
+    /// Sent to gesture bind callbacks.
+    Gesture {
+        /// Where the pointer was, in global coordinates.
+        pointer_loc: (f64, f64),
+        /// The window under the pointer, if any.
+        #[serde(default)]
+        window_id: Option<WindowId>,
+        /// The output under the pointer, if any.
+        #[serde(default)]
+        output_name: Option<String>,
+    },
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub enum RequestResponse {
+    /// The request couldn't be answered, like when it was for a window that doesn't exist.
+    Error {
+        reason: String,
+    },
+    Keybinds {
+        keybinds: Vec<KeybindInfo>,
+    },
+    Mousebinds {
+        mousebinds: Vec<MousebindInfo>,
+    },
+    BindMode {
+        mode: String,
+    },
+    XkbLayout {
+        /// The index of the active layout in the layouts that were set.
+        index: u32,
+        name: String,
+    },
+    InputDevices {
+        devices: Vec<InputDeviceInfo>,
+    },
+    TouchOutputs {
+        mappings: Vec<DeviceOutputInfo>,
+    },
+    TabletOutputs {
+        mappings: Vec<DeviceOutputInfo>,
+    },
     Window {
         window_id: Option<WindowId>,
     },
@@ -312,3 +898,47 @@ pub enum RequestResponse {
         output_name: Option<String>,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Msg, OutgoingMsg};
+
+    /// What the Rust API sends, recorded in its own tests.
+    const MSGS: &str = include_str!("../../api/rust/tests/fixtures/msgs.json");
+    /// What the Rust API must understand, recorded in its own tests.
+    const INCOMING_MSGS: &str = include_str!("../../api/rust/tests/fixtures/incoming_msgs.json");
+
+    /// Encode `msg` the way it's sent over the socket, then decode it as a `T`.
+    fn send_as<T: serde::de::DeserializeOwned>(msg: &impl serde::Serialize) -> T {
+        let bytes = rmp_serde::to_vec_named(msg).expect("failed to encode msg");
+        rmp_serde::from_slice(&bytes)
+            .unwrap_or_else(|err| panic!("failed to decode {}: {err}", std::any::type_name::<T>()))
+    }
+
+    /// The messages in a fixture file shared with the Rust API's tests.
+    fn fixtures(json: &str) -> Vec<serde_json::Value> {
+        serde_json::from_str(json).expect("fixture file isn't a JSON array")
+    }
+
+    #[test]
+    fn rust_api_msgs_are_understood() {
+        for fixture in fixtures(MSGS) {
+            let msg: Msg = send_as(&fixture);
+            assert_eq!(
+                serde_json::to_value(&msg).expect("failed to serialize msg"),
+                fixture,
+                "{fixture} changed on the way to Pinnacle"
+            );
+        }
+    }
+
+    #[test]
+    fn msgs_to_rust_api_match_fixtures() {
+        for fixture in fixtures(INCOMING_MSGS) {
+            let msg: OutgoingMsg =
+                serde_json::from_value(fixture.clone()).expect("fixture isn't a valid OutgoingMsg");
+            let sent: serde_json::Value = send_as(&msg);
+            assert_eq!(sent, fixture, "{msg:?} doesn't match its fixture");
+        }
+    }
+}
@@ -32,6 +32,7 @@ use crate::{
     window::WindowElement,
 };
 
+pub mod headless;
 pub mod udev;
 pub mod winit;
 
@@ -130,6 +131,7 @@ impl DmabufHandler for State {
     fn dmabuf_state(&mut self) -> &mut DmabufState {
         match &mut self.backend {
             Backend::Winit(winit) => &mut winit.dmabuf_state.0,
+            Backend::Headless(headless) => &mut headless.dmabuf_state,
             Backend::Udev(udev) => {
                 &mut udev
                     .dmabuf_state
@@ -152,6 +154,7 @@ impl DmabufHandler for State {
                 .import_dmabuf(&dmabuf, None)
                 .map(|_| ())
                 .map_err(|_| ImportError::Failed),
+            Backend::Headless(_) => Err(ImportError::Failed),
             Backend::Udev(udev) => udev
                 .gpu_manager
                 .single_renderer(&udev.primary_gpu)
@@ -0,0 +1,184 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! A backend that runs Pinnacle without a GPU or a display.
+//!
+//! Nothing is rendered. Instead, virtual outputs are created and frame callbacks are sent
+//! to clients at the outputs' refresh rate so they keep committing as if they were visible.
+//! This is useful for running configs and window management logic on machines with no
+//! graphical environment, like CI runners.
+
+use std::{path::Path, time::Duration};
+
+use smithay::{
+    desktop::{layer_map_for_output, utils::surface_primary_scanout_output},
+    output::{Output, PhysicalProperties, Subpixel},
+    reexports::{
+        calloop::{
+            timer::{TimeoutAction, Timer},
+            EventLoop,
+        },
+        wayland_server::{protocol::wl_surface::WlSurface, Display},
+    },
+    utils::{IsAlive, Transform},
+    wayland::dmabuf::DmabufState,
+};
+
+use crate::{
+    api::msg::Event,
+    state::{Backend, CalloopData, State},
+};
+
+use super::BackendData;
+
+mod gestures;
+
+/// The size of each virtual output.
+const OUTPUT_SIZE: (i32, i32) = (1920, 1080);
+/// The refresh rate of each virtual output, in millihertz.
+const OUTPUT_REFRESH: i32 = 60_000;
+
+pub struct Headless {
+    /// A dmabuf state with no global.
+    ///
+    /// There is no renderer to import dmabufs with, so this is only here to satisfy
+    /// [`DmabufHandler`](smithay::wayland::dmabuf::DmabufHandler).
+    pub dmabuf_state: DmabufState,
+}
+
+impl BackendData for Headless {
+    fn seat_name(&self) -> String {
+        "headless".to_string()
+    }
+
+    fn reset_buffers(&mut self, _output: &Output) {}
+
+    fn early_import(&mut self, _surface: &WlSurface) {}
+}
+
+/// Start Pinnacle with no GPU or display, using virtual outputs.
+///
+/// The number of outputs can be set with the `PINNACLE_HEADLESS_OUTPUTS` environment variable.
+/// It defaults to one. If `PINNACLE_HEADLESS_GESTURES` is set, touchpad gestures can be sent
+/// through a socket at that path.
+pub fn run_headless() -> anyhow::Result<()> {
+    let mut event_loop: EventLoop<CalloopData> = EventLoop::try_new()?;
+
+    let mut display: Display<State> = Display::new()?;
+    let display_handle = display.handle();
+
+    let output_count = std::env::var("PINNACLE_HEADLESS_OUTPUTS")
+        .ok()
+        .and_then(|count| count.parse::<u32>().ok())
+        .unwrap_or(1)
+        .max(1);
+
+    let mut state = State::init(
+        Backend::Headless(Headless {
+            dmabuf_state: DmabufState::new(),
+        }),
+        &mut display,
+        event_loop.get_signal(),
+        event_loop.handle(),
+    )?;
+
+    if let Some(path) = std::env::var_os("PINNACLE_HEADLESS_GESTURES") {
+        gestures::listen(Path::new(&path), &state.loop_handle)?;
+    }
+
+    let mode = smithay::output::Mode {
+        size: OUTPUT_SIZE.into(),
+        refresh: OUTPUT_REFRESH,
+    };
+
+    // Lay the outputs out left to right
+    let outputs = (1..=output_count)
+        .map(|i| {
+            let output = Output::new(
+                format!("HEADLESS-{i}"),
+                PhysicalProperties {
+                    size: (0, 0).into(),
+                    subpixel: Subpixel::Unknown,
+                    make: "Pinnacle".to_string(),
+                    model: "Headless".to_string(),
+                },
+            );
+            output.create_global::<State>(&display_handle);
+
+            let loc = (OUTPUT_SIZE.0 * (i as i32 - 1), 0);
+            output.change_current_state(
+                Some(mode),
+                Some(Transform::Normal),
+                None,
+                Some(loc.into()),
+            );
+            output.set_preferred(mode);
+            state.space.map_output(&output, loc);
+            state.signal_event(Event::OutputConnected {
+                output_name: output.name(),
+            });
+
+            output
+        })
+        .collect::<Vec<_>>();
+
+    state.focus_state.focused_output = outputs.first().cloned();
+
+    let frame_duration = Duration::from_secs_f64(1000.0 / OUTPUT_REFRESH as f64);
+
+    let insert_ret = state.loop_handle.insert_source(
+        Timer::immediate(),
+        move |_instant, _metadata, data| {
+            let display = &mut data.display;
+            let state = &mut data.state;
+
+            state.focus_state.fix_up_focus(&mut state.space);
+
+            let time = state.clock.now();
+
+            for output in outputs.iter() {
+                let space = &state.space;
+                let windows = space
+                    .elements()
+                    .filter(|win| win.alive() && space.outputs_for_element(win).contains(output));
+                for window in windows {
+                    window.send_frame(
+                        output,
+                        time,
+                        Some(Duration::ZERO),
+                        surface_primary_scanout_output,
+                    );
+                }
+
+                let map = layer_map_for_output(output);
+                for layer_surface in map.layers() {
+                    layer_surface.send_frame(
+                        output,
+                        time,
+                        Some(Duration::ZERO),
+                        surface_primary_scanout_output,
+                    );
+                }
+            }
+
+            state.space.refresh();
+            state.popup_manager.cleanup();
+            display
+                .flush_clients()
+                .expect("failed to flush client buffers");
+
+            TimeoutAction::ToDuration(frame_duration)
+        },
+    );
+
+    if let Err(err) = insert_ret {
+        anyhow::bail!("Failed to insert headless frame timer into event loop: {err}");
+    }
+
+    event_loop.run(
+        Some(Duration::from_millis(1)),
+        &mut CalloopData { display, state },
+        |_data| {},
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,227 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Scripted touchpad gestures for the headless backend.
+//!
+//! There's no touchpad without libinput, and no protocol for virtual gestures, so tests that
+//! need gestures set `PINNACLE_HEADLESS_GESTURES` to a socket path. Each line written to a
+//! connection on that socket is a JSON [`HeadlessGesture`], which is played back as the begin,
+//! update and end events of a real gesture.
+
+use std::{
+    io::{BufRead, BufReader},
+    os::unix::net::UnixListener,
+    path::Path,
+};
+
+use serde::Deserialize;
+use smithay::{
+    backend::input::{
+        Device, DeviceCapability, Event, GestureBeginEvent, GestureEndEvent, GestureHoldBeginEvent,
+        GestureHoldEndEvent, GesturePinchBeginEvent, GesturePinchEndEvent, GesturePinchUpdateEvent,
+        GestureSwipeBeginEvent, GestureSwipeEndEvent, GestureSwipeUpdateEvent, InputBackend,
+        InputEvent, UnusedEvent,
+    },
+    reexports::calloop::{channel, LoopHandle},
+};
+
+use crate::state::{CalloopData, State};
+
+/// A whole gesture, sent as one line of JSON.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum HeadlessGesture {
+    Swipe { fingers: u32, dx: f64, dy: f64 },
+    Pinch { fingers: u32, scale: f64 },
+    Hold { fingers: u32 },
+}
+
+/// Listen for gestures on the socket at `path` and play them back in order.
+pub fn listen(path: &Path, loop_handle: &LoopHandle<'static, CalloopData>) -> anyhow::Result<()> {
+    let _ = std::fs::remove_file(path);
+    let listener = UnixListener::bind(path)?;
+    let (sender, channel) = channel::channel::<HeadlessGesture>();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().filter_map(|stream| stream.ok()) {
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                match serde_json::from_str(&line) {
+                    Ok(gesture) => {
+                        if sender.send(gesture).is_err() {
+                            return;
+                        }
+                    }
+                    Err(err) => tracing::warn!("Invalid headless gesture {line:?}: {err}"),
+                }
+            }
+        }
+    });
+
+    if let Err(err) = loop_handle.insert_source(channel, |event, _, data| {
+        if let channel::Event::Msg(gesture) = event {
+            data.state.play_headless_gesture(gesture);
+        }
+    }) {
+        anyhow::bail!("Failed to insert headless gesture source into event loop: {err}");
+    }
+
+    Ok(())
+}
+
+impl State {
+    fn play_headless_gesture(&mut self, gesture: HeadlessGesture) {
+        let event = |fingers, dx, dy, scale| HeadlessGestureEvent {
+            fingers,
+            dx,
+            dy,
+            scale,
+        };
+
+        match gesture {
+            HeadlessGesture::Swipe { fingers, dx, dy } => {
+                let event = event(fingers, dx, dy, 1.0);
+                self.process_input_event(InputEvent::<HeadlessInput>::GestureSwipeBegin { event });
+                self.process_input_event(InputEvent::<HeadlessInput>::GestureSwipeUpdate { event });
+                self.process_input_event(InputEvent::<HeadlessInput>::GestureSwipeEnd { event });
+            }
+            HeadlessGesture::Pinch { fingers, scale } => {
+                let event = event(fingers, 0.0, 0.0, scale);
+                self.process_input_event(InputEvent::<HeadlessInput>::GesturePinchBegin { event });
+                self.process_input_event(InputEvent::<HeadlessInput>::GesturePinchUpdate { event });
+                self.process_input_event(InputEvent::<HeadlessInput>::GesturePinchEnd { event });
+            }
+            HeadlessGesture::Hold { fingers } => {
+                let event = event(fingers, 0.0, 0.0, 1.0);
+                self.process_input_event(InputEvent::<HeadlessInput>::GestureHoldBegin { event });
+                self.process_input_event(InputEvent::<HeadlessInput>::GestureHoldEnd { event });
+            }
+        }
+    }
+}
+
+/// The touchpad scripted gestures come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HeadlessTouchpad;
+
+impl Device for HeadlessTouchpad {
+    fn id(&self) -> String {
+        "headless-touchpad".to_string()
+    }
+
+    fn name(&self) -> String {
+        "Headless Touchpad".to_string()
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        capability == DeviceCapability::Gesture
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn syspath(&self) -> Option<std::path::PathBuf> {
+        None
+    }
+}
+
+/// The input backend for scripted gestures.
+#[derive(Debug)]
+pub struct HeadlessInput;
+
+impl InputBackend for HeadlessInput {
+    type Device = HeadlessTouchpad;
+
+    type KeyboardKeyEvent = UnusedEvent;
+    type PointerAxisEvent = UnusedEvent;
+    type PointerButtonEvent = UnusedEvent;
+    type PointerMotionEvent = UnusedEvent;
+    type PointerMotionAbsoluteEvent = UnusedEvent;
+
+    type GestureSwipeBeginEvent = HeadlessGestureEvent;
+    type GestureSwipeUpdateEvent = HeadlessGestureEvent;
+    type GestureSwipeEndEvent = HeadlessGestureEvent;
+    type GesturePinchBeginEvent = HeadlessGestureEvent;
+    type GesturePinchUpdateEvent = HeadlessGestureEvent;
+    type GesturePinchEndEvent = HeadlessGestureEvent;
+    type GestureHoldBeginEvent = HeadlessGestureEvent;
+    type GestureHoldEndEvent = HeadlessGestureEvent;
+
+    type TouchDownEvent = UnusedEvent;
+    type TouchUpEvent = UnusedEvent;
+    type TouchMotionEvent = UnusedEvent;
+    type TouchCancelEvent = UnusedEvent;
+    type TouchFrameEvent = UnusedEvent;
+
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+
+    type SpecialEvent = UnusedEvent;
+}
+
+/// Every stage of a scripted gesture. The whole motion happens in one update, and gestures are
+/// never cancelled.
+#[derive(Debug, Clone, Copy)]
+pub struct HeadlessGestureEvent {
+    fingers: u32,
+    dx: f64,
+    dy: f64,
+    scale: f64,
+}
+
+impl Event<HeadlessInput> for HeadlessGestureEvent {
+    fn time(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> HeadlessTouchpad {
+        HeadlessTouchpad
+    }
+}
+
+impl GestureBeginEvent<HeadlessInput> for HeadlessGestureEvent {
+    fn fingers(&self) -> u32 {
+        self.fingers
+    }
+}
+
+impl GestureEndEvent<HeadlessInput> for HeadlessGestureEvent {
+    fn cancelled(&self) -> bool {
+        false
+    }
+}
+
+impl GestureSwipeUpdateEvent<HeadlessInput> for HeadlessGestureEvent {
+    fn delta_x(&self) -> f64 {
+        self.dx
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.dy
+    }
+}
+
+impl GesturePinchUpdateEvent<HeadlessInput> for HeadlessGestureEvent {
+    fn delta_x(&self) -> f64 {
+        self.dx
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.dy
+    }
+
+    fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    fn rotation(&self) -> f64 {
+        0.0
+    }
+}
+
+impl GestureSwipeBeginEvent<HeadlessInput> for HeadlessGestureEvent {}
+impl GestureSwipeEndEvent<HeadlessInput> for HeadlessGestureEvent {}
+impl GesturePinchBeginEvent<HeadlessInput> for HeadlessGestureEvent {}
+impl GesturePinchEndEvent<HeadlessInput> for HeadlessGestureEvent {}
+impl GestureHoldBeginEvent<HeadlessInput> for HeadlessGestureEvent {}
+impl GestureHoldEndEvent<HeadlessInput> for HeadlessGestureEvent {}
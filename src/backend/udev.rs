@@ -26,6 +26,7 @@ use smithay::{
             DrmNode, DrmSurface, GbmBufferedSurface, NodeType,
         },
         egl::{self, EGLDevice, EGLDisplay},
+        input::InputEvent,
         libinput::{LibinputInputBackend, LibinputSessionInterface},
         renderer::{
             damage::{self, OutputDamageTracker},
@@ -84,7 +85,7 @@ use smithay_drm_extras::{
 };
 
 use crate::{
-    api::msg::{Args, OutgoingMsg},
+    api::msg::{Args, Event, OutgoingMsg},
     render::{pointer::PointerElement, CustomRenderElements},
     state::{
         take_presentation_feedback, Backend, CalloopData, State, SurfaceDmabufFeedback, WithState,
@@ -255,6 +256,15 @@ pub fn run_udev() -> anyhow::Result<()> {
         .handle()
         .insert_source(libinput_backend, move |event, _, data| {
             // println!("event: {:?}", event);
+            match &event {
+                InputEvent::DeviceAdded { device } => {
+                    data.state.add_libinput_device(device.clone());
+                }
+                InputEvent::DeviceRemoved { device } => {
+                    data.state.remove_libinput_device(device);
+                }
+                _ => (),
+            }
             data.state.process_input_event(event);
         });
 
@@ -930,30 +940,26 @@ impl State {
 
         self.schedule_initial_render(node, crtc, self.loop_handle.clone());
 
+        self.signal_event(Event::OutputConnected {
+            output_name: output.name(),
+        });
+
         // Run any connected callbacks
         {
             let clone = output.clone();
             self.schedule(
-                |dt| dt.state.api_state.stream.is_some(),
+                |dt| !dt.state.api_state.clients.is_empty(),
                 move |dt| {
-                    let stream = dt
-                        .state
-                        .api_state
-                        .stream
-                        .as_ref()
-                        .expect("Stream doesn't exist");
-                    let mut stream = stream.lock().expect("Couldn't lock stream");
-                    for callback_id in dt.state.output_callback_ids.iter() {
-                        crate::api::send_to_client(
-                            &mut stream,
+                    for &(client_id, callback_id) in dt.state.output_callback_ids.iter() {
+                        dt.state.api_state.send(
+                            client_id,
                             &OutgoingMsg::CallCallback {
-                                callback_id: *callback_id,
+                                callback_id,
                                 args: Some(Args::ConnectForAllOutputs {
                                     output_name: clone.name(),
                                 }),
                             },
-                        )
-                        .expect("Send to client failed");
+                        );
                     }
                 },
             );
@@ -989,6 +995,9 @@ impl State {
 
         if let Some(output) = output {
             self.space.unmap_output(&output);
+            self.signal_event(Event::OutputDisconnected {
+                output_name: output.name(),
+            });
         }
     }
 
@@ -1247,6 +1256,7 @@ impl State {
     }
 
     fn render_surface(&mut self, node: DrmNode, crtc: crtc::Handle) {
+        let text_input_origin = self.text_input_origin();
         let Backend::Udev(backend) = &mut self.backend else { unreachable!() };
 
         let Some(device) = backend.backends.get_mut(&node) else {
@@ -1342,6 +1352,7 @@ impl State {
             &mut renderer,
             &output,
             self.seat.input_method(),
+            text_input_origin,
             &pointer_image,
             &mut backend.pointer_element,
             self.pointer_location,
@@ -1447,6 +1458,7 @@ fn render_surface<'a>(
     renderer: &mut UdevRenderer<'a, '_>,
     output: &Output,
     input_method: &InputMethodHandle,
+    text_input_origin: Option<Point<i32, Logical>>,
     pointer_image: &TextureBuffer<MultiTexture>,
     pointer_element: &mut PointerElement<MultiTexture>,
     pointer_location: Point<f64, Logical>,
@@ -1487,6 +1499,7 @@ fn render_surface<'a>(
         renderer,
         output,
         input_method,
+        text_input_origin,
         pointer_element,
         Some(pointer_image),
     );
@@ -34,6 +34,7 @@ use smithay::{
 };
 
 use crate::{
+    api::msg::Event,
     render::pointer::PointerElement,
     state::{take_presentation_feedback, Backend, CalloopData, State, WithState},
 };
@@ -173,6 +174,9 @@ pub fn run_winit() -> anyhow::Result<()> {
         .update_formats(backend.backend.renderer().shm_formats());
 
     state.space.map_output(&output, (0, 0));
+    state.signal_event(Event::OutputConnected {
+        output_name: output.name(),
+    });
 
     if let Err(err) = state.xwayland.start(
         state.loop_handle.clone(),
@@ -267,6 +271,7 @@ pub fn run_winit() -> anyhow::Result<()> {
                     return TimeoutAction::ToDuration(Duration::from_millis(1));
                 }
 
+                let text_input_origin = state.text_input_origin();
                 let Backend::Winit(backend) = &mut state.backend else { unreachable!() };
                 let full_redraw = &mut backend.full_redraw;
                 *full_redraw = full_redraw.saturating_sub(1);
@@ -291,6 +296,7 @@ pub fn run_winit() -> anyhow::Result<()> {
                     backend.backend.renderer(),
                     &output,
                     state.seat.input_method(),
+                    text_input_origin,
                     &mut pointer_element,
                     None,
                 );
@@ -0,0 +1,196 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! The `pinnacle msg` and `pinnacle request` subcommands.
+//!
+//! These connect to a running instance's socket as another API client, so shell scripts and
+//! other tools can manage windows and tags without going through a config.
+//!
+//! Messages are given either as JSON in the same shape as [`Msg`] and [`Request`](crate::api::msg::Request),
+//! or as a message name followed by `key=value` fields. Field values are parsed as JSON if
+//! possible and used as strings otherwise.
+
+use std::{
+    io::{Read, Write},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    time::Duration,
+};
+
+use anyhow::Context;
+use serde_json::{json, Map, Value};
+
+use crate::api::{
+    msg::{Msg, PROTOCOL_VERSION},
+    DEFAULT_SOCKET_DIR, SOCKET_NAME,
+};
+
+/// How long to wait for Pinnacle to respond before giving up.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The request id used for every request sent from the command line.
+const REQUEST_ID: u32 = 0;
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    /// Send a message to a running Pinnacle instance
+    ///
+    /// The message can be JSON, like `pinnacle msg '{ "SwitchToTag": { "tag_id": 1 } }'`,
+    /// or a message name followed by its fields, like `pinnacle msg SwitchToTag tag_id=1`.
+    Msg {
+        /// The message to send
+        #[arg(required = true)]
+        msg: Vec<String>,
+    },
+    /// Request information from a running Pinnacle instance and print the response as JSON
+    ///
+    /// The request is given the same way as with `msg`, like `pinnacle request GetWindows`
+    /// or `pinnacle request GetWindowProps window_id=1`.
+    Request {
+        /// The request to send
+        #[arg(required = true)]
+        request: Vec<String>,
+    },
+}
+
+/// Run a subcommand, connecting to the instance at `$PINNACLE_SOCKET`.
+pub fn run(command: Command) -> anyhow::Result<()> {
+    let socket_path = socket_path();
+    let mut stream = UnixStream::connect(&socket_path)
+        .with_context(|| format!("Failed to connect to Pinnacle at {socket_path:?}"))?;
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .context("Failed to set socket read timeout")?;
+
+    send(
+        &mut stream,
+        &Msg::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            config_token: None,
+        },
+    )?;
+    let hello = read_msg(&mut stream)?;
+    if let Some(error) = hello.get("Error") {
+        anyhow::bail!(
+            "Pinnacle rejected the connection: {}",
+            error["reason"].as_str().unwrap_or("unknown reason")
+        );
+    }
+
+    match command {
+        Command::Msg { msg } => {
+            let msg: Msg =
+                serde_json::from_value(parse_args(&msg)?).context("Not a valid message")?;
+            let quit = matches!(msg, Msg::Quit);
+            send(&mut stream, &msg)?;
+
+            // Pinnacle closes the connection on quitting, so there's nothing to wait for
+            if quit {
+                return Ok(());
+            }
+
+            // Messages are handled in order, so by the time this request is answered, any error
+            // from the message above has already been sent.
+            send(&mut stream, &request(json!("GetOutputs"))?)?;
+            wait_for_response(&mut stream)?;
+        }
+        Command::Request { request: args } => {
+            send(&mut stream, &request(parse_args(&args)?)?)?;
+            let response = wait_for_response(&mut stream)?;
+            println!("{}", serde_json::to_string_pretty(&response)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the socket path from `$PINNACLE_SOCKET`, falling back to the same default socket path
+/// the Lua and Rust APIs use.
+fn socket_path() -> PathBuf {
+    std::env::var_os("PINNACLE_SOCKET")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SOCKET_DIR).join(SOCKET_NAME))
+}
+
+/// Turn command line arguments into the JSON form of a message.
+fn parse_args(args: &[String]) -> anyhow::Result<Value> {
+    let Some((name, fields)) = args.split_first() else {
+        anyhow::bail!("Nothing to send");
+    };
+
+    if name.starts_with('{') || name.starts_with('"') {
+        anyhow::ensure!(fields.is_empty(), "Fields can't be given after JSON");
+        return serde_json::from_str(name).context("Invalid JSON");
+    }
+
+    // Messages without fields are just their name
+    if fields.is_empty() {
+        return Ok(Value::String(name.clone()));
+    }
+
+    let fields = fields
+        .iter()
+        .map(|field| {
+            let (key, value) = field
+                .split_once('=')
+                .with_context(|| format!("`{field}` isn't of the form key=value"))?;
+            let value =
+                serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+            Ok((key.to_string(), value))
+        })
+        .collect::<anyhow::Result<Map<_, _>>>()?;
+
+    let mut msg = Map::new();
+    msg.insert(name.clone(), Value::Object(fields));
+    Ok(Value::Object(msg))
+}
+
+/// Wrap the JSON form of a request in a [`Msg::Request`].
+fn request(request: Value) -> anyhow::Result<Msg> {
+    serde_json::from_value(json!({
+        "Request": {
+            "request_id": REQUEST_ID,
+            "request": request,
+        }
+    }))
+    .context("Not a valid request")
+}
+
+fn send(stream: &mut UnixStream, msg: &Msg) -> anyhow::Result<()> {
+    let bytes = rmp_serde::to_vec_named(msg).context("Failed to encode message")?;
+    stream
+        .write_all(&(bytes.len() as u32).to_le_bytes())
+        .and_then(|_| stream.write_all(&bytes))
+        .context("Failed to send message to Pinnacle")
+}
+
+/// Read messages until a request response arrives, returning the response.
+///
+/// This fails if Pinnacle replies with an error first.
+fn wait_for_response(stream: &mut UnixStream) -> anyhow::Result<Value> {
+    loop {
+        let msg = read_msg(stream)?;
+
+        if let Some(response) = msg.get("RequestResponse") {
+            return Ok(response["response"].clone());
+        }
+        if let Some(error) = msg.get("Error") {
+            anyhow::bail!(
+                "Pinnacle couldn't handle {}: {}",
+                error["msg_kind"].as_str().unwrap_or("the message"),
+                error["reason"].as_str().unwrap_or("unknown reason"),
+            );
+        }
+    }
+}
+
+fn read_msg(stream: &mut UnixStream) -> anyhow::Result<Value> {
+    let mut len_bytes = [0u8; 4];
+    stream
+        .read_exact(&mut len_bytes)
+        .context("Failed to read from Pinnacle")?;
+    let mut msg_bytes = vec![0u8; u32::from_le_bytes(len_bytes) as usize];
+    stream
+        .read_exact(&mut msg_bytes)
+        .context("Failed to read from Pinnacle")?;
+    rmp_serde::from_slice(&msg_bytes).context("Pinnacle sent an invalid message")
+}
@@ -0,0 +1,645 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Supervision of the config process.
+//!
+//! The config is a separate process that connects to the API socket. A newly started config is
+//! *staged*: its keybinds, mousebinds, window rules, and tags are kept in a [`StagedConfig`]
+//! instead of replacing the live ones. Once it sends [`Msg::ConfigReady`](crate::api::msg::Msg::ConfigReady),
+//! the staged state is swapped in and the old config is killed. If it exits, disconnects, or
+//! takes longer than [`READY_TIMEOUT`] first, it's thrown away and the old config keeps running.
+//! Binds and window rules set by other clients are left alone when a config is swapped in.
+//!
+//! The config process is started with a random token in [`CONFIG_TOKEN_VAR`], which it sends
+//! back in its [`Hello`](crate::api::msg::Msg::Hello). That's how Pinnacle tells the config apart
+//! from other clients that connect while it starts up.
+//!
+//! If the live config exits or its connection closes, Pinnacle restarts it after a delay that
+//! doubles with every failure in a row. If it fails [`MAX_RESTARTS`] times without staying up
+//! for [`STABLE_DURATION`], Pinnacle gives up and switches to a built-in fallback config so the
+//! session stays usable: a terminal on the metaconfig's `terminal_keybind` (`Ctrl+Return` by
+//! default) plus the reload and kill keybinds from `metaconfig.toml`.
+
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    hash::Hash,
+    io::Read,
+    time::{Duration, Instant},
+};
+
+use smithay::{
+    input::keyboard::keysyms,
+    reexports::calloop::{
+        timer::{TimeoutAction, Timer},
+        LoopHandle, RegistrationToken,
+    },
+};
+
+use crate::{
+    api::{
+        msg::{
+            window_rules::{WindowRule, WindowRuleCondition},
+            CallbackId, Event, Modifier, ModifierMask,
+        },
+        ApiClientId,
+    },
+    input::{
+        default_shortcuts_inhibit_escape, libinput::LibinputRule, DeviceOutputs, GestureBinds,
+        KeyCombo, Keybinds, Mousebinds, XkbSettings, DEFAULT_BIND_MODE,
+        DEFAULT_KEY_SEQUENCE_TIMEOUT, DEFAULT_REPEAT_DELAY, DEFAULT_REPEAT_RATE,
+    },
+    state::{CalloopData, State, WithState},
+    tag::Tag,
+};
+
+/// The environment variable the config process gets its config token from.
+pub const CONFIG_TOKEN_VAR: &str = "PINNACLE_CONFIG_TOKEN";
+
+/// How often to check whether the config process exited.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long a new config has to send `ConfigReady` before it's given up on.
+const READY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How long a config has to stay up before its earlier failures are forgotten.
+const STABLE_DURATION: Duration = Duration::from_secs(10);
+
+/// How long to wait before the first restart. This doubles with every failure in a row.
+const RESTART_DELAY: Duration = Duration::from_millis(500);
+
+/// How many times to restart a failing config before falling back to the built-in one.
+const MAX_RESTARTS: u32 = 5;
+
+/// The terminal spawned by the fallback config if `$TERMINAL` isn't set.
+const FALLBACK_TERMINAL: &str = "alacritty";
+
+pub struct ConfigState {
+    /// The running config process.
+    pub process: Option<async_process::Child>,
+    /// The API client the config process connected as.
+    pub client_id: Option<ApiClientId>,
+    /// When the config became live.
+    pub started_at: Instant,
+    /// A config that was started but isn't ready yet.
+    pub staged: Option<StagedConfig>,
+    /// Whether a staged config's state is currently swapped in.
+    /// See [`State::with_staged_config`].
+    pub in_staged_context: bool,
+    /// How many times the config has failed in a row.
+    failures: u32,
+    /// The timer for a pending restart.
+    restart_timer: Option<RegistrationToken>,
+}
+
+/// A config that is starting up, along with everything it has set up so far.
+pub struct StagedConfig {
+    pub process: async_process::Child,
+    /// The token the config process was given in [`CONFIG_TOKEN_VAR`].
+    token: String,
+    /// The API client the config process connected as.
+    ///
+    /// This is the first client to send [`token`](Self::token) in its `Hello`.
+    pub client_id: Option<ApiClientId>,
+    pub reload_keybind: (ModifierMask, u32),
+    pub kill_keybind: (ModifierMask, u32),
+    pub keybinds: Keybinds,
+    pub bind_mode: String,
+    pub key_sequences: HashMap<(String, Vec<KeyCombo>), (ApiClientId, CallbackId)>,
+    pub key_sequence_timeout: Duration,
+    pub shortcuts_inhibit_escape: Option<(ModifierMask, u32)>,
+    pub xkb_settings: XkbSettings,
+    pub repeat_delay: i32,
+    pub repeat_rate: i32,
+    pub libinput_rules: Vec<LibinputRule>,
+    pub touch_outputs: DeviceOutputs,
+    pub tablet_outputs: DeviceOutputs,
+    pub mousebinds: Mousebinds,
+    pub gesture_binds: GestureBinds,
+    pub window_rules: Vec<(ApiClientId, WindowRuleCondition, WindowRule)>,
+    /// Tags by the name of the output they're on.
+    pub tags: HashMap<String, Vec<Tag>>,
+    timeout: RegistrationToken,
+}
+
+impl Default for ConfigState {
+    fn default() -> Self {
+        Self {
+            process: None,
+            client_id: None,
+            started_at: Instant::now(),
+            staged: None,
+            in_staged_context: false,
+            failures: 0,
+            restart_timer: None,
+        }
+    }
+}
+
+impl ConfigState {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Whether the given client is a staged config.
+    pub fn is_staged_client(&self, client_id: ApiClientId) -> bool {
+        self.staged
+            .as_ref()
+            .is_some_and(|staged| staged.client_id == Some(client_id))
+    }
+}
+
+impl StagedConfig {
+    /// Stage a newly started config process, giving it [`READY_TIMEOUT`] to get ready.
+    pub fn new(
+        process: async_process::Child,
+        token: String,
+        reload_keybind: (ModifierMask, u32),
+        kill_keybind: (ModifierMask, u32),
+        loop_handle: &LoopHandle<'static, CalloopData>,
+    ) -> anyhow::Result<Self> {
+        let timeout = loop_handle
+            .insert_source(Timer::from_duration(READY_TIMEOUT), |_, _, data| {
+                data.state.abort_staged_config(format!(
+                    "the config didn't finish setting up within {READY_TIMEOUT:?}"
+                ));
+                TimeoutAction::Drop
+            })
+            .map_err(|err| anyhow::anyhow!("Failed to insert config timeout: {err}"))?;
+
+        Ok(Self {
+            process,
+            token,
+            client_id: None,
+            reload_keybind,
+            kill_keybind,
+            keybinds: HashMap::new(),
+            bind_mode: DEFAULT_BIND_MODE.to_string(),
+            key_sequences: HashMap::new(),
+            key_sequence_timeout: DEFAULT_KEY_SEQUENCE_TIMEOUT,
+            shortcuts_inhibit_escape: Some(default_shortcuts_inhibit_escape()),
+            xkb_settings: XkbSettings::default(),
+            repeat_delay: DEFAULT_REPEAT_DELAY,
+            repeat_rate: DEFAULT_REPEAT_RATE,
+            libinput_rules: Vec::new(),
+            touch_outputs: HashMap::new(),
+            tablet_outputs: HashMap::new(),
+            mousebinds: HashMap::new(),
+            gesture_binds: HashMap::new(),
+            window_rules: Vec::new(),
+            tags: HashMap::new(),
+            timeout,
+        })
+    }
+}
+
+impl State {
+    /// Reload the config because the user asked for it.
+    pub fn reload_config(&mut self) {
+        self.config.failures = 0;
+        if let Some(token) = self.config.restart_timer.take() {
+            self.loop_handle.remove(token);
+        }
+
+        if let Err(err) = self.restart_config() {
+            self.reload_failed(format!("{err:#}"));
+        }
+    }
+
+    /// Stage a newly started config process.
+    ///
+    /// This replaces any config that is already staged.
+    pub fn stage_config(
+        &mut self,
+        process: async_process::Child,
+        token: String,
+        reload_keybind: (ModifierMask, u32),
+        kill_keybind: (ModifierMask, u32),
+    ) -> anyhow::Result<()> {
+        self.discard_staged_config();
+
+        let staged = StagedConfig::new(
+            process,
+            token,
+            reload_keybind,
+            kill_keybind,
+            &self.loop_handle,
+        )?;
+        self.config.staged = Some(staged);
+
+        Ok(())
+    }
+
+    /// Check whether a client that finished the handshake is the staged config.
+    ///
+    /// Only the first client to send the staged config's token is taken as the config.
+    pub fn identify_config_client(&mut self, client_id: ApiClientId, token: Option<String>) {
+        let Some(staged) = self.config.staged.as_mut() else { return };
+        if staged.client_id.is_none() && token.is_some_and(|token| token == staged.token) {
+            tracing::info!("API client {client_id:?} is the new config");
+            staged.client_id = Some(client_id);
+        }
+    }
+
+    /// Run `f` with the staged config's binds, rules, and tags in place of the live ones.
+    ///
+    /// Messages from a staged config are handled in here, so they see and change only what
+    /// the staged config has set up.
+    pub fn with_staged_config<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.swap_staged_config();
+        self.config.in_staged_context = true;
+        let ret = f(self);
+        self.config.in_staged_context = false;
+        self.swap_staged_config();
+        ret
+    }
+
+    fn swap_staged_config(&mut self) {
+        let Some(staged) = self.config.staged.as_mut() else { return };
+
+        std::mem::swap(&mut self.input_state.keybinds, &mut staged.keybinds);
+        std::mem::swap(&mut self.input_state.bind_mode, &mut staged.bind_mode);
+        std::mem::swap(
+            &mut self.input_state.key_sequences,
+            &mut staged.key_sequences,
+        );
+        std::mem::swap(
+            &mut self.input_state.key_sequence_timeout,
+            &mut staged.key_sequence_timeout,
+        );
+        std::mem::swap(
+            &mut self.input_state.shortcuts_inhibit_escape,
+            &mut staged.shortcuts_inhibit_escape,
+        );
+        std::mem::swap(&mut self.input_state.xkb_settings, &mut staged.xkb_settings);
+        std::mem::swap(&mut self.input_state.repeat_delay, &mut staged.repeat_delay);
+        std::mem::swap(&mut self.input_state.repeat_rate, &mut staged.repeat_rate);
+        std::mem::swap(
+            &mut self.input_state.libinput_rules,
+            &mut staged.libinput_rules,
+        );
+        std::mem::swap(
+            &mut self.input_state.touch_outputs,
+            &mut staged.touch_outputs,
+        );
+        std::mem::swap(
+            &mut self.input_state.tablet_outputs,
+            &mut staged.tablet_outputs,
+        );
+        std::mem::swap(&mut self.input_state.mousebinds, &mut staged.mousebinds);
+        std::mem::swap(
+            &mut self.input_state.gesture_binds,
+            &mut staged.gesture_binds,
+        );
+        std::mem::swap(&mut self.window_rules, &mut staged.window_rules);
+        for output in self.space.outputs() {
+            let tags = staged.tags.entry(output.name()).or_default();
+            output.with_state(|state| std::mem::swap(&mut state.tags, tags));
+        }
+    }
+
+    /// Swap in the staged config and kill the old one.
+    pub fn commit_staged_config(&mut self) {
+        let Some(mut staged) = self.config.staged.take() else { return };
+        self.loop_handle.remove(staged.timeout);
+
+        tracing::info!("New config is ready, replacing the old one");
+
+        if let Some(mut process) = self.config.process.take() {
+            if let Err(err) = process.kill() {
+                tracing::warn!("Error when killing old config: {err}");
+            }
+        }
+        let old_client_id = self.config.client_id.take();
+        if let Some(old_client_id) = old_client_id {
+            self.output_callback_ids
+                .retain(|(client_id, _)| *client_id != old_client_id);
+        }
+
+        merge_binds(
+            &mut self.input_state.keybinds,
+            staged.keybinds,
+            old_client_id,
+            |keybind| keybind.client_id,
+        );
+        merge_binds(
+            &mut self.input_state.mousebinds,
+            staged.mousebinds,
+            old_client_id,
+            |(client_id, _)| *client_id,
+        );
+        merge_binds(
+            &mut self.input_state.gesture_binds,
+            staged.gesture_binds,
+            old_client_id,
+            |(client_id, _)| *client_id,
+        );
+        merge_binds(
+            &mut self.input_state.key_sequences,
+            staged.key_sequences,
+            old_client_id,
+            |(client_id, _)| *client_id,
+        );
+        self.input_state.key_sequence_timeout = staged.key_sequence_timeout;
+        self.input_state.shortcuts_inhibit_escape = staged.shortcuts_inhibit_escape;
+        self.input_state.cancel_key_sequence();
+        self.input_state.reload_keybind = staged.reload_keybind;
+        self.input_state.kill_keybind = staged.kill_keybind;
+        self.input_state.terminal_keybind = None;
+        self.window_rules
+            .retain(|(client_id, _, _)| Some(*client_id) != old_client_id);
+        self.window_rules.extend(staged.window_rules);
+        self.set_bind_mode(staged.bind_mode);
+
+        if self.input_state.xkb_settings != staged.xkb_settings {
+            self.input_state.xkb_settings = staged.xkb_settings;
+            self.apply_xkb_settings();
+        }
+        self.input_state.repeat_delay = staged.repeat_delay;
+        self.input_state.repeat_rate = staged.repeat_rate;
+        self.apply_repeat_info();
+        if self.input_state.libinput_rules != staged.libinput_rules {
+            self.input_state.libinput_rules = staged.libinput_rules;
+            self.reapply_libinput_settings();
+        }
+        merge_binds(
+            &mut self.input_state.touch_outputs,
+            staged.touch_outputs,
+            old_client_id,
+            |(client_id, _)| *client_id,
+        );
+        merge_binds(
+            &mut self.input_state.tablet_outputs,
+            staged.tablet_outputs,
+            old_client_id,
+            |(client_id, _)| *client_id,
+        );
+
+        let outputs = self.space.outputs().cloned().collect::<Vec<_>>();
+        for output in outputs.iter() {
+            let new_tags = staged.tags.remove(&output.name()).unwrap_or_default();
+            let old_tags =
+                output.with_state(|state| std::mem::replace(&mut state.tags, new_tags.clone()));
+
+            // Keep windows on the tags with the same names as the ones they were on
+            for window in self.windows.iter() {
+                window.with_state(|state| {
+                    if !state.tags.iter().any(|tag| old_tags.contains(tag)) {
+                        return;
+                    }
+                    let mut tags = state
+                        .tags
+                        .iter()
+                        .filter_map(|tag| {
+                            if old_tags.contains(tag) {
+                                new_tags
+                                    .iter()
+                                    .find(|new| new.name() == tag.name())
+                                    .cloned()
+                            } else {
+                                Some(tag.clone())
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    if tags.is_empty() {
+                        tags.extend(new_tags.first().cloned());
+                    }
+                    state.tags = tags;
+                });
+            }
+
+            // The staged config's tag events were held back until now
+            for tag in old_tags.iter().filter(|tag| !new_tags.contains(tag)) {
+                self.signal_event(Event::TagRemoved { tag_id: tag.id() });
+            }
+            for tag in new_tags.iter().filter(|tag| !old_tags.contains(tag)) {
+                self.signal_event(Event::TagAdded {
+                    tag_id: tag.id(),
+                    output_name: output.name(),
+                });
+                if tag.active() {
+                    self.signal_event(Event::TagActivated {
+                        tag_id: tag.id(),
+                        active: true,
+                    });
+                }
+            }
+        }
+
+        self.config.process = Some(staged.process);
+        self.config.client_id = staged.client_id;
+        self.config.started_at = Instant::now();
+
+        for output in outputs.iter() {
+            self.update_windows(output);
+        }
+    }
+
+    /// Kill the staged config and throw away what it set up, if there is one.
+    fn discard_staged_config(&mut self) -> bool {
+        let Some(mut staged) = self.config.staged.take() else { return false };
+        self.loop_handle.remove(staged.timeout);
+        if let Err(err) = staged.process.kill() {
+            tracing::warn!("Error when killing staged config: {err}");
+        }
+        true
+    }
+
+    /// Throw away the staged config because it failed before it was ready.
+    pub fn abort_staged_config(&mut self, reason: impl Display) {
+        if self.discard_staged_config() {
+            self.reload_failed(reason);
+        }
+    }
+
+    /// Handle a new config failing to start.
+    ///
+    /// If the old config is still running, it's kept. Otherwise, this is a failure like any other.
+    pub fn reload_failed(&mut self, reason: impl Display) {
+        if self.config.process.is_some() {
+            tracing::error!("Reloading config failed: {reason}");
+            notify(&format!(
+                "Reloading your config failed ({reason}). The old config is still running."
+            ));
+        } else {
+            self.config_failed(reason);
+        }
+    }
+
+    /// Check whether the live or staged config process exited.
+    pub fn poll_config_process(&mut self) {
+        if let Some(staged) = self.config.staged.as_mut() {
+            match staged.process.try_status() {
+                Ok(None) => (),
+                Ok(Some(status)) => self.abort_staged_config(format!(
+                    "the config exited with {status} before it finished setting up"
+                )),
+                Err(err) => tracing::warn!("Failed to check staged config status: {err}"),
+            }
+        }
+
+        let Some(process) = self.config.process.as_mut() else { return };
+
+        match process.try_status() {
+            Ok(None) => (),
+            Ok(Some(status)) => {
+                self.config.process = None;
+                self.live_config_failed(format!("the config exited with {status}"));
+            }
+            Err(err) => tracing::warn!("Failed to check config process status: {err}"),
+        }
+    }
+
+    /// Handle a config's API connection closing.
+    ///
+    /// A config that can't talk to Pinnacle is as good as dead, so this kills and restarts it.
+    pub fn config_disconnected(&mut self, client_id: ApiClientId) {
+        if self.config.is_staged_client(client_id) {
+            self.abort_staged_config("the config disconnected before it finished setting up");
+            return;
+        }
+
+        if self.config.client_id != Some(client_id) {
+            return;
+        }
+        self.config.client_id = None;
+
+        let Some(mut process) = self.config.process.take() else { return };
+        if let Err(err) = process.kill() {
+            tracing::warn!("Failed to kill disconnected config: {err}");
+        }
+        self.live_config_failed("the config disconnected from the socket");
+    }
+
+    /// Handle the live config dying.
+    fn live_config_failed(&mut self, reason: impl Display) {
+        if self.config.started_at.elapsed() >= STABLE_DURATION {
+            self.config.failures = 0;
+        }
+        self.config_failed(reason);
+    }
+
+    /// Schedule a restart of the config after it failed, or fall back to the built-in config if
+    /// it keeps failing.
+    fn config_failed(&mut self, reason: impl Display) {
+        tracing::error!("Config failed: {reason}");
+
+        self.config.failures += 1;
+
+        if self.config.failures > MAX_RESTARTS {
+            let (modifiers, key) = self.start_fallback_config();
+            let modifiers = modifiers
+                .values()
+                .iter()
+                .map(|modifier| format!("{modifier:?}+"))
+                .collect::<String>();
+            let key = xkbcommon::xkb::keysym_get_name(key.into());
+            notify(&format!(
+                "Your config keeps failing ({reason}), so a minimal fallback config is running. \
+                Press {modifiers}{key} for a terminal, and fix your config then reload it."
+            ));
+            return;
+        }
+
+        if self.config.failures == 1 {
+            notify(&format!("Your config failed ({reason}). Restarting it."));
+        }
+
+        let delay = RESTART_DELAY * 2u32.pow(self.config.failures - 1);
+        tracing::info!("Restarting config in {delay:?}");
+
+        let timer = self
+            .loop_handle
+            .insert_source(Timer::from_duration(delay), |_, _, data| {
+                data.state.config.restart_timer = None;
+                if let Err(err) = data.state.restart_config() {
+                    data.state.reload_failed(format!("{err:#}"));
+                }
+                TimeoutAction::Drop
+            });
+
+        match timer {
+            Ok(token) => self.config.restart_timer = Some(token),
+            Err(err) => tracing::error!("Failed to schedule config restart: {err}"),
+        }
+    }
+
+    /// Set up the built-in fallback config, returning the keybind that opens a terminal.
+    fn start_fallback_config(&mut self) -> (ModifierMask, u32) {
+        tracing::warn!("Starting fallback config");
+
+        // The metaconfig may be what's broken, so don't give up on the terminal if it is
+        let terminal_keybind = crate::metaconfig::parse(&crate::state::get_config_dir())
+            .ok()
+            .and_then(|metaconfig| metaconfig.terminal_keybind)
+            .map(|keybind| (ModifierMask::from(keybind.modifiers), keybind.key as u32))
+            .unwrap_or((
+                ModifierMask::from(vec![Modifier::Ctrl]),
+                keysyms::KEY_Return,
+            ));
+        self.input_state.terminal_keybind = Some(terminal_keybind);
+        self.set_bind_mode(DEFAULT_BIND_MODE.to_string());
+
+        // Windows only show up on active tags, so give every output one
+        for output in self.space.outputs() {
+            output.with_state(|state| {
+                if state.tags.is_empty() {
+                    let tag = Tag::new("1".to_string());
+                    tag.set_active(true);
+                    state.tags.push(tag);
+                }
+            });
+        }
+
+        terminal_keybind
+    }
+}
+
+/// Spawn a terminal for the fallback config.
+pub fn spawn_terminal() {
+    let terminal = std::env::var("TERMINAL").unwrap_or(FALLBACK_TERMINAL.to_string());
+    if let Err(err) = async_process::Command::new(&terminal).spawn() {
+        tracing::error!("Failed to spawn {terminal}: {err}");
+    }
+}
+
+/// Show the user a desktop notification.
+///
+/// This is best-effort: without `notify-send` or a notification daemon, only the log has it.
+fn notify(body: &str) {
+    if let Err(err) = async_process::Command::new("notify-send")
+        .args(["--app-name=Pinnacle", "Pinnacle", body])
+        .spawn()
+    {
+        tracing::warn!("Failed to run notify-send: {err}");
+    }
+}
+
+/// Make a random token to tell the config process apart from other API clients.
+pub fn new_config_token() -> std::io::Result<String> {
+    let mut bytes = [0u8; 16];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Replace the old config's binds in `live` with the staged config's.
+///
+/// Binds other clients set are kept. If one of them clashes with a staged bind, the other
+/// client keeps it, the same as when a config tries to take a bind that's already set.
+/// Device output mappings are merged the same way.
+fn merge_binds<K, V>(
+    live: &mut HashMap<K, V>,
+    staged: HashMap<K, V>,
+    old_client_id: Option<ApiClientId>,
+    owner: impl Fn(&V) -> ApiClientId,
+) where
+    K: Eq + Hash + Debug,
+{
+    live.retain(|_, bind| Some(owner(bind)) != old_client_id);
+    for (key, bind) in staged {
+        if live.contains_key(&key) {
+            tracing::warn!("Not setting {key:?} from the new config: another client has it set");
+            continue;
+        }
+        live.insert(key, bind);
+    }
+}
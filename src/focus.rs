@@ -178,74 +178,154 @@ impl PointerTarget<State> for FocusTarget {
 
     fn gesture_swipe_begin(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureSwipeBeginEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureSwipeBeginEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_swipe_begin(window, seat, data, event);
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_swipe_begin(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_swipe_begin(surf, seat, data, event);
+            }
+        }
     }
 
     fn gesture_swipe_update(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureSwipeUpdateEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureSwipeUpdateEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_swipe_update(window, seat, data, event);
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_swipe_update(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_swipe_update(surf, seat, data, event);
+            }
+        }
     }
 
     fn gesture_swipe_end(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureSwipeEndEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureSwipeEndEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_swipe_end(window, seat, data, event);
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_swipe_end(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_swipe_end(surf, seat, data, event);
+            }
+        }
     }
 
     fn gesture_pinch_begin(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GesturePinchBeginEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GesturePinchBeginEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_pinch_begin(window, seat, data, event);
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_pinch_begin(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_pinch_begin(surf, seat, data, event);
+            }
+        }
     }
 
     fn gesture_pinch_update(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GesturePinchUpdateEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GesturePinchUpdateEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_pinch_update(window, seat, data, event);
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_pinch_update(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_pinch_update(surf, seat, data, event);
+            }
+        }
     }
 
     fn gesture_pinch_end(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GesturePinchEndEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GesturePinchEndEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_pinch_end(window, seat, data, event);
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_pinch_end(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_pinch_end(surf, seat, data, event);
+            }
+        }
     }
 
     fn gesture_hold_begin(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureHoldBeginEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureHoldBeginEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_hold_begin(window, seat, data, event);
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_hold_begin(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_hold_begin(surf, seat, data, event);
+            }
+        }
     }
 
     fn gesture_hold_end(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureHoldEndEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureHoldEndEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_hold_end(window, seat, data, event);
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_hold_end(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_hold_end(surf, seat, data, event);
+            }
+        }
     }
 }
 
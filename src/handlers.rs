@@ -5,11 +5,16 @@ mod xwayland;
 
 use smithay::{
     backend::renderer::utils,
-    delegate_compositor, delegate_data_device, delegate_fractional_scale, delegate_layer_shell,
-    delegate_output, delegate_presentation, delegate_primary_selection, delegate_relative_pointer,
-    delegate_seat, delegate_shm, delegate_viewporter,
+    delegate_compositor, delegate_data_device, delegate_fractional_scale,
+    delegate_input_method_manager, delegate_keyboard_shortcuts_inhibit, delegate_layer_shell,
+    delegate_output, delegate_pointer_constraints, delegate_pointer_gestures,
+    delegate_presentation, delegate_primary_selection, delegate_relative_pointer, delegate_seat,
+    delegate_shm, delegate_tablet_manager, delegate_text_input_manager, delegate_viewporter,
     desktop::{self, layer_map_for_output, PopupKind, WindowSurfaceType},
-    input::{pointer::CursorImageStatus, Seat, SeatHandler, SeatState},
+    input::{
+        pointer::{CursorImageStatus, PointerHandle},
+        Seat, SeatHandler, SeatState,
+    },
     output::Output,
     reexports::{
         calloop::Interest,
@@ -34,6 +39,11 @@ use smithay::{
         },
         dmabuf,
         fractional_scale::{self, FractionalScaleHandler},
+        keyboard_shortcuts_inhibit::{
+            KeyboardShortcutsInhibitHandler, KeyboardShortcutsInhibitState,
+            KeyboardShortcutsInhibitor,
+        },
+        pointer_constraints::{with_pointer_constraint, PointerConstraintsHandler},
         primary_selection::{
             self, set_primary_focus, PrimarySelectionHandler, PrimarySelectionState,
         },
@@ -48,6 +58,7 @@ use smithay::{
 };
 
 use crate::{
+    api::msg::Event,
     focus::FocusTarget,
     state::{CalloopData, ClientState, State, WithState},
     window::{window_state::LocationRequestState, WindowElement},
@@ -126,6 +137,19 @@ impl CompositorHandler for State {
                     self.space.map_element(window.clone(), new_pos, false);
                 }
             });
+
+            let title = window.title();
+            let title_changed = window.with_state(|state| {
+                let changed = state.title != title;
+                state.title = title.clone();
+                changed
+            });
+            if title_changed {
+                self.signal_event(Event::TitleChanged {
+                    window_id: window.with_state(|state| state.id),
+                    title,
+                });
+            }
         }
 
         // correct focus layering
@@ -328,9 +352,14 @@ impl SeatHandler for State {
     }
 
     fn focus_changed(&mut self, seat: &Seat<Self>, focused: Option<&Self::KeyboardFocus>) {
-        if let Some(win) =
-            focused.and_then(|focused| self.window_for_surface(&focused.wl_surface()?))
-        {
+        let focused_window =
+            focused.and_then(|focused| self.window_for_surface(&focused.wl_surface()?));
+        self.signal_event(Event::FocusChanged {
+            window_id: focused_window
+                .as_ref()
+                .map(|win| win.with_state(|state| state.id)),
+        });
+        if let Some(win) = focused_window {
             if let WindowElement::Wayland(win) = &win {
                 win.set_activated(true);
             }
@@ -411,6 +440,43 @@ delegate_fractional_scale!(State);
 
 delegate_relative_pointer!(State);
 
+delegate_pointer_gestures!(State);
+
+impl PointerConstraintsHandler for State {
+    fn new_constraint(&mut self, surface: &WlSurface, pointer: &PointerHandle<Self>) {
+        // Constraints on the surface under the pointer start right away. Others start once the
+        // pointer moves into them.
+        let focus = pointer.current_focus().and_then(|focus| focus.wl_surface());
+        if focus.as_ref() == Some(surface) {
+            with_pointer_constraint(surface, pointer, |constraint| {
+                if let Some(mut constraint) = constraint {
+                    constraint.activate();
+                }
+            });
+        }
+    }
+}
+delegate_pointer_constraints!(State);
+
+delegate_tablet_manager!(State);
+
+impl KeyboardShortcutsInhibitHandler for State {
+    fn keyboard_shortcuts_inhibit_state(&mut self) -> &mut KeyboardShortcutsInhibitState {
+        &mut self.keyboard_shortcuts_inhibit_state
+    }
+
+    fn new_inhibitor(&mut self, inhibitor: KeyboardShortcutsInhibitor) {
+        // Remote desktops and VMs get every key while they're focused, until the user presses
+        // the escape combo
+        inhibitor.activate();
+    }
+}
+delegate_keyboard_shortcuts_inhibit!(State);
+
+delegate_text_input_manager!(State);
+
+delegate_input_method_manager!(State);
+
 delegate_presentation!(State);
 
 impl WlrLayerShellHandler for State {
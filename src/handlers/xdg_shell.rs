@@ -24,6 +24,7 @@ use smithay::{
 };
 
 use crate::{
+    api::msg::Event,
     focus::FocusTarget,
     state::{State, WithState},
     window::{window_state::LocationRequestState, WindowElement},
@@ -43,8 +44,10 @@ impl XdgShellHandler for State {
         });
 
         let window = WindowElement::Wayland(Window::new(surface.clone()));
+        let title = window.title();
 
         window.with_state(|state| {
+            state.title = title;
             state.tags = match (
                 &self.focus_state.focused_output,
                 self.space.outputs().next(),
@@ -69,6 +72,10 @@ impl XdgShellHandler for State {
         // TODO: fix it so that reordering this doesn't break stuff
         self.windows.push(window.clone());
 
+        self.signal_event(Event::WindowOpened {
+            window_id: window.with_state(|state| state.id),
+        });
+
         self.space.map_element(window.clone(), (0, 0), true);
 
         let win_clone = window.clone();
@@ -116,6 +123,15 @@ impl XdgShellHandler for State {
 
     fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
         tracing::debug!("toplevel destroyed");
+        if let Some(window) = self.windows.iter().find(|window| {
+            window
+                .wl_surface()
+                .is_some_and(|surf| &surf == surface.wl_surface())
+        }) {
+            self.signal_event(Event::WindowClosed {
+                window_id: window.with_state(|state| state.id),
+            });
+        }
         self.windows.retain(|window| {
             window
                 .wl_surface()
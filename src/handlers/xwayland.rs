@@ -19,6 +19,7 @@ use smithay::{
 };
 
 use crate::{
+    api::msg::Event,
     focus::FocusTarget,
     state::{CalloopData, WithState},
     window::{window_state::FloatingOrTiled, WindowElement},
@@ -96,7 +97,9 @@ impl XwmHandler for CalloopData {
         // TODO: this is a duplicate of the code in new_toplevel,
         // |     move into its own function
         {
+            let title = window.title();
             window.with_state(|state| {
+                state.title = title;
                 state.tags = match (
                     &self.state.focus_state.focused_output,
                     self.state.space.outputs().next(),
@@ -127,6 +130,10 @@ impl XwmHandler for CalloopData {
 
             self.state.windows.push(window.clone());
 
+            self.state.signal_event(Event::WindowOpened {
+                window_id: window.with_state(|state| state.id),
+            });
+
             // FIXME: this breaks window closing if the popup is focused
             self.state.focus_state.set_focus(window.clone());
 
@@ -237,6 +244,9 @@ impl XwmHandler for CalloopData {
         tracing::debug!("{win:?}");
         if let Some(win) = win {
             tracing::debug!("removing x11 window from windows");
+            self.state.signal_event(Event::WindowClosed {
+                window_id: win.with_state(|state| state.id),
+            });
             self.state
                 .windows
                 .retain(|elem| win.wl_surface() != elem.wl_surface());
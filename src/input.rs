@@ -1,39 +1,258 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use crate::{
-    api::msg::{CallbackId, Modifier, ModifierMask, MouseEdge, OutgoingMsg},
+    api::{
+        msg::{
+            Args, CallbackId, Event as ApiEvent, Gesture, KeyEdge, Modifier, ModifierMask,
+            MouseEdge, OutgoingMsg,
+        },
+        ApiClientId,
+    },
     focus::FocusTarget,
     state::{Backend, WithState},
-    window::WindowElement,
+    window::{window_state::WindowId, WindowElement},
 };
 use smithay::{
     backend::{
         input::{
-            AbsolutePositionEvent, Axis, AxisSource, ButtonState, Event, InputBackend, InputEvent,
-            KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionEvent,
+            AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, Event, InputBackend,
+            InputEvent, KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent,
+            PointerMotionEvent,
         },
         session::Session,
     },
     desktop::{layer_map_for_output, space::SpaceElement},
     input::{
-        keyboard::{keysyms, FilterResult},
-        pointer::{AxisFrame, ButtonEvent, MotionEvent},
+        keyboard::{keysyms, FilterResult, KeyboardHandle, Layout, XkbConfig},
+        pointer::{AxisFrame, ButtonEvent, MotionEvent, PointerHandle, RelativeMotionEvent},
+        touch::TouchHandle,
+    },
+    reexports::{
+        calloop::{
+            timer::{TimeoutAction, Timer},
+            RegistrationToken,
+        },
+        input::Device as LibinputDevice,
+        wayland_server::protocol::wl_surface::WlSurface,
+    },
+    utils::{Logical, Point, Serial, SERIAL_COUNTER},
+    wayland::{
+        keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitorSeat, seat::WaylandFocus,
+        shell::wlr_layer,
     },
-    utils::{Logical, Point, SERIAL_COUNTER},
-    wayland::{seat::WaylandFocus, shell::wlr_layer},
 };
+use xkbcommon::xkb;
 
 use crate::state::State;
 
+use self::{gesture::ActiveGesture, libinput::LibinputRule, pointer_constraint::ActiveConstraint};
+
+pub mod gesture;
+pub mod input_method;
+pub mod libinput;
+pub mod pointer_constraint;
+pub mod tablet;
+pub mod touch;
+pub mod virtual_input;
+
+/// The bind mode keybinds are in unless a client sets another one.
+pub const DEFAULT_BIND_MODE: &str = "default";
+
+/// How long to wait for the next key of a key sequence unless a client sets another timeout.
+pub const DEFAULT_KEY_SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// How long a key must be held before it starts repeating, in milliseconds.
+pub const DEFAULT_REPEAT_DELAY: i32 = 200;
+
+/// How many times a held key repeats per second.
+pub const DEFAULT_REPEAT_RATE: i32 = 25;
+
+/// A modifier key and keysym pressed together.
+pub type KeyCombo = (ModifierMask, u32);
+
+/// Bind modes, modifier keys, keysyms, and edges, and the keybinds they trigger.
+pub type Keybinds = HashMap<(String, ModifierMask, u32, KeyEdge), Keybind>;
+
+/// Bind modes, modifier keys, and mouse button codes and edges, and the client and callback IDs
+/// they call.
+pub type Mousebinds = HashMap<(String, ModifierMask, u32, MouseEdge), (ApiClientId, CallbackId)>;
+
+/// Bind modes, modifier keys, finger counts, and gestures, and the client and callback IDs
+/// they call.
+pub type GestureBinds = HashMap<(String, ModifierMask, u32, Gesture), (ApiClientId, CallbackId)>;
+
+/// Device names, and the clients that mapped them and the names of the outputs they're mapped
+/// to. The `None` entry is used for devices without their own entry.
+pub type DeviceOutputs = HashMap<Option<String>, (ApiClientId, String)>;
+
+/// What a keybind calls.
+#[derive(Debug, Clone, Copy)]
+pub struct Keybind {
+    pub client_id: ApiClientId,
+    pub callback_id: CallbackId,
+    /// Whether to call the callback again on every key repeat while the key is held.
+    pub allow_repeat: bool,
+}
+
+/// A keybind that is repeating while its key is held down.
+#[derive(Debug)]
+struct KeyRepeat {
+    keycode: u32,
+    client_id: ApiClientId,
+    callback_id: CallbackId,
+    timer: RegistrationToken,
+}
+
+/// A key that is currently held down.
+#[derive(Debug)]
+struct PressedKey {
+    /// The modifiers held when the key was pressed.
+    modifier_mask: ModifierMask,
+    syms: Vec<u32>,
+    /// Whether the press triggered something instead of being sent to clients.
+    intercepted: bool,
+}
+
+/// The names xkb builds the keymap from, and which of its layouts is active.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XkbSettings {
+    pub rules: String,
+    pub model: String,
+    /// A comma separated list of layouts.
+    pub layout: String,
+    /// A comma separated list of variants, one for each layout.
+    pub variant: String,
+    pub options: Option<String>,
+    /// The index of the active layout in `layout`.
+    pub active_layout: usize,
+    /// The human readable names of the keymap's layouts, like "English (US)".
+    layout_names: Vec<String>,
+}
+
+impl Default for XkbSettings {
+    fn default() -> Self {
+        let empty = Self {
+            rules: String::new(),
+            model: String::new(),
+            layout: String::new(),
+            variant: String::new(),
+            options: None,
+            active_layout: 0,
+            layout_names: Vec::new(),
+        };
+
+        // Empty names are xkb's defaults, which only fail without xkb's data files installed
+        Self::new(
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            None,
+        )
+        .unwrap_or(empty)
+    }
+}
+
+impl XkbSettings {
+    /// Make settings from xkb names, with the first layout active.
+    ///
+    /// This returns `None` if xkb can't build a keymap from them.
+    pub fn new(
+        rules: String,
+        model: String,
+        layout: String,
+        variant: String,
+        options: Option<String>,
+    ) -> Option<Self> {
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let keymap = xkb::Keymap::new_from_names(
+            &context,
+            &rules,
+            &model,
+            &layout,
+            &variant,
+            options.clone(),
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        )?;
+        let layout_names = (0..keymap.num_layouts())
+            .map(|index| keymap.layout_get_name(index).to_string())
+            .collect();
+
+        Some(Self {
+            rules,
+            model,
+            layout,
+            variant,
+            options,
+            active_layout: 0,
+            layout_names,
+        })
+    }
+
+    /// How many layouts the keymap has.
+    pub fn layout_count(&self) -> usize {
+        self.layout_names.len().max(1)
+    }
+
+    /// The human readable name of the active layout, like "English (US)".
+    pub fn active_layout_name(&self) -> Option<&str> {
+        self.layout_names
+            .get(self.active_layout)
+            .map(String::as_str)
+    }
+}
+
 pub struct InputState {
-    /// A hashmap of modifier keys and keycodes to callback IDs
-    pub keybinds: HashMap<(ModifierMask, u32), CallbackId>,
-    /// A hashmap of modifier keys and mouse button codes to callback IDs
-    pub mousebinds: HashMap<(ModifierMask, u32, MouseEdge), CallbackId>,
+    pub keybinds: Keybinds,
+    pub mousebinds: Mousebinds,
+    pub gesture_binds: GestureBinds,
+    /// The gesture in progress, if a gesture bind intercepted it.
+    active_gesture: Option<ActiveGesture>,
+    /// The surface that locked the pointer, and where it wants the pointer to be once the lock
+    /// ends.
+    pointer_lock_hint: Option<(WlSurface, Point<f64, Logical>)>,
     pub reload_keybind: (ModifierMask, u32),
     pub kill_keybind: (ModifierMask, u32),
+    /// The keybind that spawns a terminal. This is only set by the fallback config.
+    pub terminal_keybind: Option<(ModifierMask, u32)>,
+    /// The key combo that turns a focused window's keyboard shortcuts inhibitor off and on.
+    /// While an inhibitor is on, only this, the kill keybind, and VT switching still work.
+    pub shortcuts_inhibit_escape: Option<(ModifierMask, u32)>,
+    /// The current bind mode. Only keybinds in this mode are triggered.
+    pub bind_mode: String,
+    /// A hashmap of bind modes and sequences of key combos to the client and callback IDs they call
+    pub key_sequences: HashMap<(String, Vec<KeyCombo>), (ApiClientId, CallbackId)>,
+    /// How long to wait for the next key of a key sequence before giving up on it.
+    pub key_sequence_timeout: Duration,
+    /// The keys of a key sequence pressed so far, and when the last one was pressed.
+    pending_sequence: Vec<KeyCombo>,
+    last_sequence_key: Instant,
+    /// Keys that are held down, by keycode.
+    pressed_keys: HashMap<u32, PressedKey>,
+    /// The keybind repeating while its key is held, if any.
+    key_repeat: Option<KeyRepeat>,
+    /// How long a key must be held before it starts repeating, in milliseconds.
+    pub repeat_delay: i32,
+    /// How many times a held key repeats per second.
+    pub repeat_rate: i32,
+    pub xkb_settings: XkbSettings,
+    /// Libinput settings in the order they were set. Later ones take precedence.
+    pub libinput_rules: Vec<LibinputRule>,
+    /// Libinput devices that are plugged in. These only exist on the udev backend.
+    pub libinput_devices: Vec<LibinputDevice>,
+    /// Which outputs touch devices are mapped to.
+    pub touch_outputs: DeviceOutputs,
+    /// Which outputs drawing tablets are mapped to.
+    pub tablet_outputs: DeviceOutputs,
+    /// The keymap of the virtual keyboard that last sent a key, while it replaces the seat's.
+    virtual_keymap: Option<String>,
+    /// Modifiers a virtual keyboard holds for the key it's sending.
+    virtual_modifiers: Vec<Modifier>,
 }
 
 impl InputState {
@@ -41,32 +260,166 @@ impl InputState {
         Self {
             keybinds: HashMap::new(),
             mousebinds: HashMap::new(),
+            gesture_binds: HashMap::new(),
+            active_gesture: None,
+            pointer_lock_hint: None,
             reload_keybind,
             kill_keybind,
+            terminal_keybind: None,
+            shortcuts_inhibit_escape: Some(default_shortcuts_inhibit_escape()),
+            bind_mode: DEFAULT_BIND_MODE.to_string(),
+            key_sequences: HashMap::new(),
+            key_sequence_timeout: DEFAULT_KEY_SEQUENCE_TIMEOUT,
+            pending_sequence: Vec::new(),
+            last_sequence_key: Instant::now(),
+            pressed_keys: HashMap::new(),
+            key_repeat: None,
+            repeat_delay: DEFAULT_REPEAT_DELAY,
+            repeat_rate: DEFAULT_REPEAT_RATE,
+            xkb_settings: XkbSettings::default(),
+            libinput_rules: Vec::new(),
+            libinput_devices: Vec::new(),
+            touch_outputs: HashMap::new(),
+            tablet_outputs: HashMap::new(),
+            virtual_keymap: None,
+            virtual_modifiers: Vec::new(),
+        }
+    }
+
+    /// Forget the keys of a partially pressed key sequence.
+    pub fn cancel_key_sequence(&mut self) {
+        self.pending_sequence.clear();
+    }
+
+    /// Feed a key press to the key sequence state machine.
+    ///
+    /// `syms` are the keysyms the press could be matched with, in order of preference.
+    /// This returns the action to take if the press continued or completed a sequence.
+    /// Otherwise, any partially pressed sequence is cancelled and the press should be handled
+    /// as usual.
+    fn advance_key_sequence(
+        &mut self,
+        modifier_mask: ModifierMask,
+        syms: &[u32],
+    ) -> Option<KeyAction> {
+        if self.last_sequence_key.elapsed() > self.key_sequence_timeout {
+            self.pending_sequence.clear();
         }
+
+        for &sym in syms {
+            let mut candidate = self.pending_sequence.clone();
+            candidate.push((modifier_mask, sym));
+
+            let key = (self.bind_mode.clone(), candidate);
+            if let Some(&(client_id, callback_id)) = self.key_sequences.get(&key) {
+                self.pending_sequence.clear();
+                return Some(KeyAction::CallCallback(
+                    client_id,
+                    callback_id,
+                    (modifier_mask, sym),
+                ));
+            }
+
+            let (_, candidate) = key;
+            let is_prefix = self.key_sequences.keys().any(|(mode, sequence)| {
+                *mode == self.bind_mode && sequence.starts_with(&candidate)
+            });
+            if is_prefix {
+                self.pending_sequence = candidate;
+                self.last_sequence_key = Instant::now();
+                return Some(KeyAction::Swallow);
+            }
+        }
+
+        self.pending_sequence.clear();
+        None
     }
 }
 
+/// The VT that `keysym` switches to, if it's one of the `XF86Switch_VT` keys.
+fn vt_for_keysym(keysym: u32) -> Option<i32> {
+    (keysyms::KEY_XF86Switch_VT_1..=keysyms::KEY_XF86Switch_VT_12)
+        .contains(&keysym)
+        .then(|| (keysym - keysyms::KEY_XF86Switch_VT_1 + 1) as i32)
+}
+
+/// The key combo that turns a shortcuts inhibitor off and on, unless a config sets another.
+pub fn default_shortcuts_inhibit_escape() -> KeyCombo {
+    (
+        ModifierMask::from(vec![Modifier::Super]),
+        keysyms::KEY_Escape,
+    )
+}
+
+/// Whether `keysym` is a modifier key, like `Shift_L` or `Super_R`.
+fn is_modifier_keysym(keysym: u32) -> bool {
+    (keysyms::KEY_Shift_L..=keysyms::KEY_Hyper_R).contains(&keysym)
+        || keysym == keysyms::KEY_ISO_Level3_Shift
+}
+
 #[derive(Debug)]
 enum KeyAction {
-    CallCallback(CallbackId),
+    /// Call a keybind's callback, passing it the key combo that triggered it.
+    CallCallback(ApiClientId, CallbackId, KeyCombo),
     Quit,
+    SpawnTerminal,
     SwitchVt(i32),
     ReloadConfig,
+    /// Call the callback now, then on every key repeat until the key is released.
+    RepeatCallback(ApiClientId, CallbackId, KeyCombo),
+    LeaveBindMode,
+    /// Turn the focused surface's keyboard shortcuts inhibitor off or back on.
+    ToggleShortcutsInhibit,
+    /// Don't send the key to clients, and do nothing else.
+    Swallow,
 }
 
 impl State {
+    /// Get the seat's keyboard, which it's given when it's created.
+    pub fn keyboard_handle(&self) -> KeyboardHandle<State> {
+        self.seat.get_keyboard().expect("seat has no keyboard")
+    }
+
+    /// Get the seat's pointer, which it's given when it's created.
+    pub fn pointer_handle(&self) -> PointerHandle<State> {
+        self.seat.get_pointer().expect("seat has no pointer")
+    }
+
+    /// Get the seat's touch handle, which it's given when it's created.
+    pub fn touch_handle(&self) -> TouchHandle<State> {
+        self.seat.get_touch().expect("seat has no touch")
+    }
+
     pub fn process_input_event<B: InputBackend>(&mut self, event: InputEvent<B>) {
         match event {
             // TODO: rest of input events
-
-            // InputEvent::DeviceAdded { device } => todo!(),
-            // InputEvent::DeviceRemoved { device } => todo!(),
-            InputEvent::Keyboard { event } => self.keyboard::<B>(event),
+            InputEvent::DeviceAdded { device } => self.tablet_device_added(&device),
+            InputEvent::DeviceRemoved { device } => self.tablet_device_removed(&device),
+            InputEvent::Keyboard { event } => {
+                self.restore_keymap();
+                self.keyboard::<B>(event);
+            }
             InputEvent::PointerMotion { event } => self.pointer_motion::<B>(event),
             InputEvent::PointerMotionAbsolute { event } => self.pointer_motion_absolute::<B>(event),
             InputEvent::PointerButton { event } => self.pointer_button::<B>(event),
             InputEvent::PointerAxis { event } => self.pointer_axis::<B>(event),
+            InputEvent::GestureSwipeBegin { event } => self.gesture_swipe_begin::<B>(event),
+            InputEvent::GestureSwipeUpdate { event } => self.gesture_swipe_update::<B>(event),
+            InputEvent::GestureSwipeEnd { event } => self.gesture_swipe_end::<B>(event),
+            InputEvent::GesturePinchBegin { event } => self.gesture_pinch_begin::<B>(event),
+            InputEvent::GesturePinchUpdate { event } => self.gesture_pinch_update::<B>(event),
+            InputEvent::GesturePinchEnd { event } => self.gesture_pinch_end::<B>(event),
+            InputEvent::GestureHoldBegin { event } => self.gesture_hold_begin::<B>(event),
+            InputEvent::GestureHoldEnd { event } => self.gesture_hold_end::<B>(event),
+            InputEvent::TouchDown { event } => self.touch_down::<B>(event),
+            InputEvent::TouchMotion { event } => self.touch_motion::<B>(event),
+            InputEvent::TouchUp { event } => self.touch_up::<B>(event),
+            InputEvent::TouchCancel { event } => self.touch_cancel::<B>(event),
+            InputEvent::TouchFrame { event } => self.touch_frame::<B>(event),
+            InputEvent::TabletToolAxis { event } => self.tablet_tool_axis::<B>(event),
+            InputEvent::TabletToolProximity { event } => self.tablet_tool_proximity::<B>(event),
+            InputEvent::TabletToolTip { event } => self.tablet_tool_tip::<B>(event),
+            InputEvent::TabletToolButton { event } => self.tablet_tool_button::<B>(event),
 
             _ => (),
         }
@@ -147,14 +500,100 @@ impl State {
             })
     }
 
+    /// Switch to another bind mode, telling clients about it if it changed.
+    pub fn set_bind_mode(&mut self, mode: String) {
+        if self.input_state.bind_mode == mode {
+            return;
+        }
+
+        tracing::info!("Entering bind mode {mode}");
+        self.input_state.bind_mode = mode.clone();
+        self.input_state.cancel_key_sequence();
+
+        // A staged config's mode is only announced once it's committed
+        if !self.config.in_staged_context {
+            self.signal_event(ApiEvent::BindModeChanged { mode });
+        }
+    }
+
+    /// Rebuild the keyboard's keymap from the current [`XkbSettings`].
+    ///
+    /// A staged config's settings are only applied once it's committed.
+    pub fn apply_xkb_settings(&mut self) {
+        if self.config.in_staged_context {
+            return;
+        }
+
+        self.input_state.virtual_keymap = None;
+        let settings = self.input_state.xkb_settings.clone();
+        let xkb_config = XkbConfig {
+            rules: &settings.rules,
+            model: &settings.model,
+            layout: &settings.layout,
+            variant: &settings.variant,
+            options: settings.options.clone(),
+        };
+
+        let keyboard = self.keyboard_handle();
+        if let Err(err) = keyboard.set_xkb_config(self, xkb_config) {
+            tracing::error!("Failed to set xkb config: {err}");
+            return;
+        }
+
+        self.lock_xkb_layout();
+    }
+
+    /// Lock the keyboard to the active layout of the current [`XkbSettings`].
+    ///
+    /// This switches layouts without rebuilding the keymap, so clients keep the one they have.
+    /// A staged config's layout is only locked once it's committed.
+    pub fn lock_xkb_layout(&mut self) {
+        if self.config.in_staged_context {
+            return;
+        }
+
+        let layout = Layout(self.input_state.xkb_settings.active_layout as u32);
+        let keyboard = self.keyboard_handle();
+        keyboard.with_xkb_state(self, |mut context| context.set_layout(layout));
+    }
+
+    /// Tell the keyboard and clients about the current key repeat rate and delay.
+    ///
+    /// A staged config's settings are only applied once it's committed.
+    pub fn apply_repeat_info(&mut self) {
+        if self.config.in_staged_context {
+            return;
+        }
+
+        let keyboard = self.keyboard_handle();
+        keyboard.change_repeat_info(self.input_state.repeat_rate, self.input_state.repeat_delay);
+    }
+
     fn keyboard<I: InputBackend>(&mut self, event: I::KeyboardKeyEvent) {
         let serial = SERIAL_COUNTER.next_serial();
         let time = event.time_msec();
         let press_state = event.state();
+        let keycode = event.key_code();
         let mut move_mode = false;
+        // The modifiers and keysyms of a newly pressed key, kept to match release keybinds
+        let mut new_press = None;
+        let mut release_callback = None;
 
         let reload_keybind = self.input_state.reload_keybind;
         let kill_keybind = self.input_state.kill_keybind;
+        let terminal_keybind = self.input_state.terminal_keybind;
+        let shortcuts_inhibit_escape = self.input_state.shortcuts_inhibit_escape;
+
+        // While a surface with an active inhibitor is focused, keys go to it instead of keybinds
+        let inhibitor = self
+            .seat
+            .get_keyboard()
+            .and_then(|keyboard| keyboard.current_focus())
+            .and_then(|focus| focus.wl_surface())
+            .and_then(|surface| self.seat.keyboard_shortcuts_inhibitor_for_surface(&surface));
+        let inhibited = inhibitor
+            .as_ref()
+            .is_some_and(|inhibitor| inhibitor.is_active());
 
         let action = self
             .seat
@@ -162,12 +601,21 @@ impl State {
             .expect("Seat has no keyboard") // FIXME: handle err
             .input(
                 self,
-                event.key_code(),
+                keycode,
                 press_state,
                 serial,
                 time,
                 |state, modifiers, keysym| {
+                    let bind_mode = state.input_state.bind_mode.clone();
+
                     if press_state == KeyState::Pressed {
+                        // Repeats of keys that triggered something are handled by the key repeat timer
+                        if let Some(pressed) = state.input_state.pressed_keys.get(&keycode) {
+                            if pressed.intercepted {
+                                return FilterResult::Intercept(KeyAction::Swallow);
+                            }
+                        }
+
                         let mut modifier_mask = Vec::<Modifier>::new();
                         if modifiers.alt {
                             modifier_mask.push(Modifier::Alt);
@@ -181,39 +629,89 @@ impl State {
                         if modifiers.logo {
                             modifier_mask.push(Modifier::Super);
                         }
+                        modifier_mask.extend(state.input_state.virtual_modifiers.iter().copied());
                         let modifier_mask = ModifierMask::from(modifier_mask);
                         let raw_sym = keysym.raw_syms().iter().next();
                         let mod_sym = keysym.modified_sym();
+                        let syms = std::iter::once(mod_sym).chain(raw_sym.copied()).collect::<Vec<_>>();
+                        new_press = Some((modifier_mask, syms.clone()));
+
+                        if inhibitor.is_some() && Some((modifier_mask, mod_sym)) == shortcuts_inhibit_escape {
+                            return FilterResult::Intercept(KeyAction::ToggleShortcutsInhibit);
+                        }
+                        // Quitting and VT switching still work so a client can't lock the user in
+                        if inhibited {
+                            if (modifier_mask, mod_sym) == kill_keybind {
+                                return FilterResult::Intercept(KeyAction::Quit);
+                            }
+                            if let Some(vt) = vt_for_keysym(mod_sym) {
+                                tracing::info!("Switching to vt {vt}");
+                                return FilterResult::Intercept(KeyAction::SwitchVt(vt));
+                            }
+                            return FilterResult::Forward;
+                        }
 
-                        let cb_id_mod = state
-                            .input_state
-                            .keybinds
-                            .get(&(modifier_mask, mod_sym));
+                        // Keys in the middle of a key sequence are sent to it before anything else.
+                        // Modifiers are let through so the next combo in the sequence can be pressed.
+                        if !state.input_state.pending_sequence.is_empty() && !is_modifier_keysym(mod_sym) {
+                            if let Some(action) = state.input_state.advance_key_sequence(modifier_mask, &syms) {
+                                return FilterResult::Intercept(action);
+                            }
+                        }
 
-                        let cb_id_raw = if let Some(raw_sym) = raw_sym {
-                            state.input_state.keybinds.get(&(modifier_mask, *raw_sym))
-                        } else {
-                            None
-                        };
+                        let keybind = syms.iter().find_map(|&sym| {
+                            state
+                                .input_state
+                                .keybinds
+                                .get(&(bind_mode.clone(), modifier_mask, sym, KeyEdge::Press))
+                                .map(|keybind| (keybind, sym))
+                        });
+
+                        if let Some((keybind, sym)) = keybind {
+                            let combo = (modifier_mask, sym);
+                            return FilterResult::Intercept(if keybind.allow_repeat {
+                                KeyAction::RepeatCallback(keybind.client_id, keybind.callback_id, combo)
+                            } else {
+                                KeyAction::CallCallback(keybind.client_id, keybind.callback_id, combo)
+                            });
+                        }
 
-                        match (cb_id_mod, cb_id_raw) {
-                            (Some(cb_id), _) | (None, Some(cb_id)) => {
-                                return FilterResult::Intercept(KeyAction::CallCallback(*cb_id));
+                        // Keys that aren't keybinds may start a key sequence
+                        if !is_modifier_keysym(mod_sym) {
+                            if let Some(action) = state.input_state.advance_key_sequence(modifier_mask, &syms) {
+                                return FilterResult::Intercept(action);
                             }
-                            (None, None) => ()
                         }
 
                         if (modifier_mask, mod_sym) == kill_keybind {
                             return FilterResult::Intercept(KeyAction::Quit);
                         } else if (modifier_mask, mod_sym) == reload_keybind {
                             return FilterResult::Intercept(KeyAction::ReloadConfig);
-                        } else if let mut vt @ keysyms::KEY_XF86Switch_VT_1..=keysyms::KEY_XF86Switch_VT_12 =
-                            keysym.modified_sym() {
-                            vt = vt - keysyms::KEY_XF86Switch_VT_1 + 1;
+                        } else if Some((modifier_mask, mod_sym)) == terminal_keybind {
+                            return FilterResult::Intercept(KeyAction::SpawnTerminal);
+                        } else if let Some(vt) = vt_for_keysym(mod_sym) {
                             tracing::info!("Switching to vt {vt}");
-                            return FilterResult::Intercept(KeyAction::SwitchVt(vt as i32));
+                            return FilterResult::Intercept(KeyAction::SwitchVt(vt));
                         }
 
+                        // Keys that don't match anything in a bind mode are swallowed
+                        if bind_mode != DEFAULT_BIND_MODE {
+                            if modifier_mask.values().is_empty() && mod_sym == keysyms::KEY_Escape {
+                                return FilterResult::Intercept(KeyAction::LeaveBindMode);
+                            }
+                            return FilterResult::Intercept(KeyAction::Swallow);
+                        }
+                    } else if let Some(pressed) = state.input_state.pressed_keys.get(&keycode).filter(|_| !inhibited) {
+                        // Release keybinds match the modifiers held when the key was pressed, so
+                        // releasing a modifier key can trigger a keybind with that modifier.
+                        // Releases are always sent to clients so no key gets stuck.
+                        release_callback = pressed.syms.iter().find_map(|&sym| {
+                            state
+                                .input_state
+                                .keybinds
+                                .get(&(bind_mode.clone(), pressed.modifier_mask, sym, KeyEdge::Release))
+                                .map(|keybind| (keybind.client_id, keybind.callback_id, (pressed.modifier_mask, sym)))
+                        });
                     }
 
                     if keysym.modified_sym() == keysyms::KEY_Control_L {
@@ -232,20 +730,37 @@ impl State {
 
         self.move_mode = move_mode;
 
-        match action {
-            Some(KeyAction::CallCallback(callback_id)) => {
-                if let Some(stream) = self.api_state.stream.as_ref() {
-                    if let Err(err) = crate::api::send_to_client(
-                        &mut stream.lock().expect("Could not lock stream mutex"),
-                        &OutgoingMsg::CallCallback {
-                            callback_id,
-                            args: None,
+        match press_state {
+            KeyState::Pressed => {
+                if let Some((modifier_mask, syms)) = new_press {
+                    self.input_state.pressed_keys.insert(
+                        keycode,
+                        PressedKey {
+                            modifier_mask,
+                            syms,
+                            intercepted: action.is_some(),
                         },
-                    ) {
-                        tracing::error!("error sending msg to client: {err}");
-                    }
+                    );
                 }
             }
+            KeyState::Released => {
+                self.input_state.pressed_keys.remove(&keycode);
+                self.stop_key_repeat(keycode);
+
+                if let Some((client_id, callback_id, combo)) = release_callback {
+                    self.call_keybind(client_id, callback_id, combo);
+                }
+            }
+        }
+
+        match action {
+            Some(KeyAction::CallCallback(client_id, callback_id, combo)) => {
+                self.call_keybind(client_id, callback_id, combo);
+            }
+            Some(KeyAction::RepeatCallback(client_id, callback_id, combo)) => {
+                self.call_keybind(client_id, callback_id, combo);
+                self.start_key_repeat(keycode, client_id, callback_id, combo);
+            }
             Some(KeyAction::SwitchVt(vt)) => {
                 if let Backend::Udev(udev) = &mut self.backend {
                     if let Err(err) = udev.session.change_vt(vt) {
@@ -257,15 +772,188 @@ impl State {
                 self.loop_signal.stop();
             }
             Some(KeyAction::ReloadConfig) => {
-                self.restart_config().expect("failed to restart config");
+                self.reload_config();
+            }
+            Some(KeyAction::SpawnTerminal) => {
+                crate::config::spawn_terminal();
+            }
+            Some(KeyAction::LeaveBindMode) => {
+                self.set_bind_mode(DEFAULT_BIND_MODE.to_string());
+            }
+            Some(KeyAction::ToggleShortcutsInhibit) => {
+                if let Some(inhibitor) = inhibitor {
+                    if inhibitor.is_active() {
+                        inhibitor.inactivate();
+                    } else {
+                        inhibitor.activate();
+                    }
+                }
+            }
+            Some(KeyAction::Swallow) | None => {}
+        }
+    }
+
+    /// Call a keybind's callback with the key combo that triggered it and the focused window.
+    fn call_keybind(&self, client_id: ApiClientId, callback_id: CallbackId, combo: KeyCombo) {
+        let (modifier_mask, key) = combo;
+        let window_id = self
+            .seat
+            .get_keyboard()
+            .and_then(|keyboard| keyboard.current_focus())
+            .and_then(|focus| self.window_for_surface(&focus.wl_surface()?))
+            .map(|window| window.with_state(|state| state.id));
+
+        self.api_state.send(
+            client_id,
+            &OutgoingMsg::CallCallback {
+                callback_id,
+                args: Some(Args::Keybind {
+                    key,
+                    modifiers: modifier_mask.values(),
+                    window_id,
+                }),
+            },
+        );
+    }
+
+    /// Call a keybind's callback every key repeat while the key with `keycode` is held.
+    fn start_key_repeat(
+        &mut self,
+        keycode: u32,
+        client_id: ApiClientId,
+        callback_id: CallbackId,
+        combo: KeyCombo,
+    ) {
+        if let Some(repeat) = self.input_state.key_repeat.take() {
+            self.loop_handle.remove(repeat.timer);
+        }
+
+        // A rate of 0 turns key repeat off
+        if self.input_state.repeat_rate <= 0 {
+            return;
+        }
+
+        let delay = Duration::from_millis(self.input_state.repeat_delay.max(0) as u64);
+        let interval = Duration::from_secs(1) / self.input_state.repeat_rate as u32;
+
+        let timer = Timer::from_duration(delay);
+        match self.loop_handle.insert_source(timer, move |_, _, data| {
+            data.state.call_keybind(client_id, callback_id, combo);
+            TimeoutAction::ToDuration(interval)
+        }) {
+            Ok(timer) => {
+                self.input_state.key_repeat = Some(KeyRepeat {
+                    keycode,
+                    client_id,
+                    callback_id,
+                    timer,
+                })
+            }
+            Err(err) => tracing::error!("Failed to insert key repeat timer: {err}"),
+        }
+    }
+
+    /// Stop repeating a keybind if the key with `keycode` was the one repeating it.
+    fn stop_key_repeat(&mut self, keycode: u32) {
+        self.stop_key_repeat_if(|repeat| repeat.keycode == keycode);
+    }
+
+    /// Stop repeating a keybind that calls into the given client.
+    ///
+    /// With a `callback_id`, only a keybind calling that callback is stopped.
+    pub fn stop_client_key_repeat(
+        &mut self,
+        client_id: ApiClientId,
+        callback_id: Option<CallbackId>,
+    ) {
+        self.stop_key_repeat_if(|repeat| {
+            repeat.client_id == client_id
+                && callback_id.map_or(true, |callback_id| repeat.callback_id == callback_id)
+        });
+    }
+
+    fn stop_key_repeat_if(&mut self, pred: impl FnOnce(&KeyRepeat) -> bool) {
+        if self.input_state.key_repeat.as_ref().is_some_and(pred) {
+            if let Some(repeat) = self.input_state.key_repeat.take() {
+                self.loop_handle.remove(repeat.timer);
             }
-            None => {}
         }
     }
 
+    /// Move a window to the top of the stack.
+    fn raise_window(&mut self, window: &WindowElement) {
+        self.space.raise_element(window, true);
+        if let WindowElement::X11(surface) = &window {
+            if !surface.is_override_redirect() {
+                self.xwm
+                    .as_mut()
+                    .expect("no xwm")
+                    .raise_window(surface)
+                    .expect("failed to raise x11 win");
+                surface
+                    .set_activated(true)
+                    .expect("failed to set x11 win to activated");
+            }
+        }
+    }
+
+    /// Raise and keyboard-focus the window a touch or tablet pen landed on.
+    ///
+    /// Layer surfaces like on-screen keyboards are left alone so they don't take keyboard focus
+    /// from the window they type in.
+    fn focus_tapped(&mut self, focus: &FocusTarget, serial: Serial) {
+        let FocusTarget::Window(window) = focus else { return };
+        self.raise_window(window);
+
+        if matches!(window, WindowElement::X11(surf) if surf.is_override_redirect()) {
+            return;
+        }
+        let keyboard = self.keyboard_handle();
+        keyboard.set_focus(self, Some(focus.clone()), serial);
+
+        self.space.elements().for_each(|window| {
+            if let WindowElement::Wayland(window) = window {
+                window.toplevel().send_configure();
+            }
+        });
+    }
+
+    /// Where an absolute position event lands, given the outputs its kind of device is mapped to.
+    ///
+    /// Devices are looked up by name, then the `None` entry is tried. Unmapped devices are
+    /// mapped to the first output.
+    fn mapped_location<I: InputBackend>(
+        &self,
+        device_outputs: &DeviceOutputs,
+        event: &impl AbsolutePositionEvent<I>,
+    ) -> Option<Point<f64, Logical>> {
+        let output = device_outputs
+            .get(&Some(event.device().name()))
+            .or_else(|| device_outputs.get(&None))
+            .and_then(|(_, name)| self.space.outputs().find(|output| output.name() == *name))
+            .or_else(|| self.space.outputs().next())?;
+        let output_geo = self.space.output_geometry(output)?;
+
+        Some(event.position_transformed(output_geo.size) + output_geo.loc.to_f64())
+    }
+
+    /// Get the window and output under a point, which pointer bind callbacks are called with.
+    fn bind_targets_at(&self, loc: Point<f64, Logical>) -> (Option<WindowId>, Option<String>) {
+        let window_id = match self.surface_under(loc) {
+            Some((FocusTarget::Window(window), _)) => Some(window.with_state(|state| state.id)),
+            _ => None,
+        };
+        let output_name = self
+            .space
+            .output_under(loc)
+            .next()
+            .map(|output| output.name());
+        (window_id, output_name)
+    }
+
     fn pointer_button<I: InputBackend>(&mut self, event: I::PointerButtonEvent) {
-        let pointer = self.seat.get_pointer().expect("Seat has no pointer"); // FIXME: handle err
-        let keyboard = self.seat.get_keyboard().expect("Seat has no keyboard"); // FIXME: handle err
+        let pointer = self.pointer_handle();
+        let keyboard = self.keyboard_handle();
 
         let serial = SERIAL_COUNTER.next_serial();
 
@@ -282,22 +970,25 @@ impl State {
         let modifier_mask = ModifierMask::from(keyboard.modifier_state());
 
         // If any mousebinds are detected, call the config's callback and return.
-        if let Some(&callback_id) = self
-            .input_state
-            .mousebinds
-            .get(&(modifier_mask, button, edge))
-        {
-            if let Some(stream) = self.api_state.stream.clone() {
-                let mut stream = stream.lock().expect("failed to lock api stream");
-                crate::api::send_to_client(
-                    &mut stream,
-                    &OutgoingMsg::CallCallback {
-                        callback_id,
-                        args: None,
-                    },
-                )
-                .expect("failed to call callback");
-            }
+        let bind = (
+            self.input_state.bind_mode.clone(),
+            modifier_mask,
+            button,
+            edge,
+        );
+        if let Some(&(client_id, callback_id)) = self.input_state.mousebinds.get(&bind) {
+            let (window_id, output_name) = self.bind_targets_at(pointer_loc);
+            self.api_state.send(
+                client_id,
+                &OutgoingMsg::CallCallback {
+                    callback_id,
+                    args: Some(Args::Mousebind {
+                        pointer_loc: (pointer_loc.x, pointer_loc.y),
+                        window_id,
+                        output_name,
+                    }),
+                },
+            );
             return;
         }
 
@@ -307,19 +998,7 @@ impl State {
             if let Some((focus, _)) = self.surface_under(pointer_loc) {
                 // Move window to top of stack.
                 if let FocusTarget::Window(window) = &focus {
-                    self.space.raise_element(window, true);
-                    if let WindowElement::X11(surface) = &window {
-                        if !surface.is_override_redirect() {
-                            self.xwm
-                                .as_mut()
-                                .expect("no xwm")
-                                .raise_window(surface)
-                                .expect("failed to raise x11 win");
-                            surface
-                                .set_activated(true)
-                                .expect("failed to set x11 win to activated");
-                        }
-                    }
+                    self.raise_window(window);
                 }
 
                 tracing::debug!("wl_surface focus is some? {}", focus.wl_surface().is_some());
@@ -416,10 +1095,7 @@ impl State {
         //     self.seat.get_pointer().unwrap().current_focus()
         // );
 
-        self.seat
-            .get_pointer()
-            .expect("Seat has no pointer")
-            .axis(self, frame);
+        self.pointer_handle().axis(self, frame);
     }
 
     /// Clamp pointer coordinates inside outputs
@@ -453,17 +1129,31 @@ impl State {
     }
 
     fn pointer_motion_absolute<I: InputBackend>(&mut self, event: I::PointerMotionAbsoluteEvent) {
-        let Some(output) = self.space.outputs().next() else { return; };
-        let output_geo = self
+        // Absolute devices span the whole layout, not just one output
+        let Some(layout_geo) = self
             .space
-            .output_geometry(output)
-            .expect("Output geometry doesn't exist");
-        let pointer_loc = event.position_transformed(output_geo.size) + output_geo.loc.to_f64();
+            .outputs()
+            .filter_map(|output| self.space.output_geometry(output))
+            .reduce(|geo, other| geo.merge(other))
+        else {
+            return;
+        };
+        let pointer_loc = event.position_transformed(layout_geo.size) + layout_geo.loc.to_f64();
         let serial = SERIAL_COUNTER.next_serial();
-        let pointer = self.seat.get_pointer().expect("Seat has no pointer"); // FIXME: handle err
+        let pointer = self.pointer_handle();
 
         // tracing::info!("pointer_loc: {:?}", pointer_loc);
 
+        let under = self.surface_under(self.pointer_location);
+        let constraint = self.active_pointer_constraint(&pointer, under.as_ref());
+        let Some((pointer_loc, surface_under_pointer)) =
+            self.constrain_motion(constraint.as_ref(), pointer_loc)
+        else {
+            return;
+        };
+
+        // The pointer was moved somewhere else, so it shouldn't jump back to a lock's hint
+        self.input_state.pointer_lock_hint = None;
         self.pointer_location = pointer_loc;
 
         match self.focus_state.focused_output {
@@ -482,28 +1172,67 @@ impl State {
             }
         }
 
-        let surface_under_pointer = self.surface_under(pointer_loc);
-
         // tracing::debug!("surface_under_pointer: {surface_under_pointer:?}");
         // tracing::debug!("pointer focus: {:?}", pointer.current_focus());
         pointer.motion(
             self,
-            surface_under_pointer,
+            surface_under_pointer.clone(),
             &MotionEvent {
                 location: pointer_loc,
                 serial,
                 time: event.time_msec(),
             },
         );
+
+        self.activate_pointer_constraint(&pointer, surface_under_pointer.as_ref());
     }
 
     fn pointer_motion<I: InputBackend>(&mut self, event: I::PointerMotionEvent) {
         let serial = SERIAL_COUNTER.next_serial();
-        self.pointer_location += event.delta();
+        let pointer = self.pointer_handle();
+
+        let under = self.surface_under(self.pointer_location);
+        let constraint = self.active_pointer_constraint(&pointer, under.as_ref());
+        let surface = under.as_ref().and_then(|(focus, _)| focus.wl_surface());
+
+        pointer.relative_motion(
+            self,
+            under,
+            &RelativeMotionEvent {
+                delta: event.delta(),
+                delta_unaccel: event.delta_unaccel(),
+                utime: event.time(),
+            },
+        );
+
+        // A locked pointer stays put, so clients only get the relative motion
+        if let Some(ActiveConstraint::Locked { surface, hint }) = constraint {
+            self.input_state.pointer_lock_hint = hint.map(|hint| (surface, hint));
+            return;
+        }
+
+        // Once a lock ends, the pointer goes where the client last asked it to be. If the
+        // pointer isn't over the locked surface anymore, the lock ended some other way and the
+        // hint is stale.
+        let mut pointer_location = self
+            .input_state
+            .pointer_lock_hint
+            .take()
+            .filter(|(locked, _)| surface.as_ref() == Some(locked))
+            .map_or(self.pointer_location, |(_, hint)| hint);
+        pointer_location += event.delta();
 
         // clamp to screen limits
         // this event is never generated by winit
-        self.pointer_location = self.clamp_coords(self.pointer_location);
+        pointer_location = self.clamp_coords(pointer_location);
+
+        let Some((pointer_location, surface_under)) =
+            self.constrain_motion(constraint.as_ref(), pointer_location)
+        else {
+            return;
+        };
+
+        self.pointer_location = pointer_location;
         match self.focus_state.focused_output {
             Some(_) => {
                 if let Some(output) = self
@@ -520,29 +1249,116 @@ impl State {
             }
         }
 
-        let surface_under = self.surface_under(self.pointer_location);
-
         // tracing::info!("{:?}", self.pointer_location);
-        if let Some(ptr) = self.seat.get_pointer() {
-            ptr.motion(
-                self,
-                surface_under,
-                &MotionEvent {
-                    location: self.pointer_location,
-                    serial,
-                    time: event.time_msec(),
-                },
-            );
+        pointer.motion(
+            self,
+            surface_under.clone(),
+            &MotionEvent {
+                location: self.pointer_location,
+                serial,
+                time: event.time_msec(),
+            },
+        );
 
-            // ptr.relative_motion(
-            //     self,
-            //     under,
-            //     &RelativeMotionEvent {
-            //         delta: event.delta(),
-            //         delta_unaccel: event.delta_unaccel(),
-            //         utime: event.time(),
-            //     },
-            // )
-        }
+        self.activate_pointer_constraint(&pointer, surface_under.as_ref());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use smithay::input::keyboard::keysyms;
+
+    use crate::api::{
+        msg::{CallbackId, Modifier, ModifierMask},
+        ApiClientId,
+    };
+
+    use super::{vt_for_keysym, InputState, KeyAction, DEFAULT_BIND_MODE};
+
+    /// An input state with `Super+a`, then `t` bound as a key sequence.
+    fn with_sequence() -> (InputState, ModifierMask) {
+        let no_mods = ModifierMask::from(Vec::new());
+        let mut state = InputState::new((no_mods, 0), (no_mods, 0));
+        let sequence = vec![
+            (ModifierMask::from(vec![Modifier::Super]), keysyms::KEY_a),
+            (no_mods, keysyms::KEY_t),
+        ];
+        state.key_sequences.insert(
+            (DEFAULT_BIND_MODE.to_string(), sequence),
+            (ApiClientId::next(), CallbackId(1)),
+        );
+        (state, no_mods)
+    }
+
+    fn press_super_a(state: &mut InputState) -> Option<KeyAction> {
+        let super_mask = ModifierMask::from(vec![Modifier::Super]);
+        state.advance_key_sequence(super_mask, &[keysyms::KEY_a])
+    }
+
+    #[test]
+    fn key_sequence_prefix_is_swallowed() {
+        let (mut state, _) = with_sequence();
+        assert!(matches!(
+            press_super_a(&mut state),
+            Some(KeyAction::Swallow)
+        ));
+    }
+
+    #[test]
+    fn key_sequence_completes() {
+        let (mut state, no_mods) = with_sequence();
+        press_super_a(&mut state);
+        assert!(matches!(
+            state.advance_key_sequence(no_mods, &[keysyms::KEY_t]),
+            Some(KeyAction::CallCallback(
+                _,
+                CallbackId(1),
+                (_, keysyms::KEY_t)
+            ))
+        ));
+
+        // The sequence starts over after completing
+        assert!(state
+            .advance_key_sequence(no_mods, &[keysyms::KEY_t])
+            .is_none());
+    }
+
+    #[test]
+    fn key_sequence_times_out() {
+        let (mut state, no_mods) = with_sequence();
+        press_super_a(&mut state);
+        state.last_sequence_key =
+            Instant::now() - state.key_sequence_timeout - Duration::from_millis(1);
+        assert!(state
+            .advance_key_sequence(no_mods, &[keysyms::KEY_t])
+            .is_none());
+    }
+
+    #[test]
+    fn key_sequence_is_cancelled_by_other_keys() {
+        let (mut state, no_mods) = with_sequence();
+        press_super_a(&mut state);
+        assert!(state
+            .advance_key_sequence(no_mods, &[keysyms::KEY_x])
+            .is_none());
+        assert!(state
+            .advance_key_sequence(no_mods, &[keysyms::KEY_t])
+            .is_none());
+
+        // Cancelling it outright works the same
+        press_super_a(&mut state);
+        state.cancel_key_sequence();
+        assert!(state
+            .advance_key_sequence(no_mods, &[keysyms::KEY_t])
+            .is_none());
+    }
+
+    #[test]
+    fn vt_keys_switch_vts() {
+        assert_eq!(vt_for_keysym(keysyms::KEY_XF86Switch_VT_1), Some(1));
+        assert_eq!(vt_for_keysym(keysyms::KEY_XF86Switch_VT_12), Some(12));
+        assert_eq!(vt_for_keysym(keysyms::KEY_Escape), None);
     }
 }
@@ -0,0 +1,290 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Touchpad gestures and the gesture binds that intercept them.
+
+use smithay::{
+    backend::input::{
+        Event, GestureBeginEvent, GestureEndEvent, GesturePinchUpdateEvent as _,
+        GestureSwipeUpdateEvent as _, InputBackend,
+    },
+    input::pointer::{
+        GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent, GesturePinchEndEvent,
+        GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+        GestureSwipeUpdateEvent,
+    },
+    utils::{Logical, Point, SERIAL_COUNTER},
+};
+
+use crate::{
+    api::msg::{Args, Gesture, ModifierMask, OutgoingMsg, PinchDirection, SwipeDirection},
+    state::State,
+};
+
+/// How far fingers must move, in logical pixels, for a swipe to count.
+const SWIPE_THRESHOLD: f64 = 50.0;
+
+/// How far the pinch scale must get from 1.0 for a pinch to count.
+const PINCH_THRESHOLD: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GestureKind {
+    Swipe,
+    Pinch,
+    Hold,
+}
+
+impl GestureKind {
+    fn of(gesture: &Gesture) -> Self {
+        match gesture {
+            Gesture::Swipe(_) => GestureKind::Swipe,
+            Gesture::Pinch(_) => GestureKind::Pinch,
+            Gesture::Hold => GestureKind::Hold,
+        }
+    }
+}
+
+/// A gesture in progress that a gesture bind intercepted.
+#[derive(Debug)]
+pub struct ActiveGesture {
+    kind: GestureKind,
+    fingers: u32,
+    /// The modifiers held when the gesture began.
+    modifier_mask: ModifierMask,
+    /// How far the fingers moved in total.
+    delta: Point<f64, Logical>,
+    /// The pinch scale relative to when the gesture began.
+    scale: f64,
+}
+
+impl ActiveGesture {
+    /// The gesture that was done, if the fingers moved far enough for it to count.
+    fn gesture(&self) -> Option<Gesture> {
+        match self.kind {
+            GestureKind::Swipe => {
+                let (x, y) = (self.delta.x, self.delta.y);
+                if x.abs().max(y.abs()) < SWIPE_THRESHOLD {
+                    None
+                } else if x.abs() > y.abs() {
+                    let direction = if x < 0.0 {
+                        SwipeDirection::Left
+                    } else {
+                        SwipeDirection::Right
+                    };
+                    Some(Gesture::Swipe(direction))
+                } else {
+                    let direction = if y < 0.0 {
+                        SwipeDirection::Up
+                    } else {
+                        SwipeDirection::Down
+                    };
+                    Some(Gesture::Swipe(direction))
+                }
+            }
+            GestureKind::Pinch if self.scale < 1.0 - PINCH_THRESHOLD => {
+                Some(Gesture::Pinch(PinchDirection::In))
+            }
+            GestureKind::Pinch if self.scale > 1.0 + PINCH_THRESHOLD => {
+                Some(Gesture::Pinch(PinchDirection::Out))
+            }
+            GestureKind::Pinch => None,
+            GestureKind::Hold => Some(Gesture::Hold),
+        }
+    }
+}
+
+impl State {
+    /// Start intercepting a gesture if a gesture bind could match it.
+    ///
+    /// Returns whether the gesture was intercepted.
+    fn begin_gesture_bind(&mut self, kind: GestureKind, fingers: u32) -> bool {
+        let keyboard = self.keyboard_handle();
+        let modifier_mask = ModifierMask::from(keyboard.modifier_state());
+
+        let input_state = &self.input_state;
+        let bound = input_state
+            .gesture_binds
+            .keys()
+            .any(|(mode, mask, bind_fingers, gesture)| {
+                *mode == input_state.bind_mode
+                    && *mask == modifier_mask
+                    && *bind_fingers == fingers
+                    && GestureKind::of(gesture) == kind
+            });
+
+        self.input_state.active_gesture = bound.then_some(ActiveGesture {
+            kind,
+            fingers,
+            modifier_mask,
+            delta: (0.0, 0.0).into(),
+            scale: 1.0,
+        });
+        bound
+    }
+
+    /// Stop intercepting the current gesture, calling its bind unless it was cancelled.
+    ///
+    /// Returns whether the gesture was intercepted.
+    fn end_gesture_bind(&mut self, cancelled: bool) -> bool {
+        let Some(active) = self.input_state.active_gesture.take() else { return false };
+        if cancelled {
+            return true;
+        }
+
+        let mode = self.input_state.bind_mode.clone();
+        let bind = active.gesture().and_then(|gesture| {
+            let bind = (mode, active.modifier_mask, active.fingers, gesture);
+            self.input_state.gesture_binds.get(&bind)
+        });
+        if let Some(&(client_id, callback_id)) = bind {
+            let pointer_loc = self.pointer_location;
+            let (window_id, output_name) = self.bind_targets_at(pointer_loc);
+            self.api_state.send(
+                client_id,
+                &OutgoingMsg::CallCallback {
+                    callback_id,
+                    args: Some(Args::Gesture {
+                        pointer_loc: (pointer_loc.x, pointer_loc.y),
+                        window_id,
+                        output_name,
+                    }),
+                },
+            );
+        }
+        true
+    }
+
+    pub(super) fn gesture_swipe_begin<I: InputBackend>(
+        &mut self,
+        event: I::GestureSwipeBeginEvent,
+    ) {
+        if self.begin_gesture_bind(GestureKind::Swipe, event.fingers()) {
+            return;
+        }
+        let pointer = self.pointer_handle();
+        pointer.gesture_swipe_begin(
+            self,
+            &GestureSwipeBeginEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                fingers: event.fingers(),
+            },
+        );
+    }
+
+    pub(super) fn gesture_swipe_update<I: InputBackend>(
+        &mut self,
+        event: I::GestureSwipeUpdateEvent,
+    ) {
+        if let Some(active) = self.input_state.active_gesture.as_mut() {
+            active.delta += event.delta();
+            return;
+        }
+        let pointer = self.pointer_handle();
+        pointer.gesture_swipe_update(
+            self,
+            &GestureSwipeUpdateEvent {
+                time: event.time_msec(),
+                delta: event.delta(),
+            },
+        );
+    }
+
+    pub(super) fn gesture_swipe_end<I: InputBackend>(&mut self, event: I::GestureSwipeEndEvent) {
+        if self.end_gesture_bind(event.cancelled()) {
+            return;
+        }
+        let pointer = self.pointer_handle();
+        pointer.gesture_swipe_end(
+            self,
+            &GestureSwipeEndEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                cancelled: event.cancelled(),
+            },
+        );
+    }
+
+    pub(super) fn gesture_pinch_begin<I: InputBackend>(
+        &mut self,
+        event: I::GesturePinchBeginEvent,
+    ) {
+        if self.begin_gesture_bind(GestureKind::Pinch, event.fingers()) {
+            return;
+        }
+        let pointer = self.pointer_handle();
+        pointer.gesture_pinch_begin(
+            self,
+            &GesturePinchBeginEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                fingers: event.fingers(),
+            },
+        );
+    }
+
+    pub(super) fn gesture_pinch_update<I: InputBackend>(
+        &mut self,
+        event: I::GesturePinchUpdateEvent,
+    ) {
+        if let Some(active) = self.input_state.active_gesture.as_mut() {
+            active.delta += event.delta();
+            active.scale = event.scale();
+            return;
+        }
+        let pointer = self.pointer_handle();
+        pointer.gesture_pinch_update(
+            self,
+            &GesturePinchUpdateEvent {
+                time: event.time_msec(),
+                delta: event.delta(),
+                scale: event.scale(),
+                rotation: event.rotation(),
+            },
+        );
+    }
+
+    pub(super) fn gesture_pinch_end<I: InputBackend>(&mut self, event: I::GesturePinchEndEvent) {
+        if self.end_gesture_bind(event.cancelled()) {
+            return;
+        }
+        let pointer = self.pointer_handle();
+        pointer.gesture_pinch_end(
+            self,
+            &GesturePinchEndEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                cancelled: event.cancelled(),
+            },
+        );
+    }
+
+    pub(super) fn gesture_hold_begin<I: InputBackend>(&mut self, event: I::GestureHoldBeginEvent) {
+        if self.begin_gesture_bind(GestureKind::Hold, event.fingers()) {
+            return;
+        }
+        let pointer = self.pointer_handle();
+        pointer.gesture_hold_begin(
+            self,
+            &GestureHoldBeginEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                fingers: event.fingers(),
+            },
+        );
+    }
+
+    pub(super) fn gesture_hold_end<I: InputBackend>(&mut self, event: I::GestureHoldEndEvent) {
+        if self.end_gesture_bind(event.cancelled()) {
+            return;
+        }
+        let pointer = self.pointer_handle();
+        pointer.gesture_hold_end(
+            self,
+            &GestureHoldEndEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                cancelled: event.cancelled(),
+            },
+        );
+    }
+}
@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Input methods, through the input-method-v2 and text-input-v3 protocols.
+//!
+//! Smithay routes text input to the keyboard focus and gives the input method a keyboard grab,
+//! so all that's left here is placing the input method's candidate popup.
+
+use smithay::{
+    desktop::{layer_map_for_output, space::SpaceElement},
+    utils::{Logical, Point, Rectangle, Size},
+};
+
+use crate::{focus::FocusTarget, state::State};
+
+impl State {
+    /// Get the global location of the keyboard focus's surface.
+    ///
+    /// Clients give the text cursor rectangle relative to this, so it's where the input method
+    /// popup is placed from.
+    pub fn text_input_origin(&self) -> Option<Point<i32, Logical>> {
+        let keyboard = self.seat.get_keyboard()?;
+
+        match keyboard.current_focus()? {
+            FocusTarget::Window(window) => {
+                Some(self.space.element_location(&window)? - window.geometry().loc)
+            }
+            FocusTarget::LayerSurface(layer) => self.space.outputs().find_map(|output| {
+                let output_loc = self.space.output_geometry(output)?.loc;
+                let layer_loc = layer_map_for_output(output).layer_geometry(&layer)?.loc;
+                Some(output_loc + layer_loc)
+            }),
+            FocusTarget::Popup(_) => None,
+        }
+    }
+}
+
+/// Place an input method popup of `popup_size` just below the text cursor at `cursor`,
+/// keeping it inside an output of `output_size`. Both are relative to the output.
+///
+/// If there's no room below the cursor, the popup goes above it.
+pub fn popup_location(
+    cursor: Rectangle<i32, Logical>,
+    popup_size: Size<i32, Logical>,
+    output_size: Size<i32, Logical>,
+) -> Point<i32, Logical> {
+    let x = cursor.loc.x.min(output_size.w - popup_size.w).max(0);
+    let below = cursor.loc.y + cursor.size.h;
+    let y = if below + popup_size.h <= output_size.h {
+        below
+    } else {
+        (cursor.loc.y - popup_size.h).max(0)
+    };
+    (x, y).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use smithay::utils::Rectangle;
+
+    use super::popup_location;
+
+    #[test]
+    fn popup_goes_below_the_cursor() {
+        let cursor = Rectangle::from_loc_and_size((100, 100), (2, 20));
+        let loc = popup_location(cursor, (200, 50).into(), (1920, 1080).into());
+        assert_eq!(loc, (100, 120).into());
+    }
+
+    #[test]
+    fn popup_stays_inside_the_output() {
+        let cursor = Rectangle::from_loc_and_size((1900, 100), (2, 20));
+        let loc = popup_location(cursor, (200, 50).into(), (1920, 1080).into());
+        assert_eq!(loc, (1720, 120).into());
+
+        // Near the bottom, it flips above the cursor
+        let cursor = Rectangle::from_loc_and_size((100, 1050), (2, 20));
+        let loc = popup_location(cursor, (200, 50).into(), (1920, 1080).into());
+        assert_eq!(loc, (100, 1000).into());
+    }
+}
@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Settings for libinput devices like touchpads and mice.
+//!
+//! Settings are kept as [`LibinputRule`]s so they can be applied again when a matching device
+//! is plugged in.
+
+use smithay::reexports::input::{self, DeviceCapability, DeviceConfigError};
+
+use crate::{
+    api::msg::{
+        AccelProfile, ClickMethod, InputDeviceInfo, InputDeviceType, LibinputSetting, ScrollMethod,
+    },
+    state::State,
+};
+
+/// A libinput setting and the devices it applies to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibinputRule {
+    /// Only apply to devices with this name.
+    pub device_name: Option<String>,
+    /// Only apply to devices of this type.
+    pub device_type: Option<InputDeviceType>,
+    pub setting: LibinputSetting,
+}
+
+impl LibinputRule {
+    pub fn matches(&self, device: &input::Device) -> bool {
+        self.device_name
+            .as_ref()
+            .map_or(true, |name| device.name() == name)
+            && self.device_type.map_or(true, |device_type| {
+                device_types(device).contains(&device_type)
+            })
+    }
+
+    /// Whether this rule sets the same thing on the same devices as `other`.
+    fn overrides(&self, other: &LibinputRule) -> bool {
+        self.device_name == other.device_name
+            && self.device_type == other.device_type
+            && std::mem::discriminant(&self.setting) == std::mem::discriminant(&other.setting)
+    }
+}
+
+/// The kinds of device `device` is.
+pub fn device_types(device: &input::Device) -> Vec<InputDeviceType> {
+    let mut types = Vec::new();
+    if device.has_capability(DeviceCapability::Keyboard) {
+        types.push(InputDeviceType::Keyboard);
+    }
+    if device.has_capability(DeviceCapability::Pointer) {
+        // libinput has no touchpad capability, but only touchpads can be tapped
+        if device.config_tap_finger_count() > 0 {
+            types.push(InputDeviceType::Touchpad);
+        } else {
+            types.push(InputDeviceType::Pointer);
+        }
+    }
+    if device.has_capability(DeviceCapability::Touch) {
+        types.push(InputDeviceType::Touch);
+    }
+    if device.has_capability(DeviceCapability::TabletTool)
+        || device.has_capability(DeviceCapability::TabletPad)
+    {
+        types.push(InputDeviceType::Tablet);
+    }
+    types
+}
+
+fn apply_setting(device: &mut input::Device, setting: LibinputSetting) {
+    let res = match setting {
+        LibinputSetting::Tap(enabled) => device.config_tap_set_enabled(enabled),
+        LibinputSetting::NaturalScroll(enabled) => {
+            device.config_scroll_set_natural_scroll_enabled(enabled)
+        }
+        LibinputSetting::AccelProfile(profile) => device.config_accel_set_profile(match profile {
+            AccelProfile::Flat => input::AccelProfile::Flat,
+            AccelProfile::Adaptive => input::AccelProfile::Adaptive,
+        }),
+        LibinputSetting::AccelSpeed(speed) => device.config_accel_set_speed(speed),
+        LibinputSetting::LeftHanded(enabled) => device.config_left_handed_set(enabled),
+        LibinputSetting::DisableWhileTyping(enabled) => device.config_dwt_set_enabled(enabled),
+        LibinputSetting::ScrollMethod(method) => device.config_scroll_set_method(match method {
+            ScrollMethod::NoScroll => input::ScrollMethod::NoScroll,
+            ScrollMethod::TwoFinger => input::ScrollMethod::TwoFinger,
+            ScrollMethod::Edge => input::ScrollMethod::Edge,
+            ScrollMethod::OnButtonDown => input::ScrollMethod::OnButtonDown,
+        }),
+        LibinputSetting::ClickMethod(method) => device.config_click_set_method(match method {
+            ClickMethod::ButtonAreas => input::ClickMethod::ButtonAreas,
+            ClickMethod::Clickfinger => input::ClickMethod::Clickfinger,
+        }),
+    };
+
+    match res {
+        // Settings that match by type or all devices hit plenty of devices that can't use them
+        Ok(()) | Err(DeviceConfigError::Unsupported) => (),
+        Err(err) => tracing::warn!("Failed to set {setting:?} on {}: {err:?}", device.name()),
+    }
+}
+
+/// Put every setting back to the device's default.
+fn reset_settings(device: &mut input::Device) {
+    // Errors mean the device doesn't have the setting, so there's nothing to reset
+    let _ = device.config_tap_set_enabled(device.config_tap_default_enabled());
+    let _ = device.config_scroll_set_natural_scroll_enabled(
+        device.config_scroll_default_natural_scroll_enabled(),
+    );
+    if let Some(profile) = device.config_accel_default_profile() {
+        let _ = device.config_accel_set_profile(profile);
+    }
+    let _ = device.config_accel_set_speed(device.config_accel_default_speed());
+    let _ = device.config_left_handed_set(device.config_left_handed_default());
+    let _ = device.config_dwt_set_enabled(device.config_dwt_default_enabled());
+    let _ = device.config_scroll_set_method(device.config_scroll_default_method());
+    if let Some(method) = device.config_click_default_method() {
+        let _ = device.config_click_set_method(method);
+    }
+}
+
+fn apply_rules(device: &mut input::Device, rules: &[LibinputRule]) {
+    for rule in rules.iter().filter(|rule| rule.matches(device)) {
+        apply_setting(device, rule.setting);
+    }
+}
+
+impl State {
+    /// Start keeping track of a newly plugged in device and apply the settings that match it.
+    pub fn add_libinput_device(&mut self, mut device: input::Device) {
+        apply_rules(&mut device, &self.input_state.libinput_rules);
+        self.input_state.libinput_devices.push(device);
+    }
+
+    pub fn remove_libinput_device(&mut self, device: &input::Device) {
+        self.input_state
+            .libinput_devices
+            .retain(|tracked| tracked != device);
+    }
+
+    /// Add a libinput setting, replacing any earlier one of the same kind for the same devices.
+    ///
+    /// A staged config's settings are only applied once it's committed.
+    pub fn set_libinput_setting(&mut self, rule: LibinputRule) {
+        self.input_state
+            .libinput_rules
+            .retain(|old_rule| !rule.overrides(old_rule));
+
+        if !self.config.in_staged_context {
+            for device in self.input_state.libinput_devices.iter_mut() {
+                if rule.matches(device) {
+                    apply_setting(device, rule.setting);
+                }
+            }
+        }
+
+        self.input_state.libinput_rules.push(rule);
+    }
+
+    /// Reset every device, then apply the current settings to it.
+    pub fn reapply_libinput_settings(&mut self) {
+        for device in self.input_state.libinput_devices.iter_mut() {
+            reset_settings(device);
+            apply_rules(device, &self.input_state.libinput_rules);
+        }
+    }
+
+    /// Describe all the libinput devices that are plugged in.
+    pub fn input_devices(&self) -> Vec<InputDeviceInfo> {
+        self.input_state
+            .libinput_devices
+            .iter()
+            .map(|device| InputDeviceInfo {
+                name: device.name().to_string(),
+                types: device_types(device),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Pointer locks and confinement from the pointer-constraints protocol.
+//!
+//! A constraint only does something while it's active. It's activated when the pointer is over
+//! its surface and inside its region, and smithay deactivates it when the pointer leaves the
+//! surface.
+//!
+//! A confined pointer that would leave its region moves along just one axis if it can, so it
+//! slides along the region's edge instead of stopping.
+
+use smithay::{
+    input::pointer::PointerHandle,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Point},
+    wayland::{
+        compositor::RegionAttributes,
+        pointer_constraints::{with_pointer_constraint, PointerConstraint},
+        seat::WaylandFocus,
+    },
+};
+
+use crate::{focus::FocusTarget, state::State};
+
+/// Whether `location` is inside a constraint's region. Constraints without a region cover their
+/// whole surface.
+fn in_region(
+    region: Option<&RegionAttributes>,
+    location: Point<f64, Logical>,
+    surface_loc: Point<i32, Logical>,
+) -> bool {
+    region.map_or(true, |region| {
+        region.contains(location.to_i32_round() - surface_loc)
+    })
+}
+
+/// Where a confined pointer moving from `from` to `to` ends up, or `None` if it can't move.
+///
+/// If `allows` rejects `to`, the pointer moves along just one axis.
+fn confined_location(
+    from: Point<f64, Logical>,
+    to: Point<f64, Logical>,
+    mut allows: impl FnMut(Point<f64, Logical>) -> bool,
+) -> Option<Point<f64, Logical>> {
+    [to, (to.x, from.y).into(), (from.x, to.y).into()]
+        .into_iter()
+        .find(|&location| location != from && allows(location))
+}
+
+/// An active constraint on the surface under the pointer.
+pub enum ActiveConstraint {
+    /// The pointer can't move.
+    Locked {
+        surface: WlSurface,
+        /// Where the client would like the pointer to be when the lock ends, in global
+        /// coordinates.
+        hint: Option<Point<f64, Logical>>,
+    },
+    /// The pointer can't leave `surface`, or `region` of it if there is one.
+    Confined {
+        surface: WlSurface,
+        region: Option<RegionAttributes>,
+    },
+}
+
+impl State {
+    /// Get where the pointer ends up when it moves to `location`, and what's under it there.
+    ///
+    /// Returns `None` if `constraint` keeps the pointer where it is.
+    pub(super) fn constrain_motion(
+        &self,
+        constraint: Option<&ActiveConstraint>,
+        location: Point<f64, Logical>,
+    ) -> Option<(
+        Point<f64, Logical>,
+        Option<(FocusTarget, Point<i32, Logical>)>,
+    )> {
+        let (surface, region) = match constraint {
+            None => return Some((location, self.surface_under(location))),
+            Some(ActiveConstraint::Locked { .. }) => return None,
+            Some(ActiveConstraint::Confined { surface, region }) => (surface, region.as_ref()),
+        };
+
+        let mut under = None;
+        let location = confined_location(self.pointer_location, location, |location| {
+            under = self.surface_under(location);
+            under.as_ref().is_some_and(|(focus, surface_loc)| {
+                focus.wl_surface().as_ref() == Some(surface)
+                    && in_region(region, location, *surface_loc)
+            })
+        })?;
+        Some((location, under))
+    }
+
+    /// Get the active constraint on the surface under the pointer, if the pointer is inside its
+    /// region.
+    pub(super) fn active_pointer_constraint(
+        &self,
+        pointer: &PointerHandle<Self>,
+        under: Option<&(FocusTarget, Point<i32, Logical>)>,
+    ) -> Option<ActiveConstraint> {
+        let (focus, surface_loc) = under?;
+        let surface = focus.wl_surface()?;
+        let location = self.pointer_location;
+
+        with_pointer_constraint(&surface, pointer, |constraint| {
+            let constraint = constraint.filter(|constraint| constraint.is_active())?;
+            let region = constraint.region();
+            if !in_region(region, location, *surface_loc) {
+                return None;
+            }
+
+            Some(match &*constraint {
+                PointerConstraint::Locked(locked) => ActiveConstraint::Locked {
+                    surface: surface.clone(),
+                    hint: locked
+                        .cursor_position_hint()
+                        .map(|hint| hint + surface_loc.to_f64()),
+                },
+                PointerConstraint::Confined(_) => ActiveConstraint::Confined {
+                    surface: surface.clone(),
+                    region: region.cloned(),
+                },
+            })
+        })
+    }
+
+    /// Activate the constraint on the surface under the pointer if the pointer moved into its
+    /// region.
+    pub(super) fn activate_pointer_constraint(
+        &self,
+        pointer: &PointerHandle<Self>,
+        under: Option<&(FocusTarget, Point<i32, Logical>)>,
+    ) {
+        let Some((focus, surface_loc)) = under else { return };
+        let Some(surface) = focus.wl_surface() else { return };
+        let location = self.pointer_location;
+
+        with_pointer_constraint(&surface, pointer, |constraint| {
+            let Some(mut constraint) = constraint else { return };
+            if !constraint.is_active() && in_region(constraint.region(), location, *surface_loc) {
+                constraint.activate();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use smithay::{
+        utils::{Logical, Point, Rectangle},
+        wayland::compositor::{RectangleKind, RegionAttributes},
+    };
+
+    use super::{confined_location, in_region};
+
+    fn inside_box(location: Point<f64, Logical>) -> bool {
+        (0.0..=100.0).contains(&location.x) && (0.0..=100.0).contains(&location.y)
+    }
+
+    #[test]
+    fn confined_pointer_moves_freely_inside() {
+        let to = confined_location((50.0, 50.0).into(), (60.0, 40.0).into(), inside_box);
+        assert_eq!(to, Some((60.0, 40.0).into()));
+    }
+
+    #[test]
+    fn confined_pointer_slides_along_edges() {
+        let to = confined_location((95.0, 50.0).into(), (105.0, 60.0).into(), inside_box);
+        assert_eq!(to, Some((95.0, 60.0).into()));
+
+        let to = confined_location((50.0, 5.0).into(), (40.0, -5.0).into(), inside_box);
+        assert_eq!(to, Some((40.0, 5.0).into()));
+    }
+
+    #[test]
+    fn confined_pointer_stops_in_corners() {
+        let to = confined_location((95.0, 95.0).into(), (105.0, 105.0).into(), inside_box);
+        assert_eq!(to, None);
+    }
+
+    #[test]
+    fn region_limits_where_constraints_apply() {
+        let surface_loc = Point::from((100, 100));
+        assert!(in_region(None, (5000.0, 5000.0).into(), surface_loc));
+
+        let region = RegionAttributes {
+            rects: vec![(
+                RectangleKind::Add,
+                Rectangle::from_loc_and_size((0, 0), (10, 10)),
+            )],
+        };
+        let region = Some(&region);
+        assert!(in_region(region, (105.0, 105.0).into(), surface_loc));
+        assert!(!in_region(region, (115.0, 105.0).into(), surface_loc));
+    }
+}
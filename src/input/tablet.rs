@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Drawing tablets, through the tablet-v2 protocol.
+//!
+//! Like touchscreens, tablets are mapped onto an output. While a tool is in proximity, it
+//! also moves the pointer so the cursor follows the pen.
+
+use smithay::{
+    backend::input::{
+        Device, DeviceCapability, Event, InputBackend, ProximityState, TabletToolButtonEvent,
+        TabletToolEvent, TabletToolProximityEvent, TabletToolTipEvent, TabletToolTipState,
+    },
+    input::pointer::MotionEvent,
+    utils::{Logical, Point, SERIAL_COUNTER},
+    wayland::{
+        seat::WaylandFocus,
+        tablet_manager::{TabletDescriptor, TabletSeatTrait},
+    },
+};
+
+use crate::{focus::FocusTarget, state::State};
+
+impl State {
+    pub(super) fn tablet_device_added(&mut self, device: &impl Device) {
+        if device.has_capability(DeviceCapability::TabletTool) {
+            self.seat
+                .tablet_seat()
+                .add_tablet::<Self>(&self.display_handle, &TabletDescriptor::from(device));
+        }
+    }
+
+    pub(super) fn tablet_device_removed(&mut self, device: &impl Device) {
+        if device.has_capability(DeviceCapability::TabletTool) {
+            let tablet_seat = self.seat.tablet_seat();
+            tablet_seat.remove_tablet(&TabletDescriptor::from(device));
+
+            // Tools aren't tied to a tablet, so they can only go once every tablet is gone
+            if tablet_seat.count_tablets() == 0 {
+                tablet_seat.clear_tools();
+            }
+        }
+    }
+
+    /// Move the pointer to where a tablet tool is, returning what's under it.
+    fn tablet_tool_moved(
+        &mut self,
+        location: Point<f64, Logical>,
+        time: u32,
+    ) -> Option<(FocusTarget, Point<i32, Logical>)> {
+        self.pointer_location = location;
+        if let Some(output) = self.space.output_under(location).next().cloned() {
+            self.focus_state.focused_output = Some(output);
+        }
+
+        let under = self.surface_under(location);
+        let pointer = self.pointer_handle();
+        pointer.motion(
+            self,
+            under.clone(),
+            &MotionEvent {
+                location,
+                serial: SERIAL_COUNTER.next_serial(),
+                time,
+            },
+        );
+        under
+    }
+
+    pub(super) fn tablet_tool_axis<I: InputBackend>(&mut self, event: I::TabletToolAxisEvent) {
+        let tablet_outputs = &self.input_state.tablet_outputs;
+        let Some(location) = self.mapped_location(tablet_outputs, &event) else { return };
+        let under = self.tablet_tool_moved(location, event.time_msec());
+
+        let tablet_seat = self.seat.tablet_seat();
+        let tablet = tablet_seat.get_tablet(&TabletDescriptor::from(&event.device()));
+        let tool = tablet_seat.get_tool(&event.tool());
+        let (Some(tablet), Some(tool)) = (tablet, tool) else { return };
+
+        if event.pressure_has_changed() {
+            tool.pressure(event.pressure());
+        }
+        if event.distance_has_changed() {
+            tool.distance(event.distance());
+        }
+        if event.tilt_has_changed() {
+            tool.tilt(event.tilt());
+        }
+        if event.slider_has_changed() {
+            tool.slider_position(event.slider_position());
+        }
+        if event.rotation_has_changed() {
+            tool.rotation(event.rotation());
+        }
+        if event.wheel_has_changed() {
+            tool.wheel(event.wheel_delta(), event.wheel_delta_discrete());
+        }
+
+        let focus = under.and_then(|(focus, loc)| Some((focus.wl_surface()?, loc)));
+        tool.motion(
+            location,
+            focus,
+            &tablet,
+            SERIAL_COUNTER.next_serial(),
+            event.time_msec(),
+        );
+    }
+
+    pub(super) fn tablet_tool_proximity<I: InputBackend>(
+        &mut self,
+        event: I::TabletToolProximityEvent,
+    ) {
+        let tablet_outputs = &self.input_state.tablet_outputs;
+        let Some(location) = self.mapped_location(tablet_outputs, &event) else { return };
+
+        let tablet_seat = self.seat.tablet_seat();
+        let tool = tablet_seat.add_tool::<Self>(&self.display_handle, &event.tool());
+        let tablet = tablet_seat.get_tablet(&TabletDescriptor::from(&event.device()));
+        let Some(tablet) = tablet else { return };
+
+        let under = self.tablet_tool_moved(location, event.time_msec());
+
+        match event.state() {
+            ProximityState::In => {
+                let Some((focus, loc)) = under else { return };
+                let Some(surface) = focus.wl_surface() else { return };
+                tool.proximity_in(
+                    location,
+                    (surface, loc),
+                    &tablet,
+                    SERIAL_COUNTER.next_serial(),
+                    event.time_msec(),
+                );
+            }
+            ProximityState::Out => tool.proximity_out(event.time_msec()),
+        }
+    }
+
+    pub(super) fn tablet_tool_tip<I: InputBackend>(&mut self, event: I::TabletToolTipEvent) {
+        let Some(tool) = self.seat.tablet_seat().get_tool(&event.tool()) else { return };
+
+        match event.tip_state() {
+            TabletToolTipState::Down => {
+                let serial = SERIAL_COUNTER.next_serial();
+                tool.tip_down(serial, event.time_msec());
+
+                // Putting the pen down focuses the window under it like a click does
+                if let Some((focus, _)) = self.surface_under(self.pointer_location) {
+                    self.focus_tapped(&focus, serial);
+                }
+            }
+            TabletToolTipState::Up => tool.tip_up(event.time_msec()),
+        }
+    }
+
+    pub(super) fn tablet_tool_button<I: InputBackend>(&mut self, event: I::TabletToolButtonEvent) {
+        let Some(tool) = self.seat.tablet_seat().get_tool(&event.tool()) else { return };
+
+        tool.button(
+            event.button(),
+            event.button_state(),
+            SERIAL_COUNTER.next_serial(),
+            event.time_msec(),
+        );
+    }
+}
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Touchscreens.
+//!
+//! Touch positions are absolute, so every touch device is mapped onto an output. Unless a
+//! client maps a device to an output by name, it's mapped to the first output.
+
+use smithay::{
+    backend::input::{Event, InputBackend, TouchEvent as _},
+    utils::SERIAL_COUNTER,
+    wayland::seat::WaylandFocus,
+};
+
+use crate::state::State;
+
+impl State {
+    pub(super) fn touch_down<I: InputBackend>(&mut self, event: I::TouchDownEvent) {
+        let touch_outputs = &self.input_state.touch_outputs;
+        let Some(location) = self.mapped_location(touch_outputs, &event) else { return };
+        let Some((focus, focus_loc)) = self.surface_under(location) else { return };
+        let Some(surface) = focus.wl_surface() else { return };
+
+        let serial = SERIAL_COUNTER.next_serial();
+        self.focus_tapped(&focus, serial);
+
+        let touch = self.touch_handle();
+        touch.down(
+            serial,
+            event.time_msec(),
+            &surface,
+            focus_loc,
+            event.slot(),
+            location,
+        );
+    }
+
+    pub(super) fn touch_motion<I: InputBackend>(&mut self, event: I::TouchMotionEvent) {
+        let touch_outputs = &self.input_state.touch_outputs;
+        let Some(location) = self.mapped_location(touch_outputs, &event) else { return };
+
+        let touch = self.touch_handle();
+        touch.motion(event.time_msec(), event.slot(), location);
+    }
+
+    pub(super) fn touch_up<I: InputBackend>(&mut self, event: I::TouchUpEvent) {
+        let touch = self.touch_handle();
+        touch.up(
+            SERIAL_COUNTER.next_serial(),
+            event.time_msec(),
+            event.slot(),
+        );
+    }
+
+    pub(super) fn touch_frame<I: InputBackend>(&mut self, _event: I::TouchFrameEvent) {
+        let touch = self.touch_handle();
+        touch.frame();
+    }
+
+    pub(super) fn touch_cancel<I: InputBackend>(&mut self, _event: I::TouchCancelEvent) {
+        let touch = self.touch_handle();
+        touch.cancel();
+    }
+}
@@ -0,0 +1,701 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Virtual keyboards and pointers for automation and accessibility tools.
+//!
+//! Events from the virtual-keyboard and wlr-virtual-pointer protocols are fed through the same
+//! path as real input, so keybinds and mousebinds work with them too. A virtual keyboard brings
+//! its own keymap, which replaces the seat's keymap until a real key is pressed.
+//!
+//! Virtual keys can trigger the kill and reload keybinds and switch VTs, so sandboxed clients
+//! don't get these globals.
+
+use std::{
+    fs::File,
+    os::unix::fs::FileExt,
+    sync::{atomic::Ordering, Mutex},
+};
+
+use smithay::{
+    backend::input::{
+        AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, DeviceCapability, Event,
+        InputBackend, InputEvent, KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent,
+        PointerMotionAbsoluteEvent, PointerMotionEvent, UnusedEvent,
+    },
+    input::keyboard::{KeyboardTarget, ModifiersState, SerializedMods},
+    reexports::{
+        wayland_protocols_misc::zwp_virtual_keyboard_v1::server::{
+            zwp_virtual_keyboard_manager_v1::{self, ZwpVirtualKeyboardManagerV1},
+            zwp_virtual_keyboard_v1::{self, ZwpVirtualKeyboardV1},
+        },
+        wayland_protocols_wlr::virtual_pointer::v1::server::{
+            zwlr_virtual_pointer_manager_v1::{self, ZwlrVirtualPointerManagerV1},
+            zwlr_virtual_pointer_v1::{self, ZwlrVirtualPointerV1},
+        },
+        wayland_server::{
+            protocol::wl_pointer, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New,
+            Resource, WEnum,
+        },
+    },
+    utils::SERIAL_COUNTER,
+};
+use xkbcommon::xkb;
+
+use crate::{
+    api::msg::Modifier,
+    state::{ClientState, State},
+};
+
+/// `wl_keyboard.keymap_format.xkb_v1`, the only keymap format virtual keyboards can use.
+const KEYMAP_FORMAT_XKB_V1: u32 = 1;
+
+/// The largest keymap a virtual keyboard can send, in bytes. Real keymaps are well under this.
+const MAX_KEYMAP_SIZE: u32 = 4 * 1024 * 1024;
+
+/// Advertise the virtual keyboard and pointer managers.
+pub fn create_globals(display_handle: &DisplayHandle) {
+    display_handle.create_global::<State, ZwpVirtualKeyboardManagerV1, _>(1, ());
+    // Version 2 lets pointers be bound to an output, which isn't supported
+    display_handle.create_global::<State, ZwlrVirtualPointerManagerV1, _>(1, ());
+}
+
+/// Whether `client` may use virtual input devices.
+fn can_use_virtual_input(client: &Client) -> bool {
+    client
+        .get_data::<ClientState>()
+        .is_some_and(|data| !data.sandboxed.load(Ordering::Relaxed))
+}
+
+/// The device virtual events come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VirtualDevice {
+    Keyboard,
+    Pointer,
+}
+
+impl Device for VirtualDevice {
+    fn id(&self) -> String {
+        match self {
+            VirtualDevice::Keyboard => "virtual-keyboard".to_string(),
+            VirtualDevice::Pointer => "virtual-pointer".to_string(),
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            VirtualDevice::Keyboard => "Virtual Keyboard".to_string(),
+            VirtualDevice::Pointer => "Virtual Pointer".to_string(),
+        }
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        match self {
+            VirtualDevice::Keyboard => capability == DeviceCapability::Keyboard,
+            VirtualDevice::Pointer => capability == DeviceCapability::Pointer,
+        }
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn syspath(&self) -> Option<std::path::PathBuf> {
+        None
+    }
+}
+
+/// The input backend for events from virtual devices.
+#[derive(Debug)]
+pub struct VirtualInput;
+
+impl InputBackend for VirtualInput {
+    type Device = VirtualDevice;
+
+    type KeyboardKeyEvent = VirtualKeyEvent;
+    type PointerAxisEvent = VirtualAxisEvent;
+    type PointerButtonEvent = VirtualButtonEvent;
+    type PointerMotionEvent = VirtualMotionEvent;
+    type PointerMotionAbsoluteEvent = VirtualMotionAbsoluteEvent;
+
+    type GestureSwipeBeginEvent = UnusedEvent;
+    type GestureSwipeUpdateEvent = UnusedEvent;
+    type GestureSwipeEndEvent = UnusedEvent;
+    type GesturePinchBeginEvent = UnusedEvent;
+    type GesturePinchUpdateEvent = UnusedEvent;
+    type GesturePinchEndEvent = UnusedEvent;
+    type GestureHoldBeginEvent = UnusedEvent;
+    type GestureHoldEndEvent = UnusedEvent;
+
+    type TouchDownEvent = UnusedEvent;
+    type TouchUpEvent = UnusedEvent;
+    type TouchMotionEvent = UnusedEvent;
+    type TouchCancelEvent = UnusedEvent;
+    type TouchFrameEvent = UnusedEvent;
+
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+
+    type SpecialEvent = UnusedEvent;
+}
+
+#[derive(Debug)]
+pub struct VirtualKeyEvent {
+    /// The time in milliseconds.
+    time: u32,
+    keycode: u32,
+    state: KeyState,
+}
+
+impl Event<VirtualInput> for VirtualKeyEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> VirtualDevice {
+        VirtualDevice::Keyboard
+    }
+}
+
+impl KeyboardKeyEvent<VirtualInput> for VirtualKeyEvent {
+    fn key_code(&self) -> u32 {
+        self.keycode
+    }
+
+    fn state(&self) -> KeyState {
+        self.state
+    }
+
+    fn count(&self) -> u32 {
+        match self.state {
+            KeyState::Pressed => 1,
+            KeyState::Released => 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct VirtualMotionEvent {
+    time: u32,
+    dx: f64,
+    dy: f64,
+}
+
+impl Event<VirtualInput> for VirtualMotionEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> VirtualDevice {
+        VirtualDevice::Pointer
+    }
+}
+
+impl PointerMotionEvent<VirtualInput> for VirtualMotionEvent {
+    fn delta_x(&self) -> f64 {
+        self.dx
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.dy
+    }
+
+    // Virtual pointers have no acceleration
+    fn delta_x_unaccel(&self) -> f64 {
+        self.dx
+    }
+
+    fn delta_y_unaccel(&self) -> f64 {
+        self.dy
+    }
+}
+
+#[derive(Debug)]
+pub struct VirtualMotionAbsoluteEvent {
+    time: u32,
+    x: u32,
+    y: u32,
+    /// The size of the area `x` and `y` are in.
+    x_extent: u32,
+    y_extent: u32,
+}
+
+impl Event<VirtualInput> for VirtualMotionAbsoluteEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> VirtualDevice {
+        VirtualDevice::Pointer
+    }
+}
+
+impl AbsolutePositionEvent<VirtualInput> for VirtualMotionAbsoluteEvent {
+    fn x(&self) -> f64 {
+        self.x as f64
+    }
+
+    fn y(&self) -> f64 {
+        self.y as f64
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.x as f64 / self.x_extent as f64 * width as f64
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.y as f64 / self.y_extent as f64 * height as f64
+    }
+}
+
+impl PointerMotionAbsoluteEvent<VirtualInput> for VirtualMotionAbsoluteEvent {}
+
+#[derive(Debug)]
+pub struct VirtualButtonEvent {
+    time: u32,
+    button: u32,
+    state: ButtonState,
+}
+
+impl Event<VirtualInput> for VirtualButtonEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> VirtualDevice {
+        VirtualDevice::Pointer
+    }
+}
+
+impl PointerButtonEvent<VirtualInput> for VirtualButtonEvent {
+    fn button_code(&self) -> u32 {
+        self.button
+    }
+
+    fn state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+/// Scrolling gathered from axis requests until the frame request that ends it.
+#[derive(Debug, Clone)]
+pub struct VirtualAxisEvent {
+    time: u32,
+    source: AxisSource,
+    /// Amounts for the horizontal and vertical axis.
+    amount: [Option<f64>; 2],
+    discrete: [Option<f64>; 2],
+}
+
+impl VirtualAxisEvent {
+    fn index(axis: Axis) -> usize {
+        match axis {
+            Axis::Horizontal => 0,
+            Axis::Vertical => 1,
+        }
+    }
+}
+
+impl Event<VirtualInput> for VirtualAxisEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> VirtualDevice {
+        VirtualDevice::Pointer
+    }
+}
+
+impl PointerAxisEvent<VirtualInput> for VirtualAxisEvent {
+    fn amount(&self, axis: Axis) -> Option<f64> {
+        self.amount[Self::index(axis)]
+    }
+
+    fn amount_discrete(&self, axis: Axis) -> Option<f64> {
+        self.discrete[Self::index(axis)]
+    }
+
+    fn source(&self) -> AxisSource {
+        self.source
+    }
+}
+
+/// The keymap a virtual keyboard uploaded, and the indices of the modifiers in it.
+#[derive(Debug)]
+struct VirtualKeymap {
+    keymap: String,
+    modifier_indices: Vec<(Modifier, u32)>,
+}
+
+impl VirtualKeymap {
+    fn new(keymap: String) -> Option<Self> {
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let xkb_keymap = xkb::Keymap::new_from_string(
+            &context,
+            keymap.clone(),
+            xkb::KEYMAP_FORMAT_TEXT_V1,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        )?;
+
+        let modifier_indices = [
+            (Modifier::Shift, xkb::MOD_NAME_SHIFT),
+            (Modifier::Ctrl, xkb::MOD_NAME_CTRL),
+            (Modifier::Alt, xkb::MOD_NAME_ALT),
+            (Modifier::Super, xkb::MOD_NAME_LOGO),
+        ]
+        .into_iter()
+        .map(|(modifier, name)| (modifier, xkb_keymap.mod_get_index(name)))
+        .filter(|(_, index)| *index != xkb::MOD_INVALID)
+        .collect();
+
+        Some(Self {
+            keymap,
+            modifier_indices,
+        })
+    }
+
+    /// The modifiers in a serialized xkb modifier mask.
+    fn modifiers(&self, mask: u32) -> Vec<Modifier> {
+        self.modifier_indices
+            .iter()
+            .filter(|(_, index)| mask & (1 << index) != 0)
+            .map(|(modifier, _)| *modifier)
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct VirtualKeyboard {
+    keymap: Option<VirtualKeymap>,
+    /// The modifiers the client set with the modifiers request.
+    modifiers: Vec<Modifier>,
+}
+
+/// Axis requests waiting for a frame request.
+#[derive(Debug, Default)]
+pub struct VirtualPointer {
+    pending_axis: Option<VirtualAxisEvent>,
+}
+
+impl VirtualPointer {
+    fn pending_axis(&mut self, time: u32) -> &mut VirtualAxisEvent {
+        self.pending_axis.get_or_insert(VirtualAxisEvent {
+            time,
+            source: AxisSource::Wheel,
+            amount: [None; 2],
+            discrete: [None; 2],
+        })
+    }
+}
+
+impl State {
+    /// Feed a key from a virtual keyboard through the same path as real keys, using the
+    /// virtual keyboard's keymap and modifiers.
+    fn virtual_key(&mut self, keymap: &str, modifiers: Vec<Modifier>, event: VirtualKeyEvent) {
+        if self.input_state.virtual_keymap.as_deref() != Some(keymap) {
+            let keyboard = self.keyboard_handle();
+            if let Err(err) = keyboard.set_keymap_from_string(self, keymap.to_string()) {
+                tracing::warn!("Failed to set virtual keyboard keymap: {err}");
+                return;
+            }
+            self.input_state.virtual_keymap = Some(keymap.to_string());
+        }
+
+        self.input_state.virtual_modifiers = modifiers;
+        self.keyboard::<VirtualInput>(event);
+        self.input_state.virtual_modifiers.clear();
+    }
+
+    /// Put the seat's own keymap back after a virtual keyboard replaced it.
+    pub(super) fn restore_keymap(&mut self) {
+        if self.input_state.virtual_keymap.is_some() {
+            self.apply_xkb_settings();
+        }
+    }
+
+    /// Tell the focused client about modifiers a virtual keyboard set.
+    fn send_virtual_modifiers(&mut self, modifiers: &[Modifier], serialized: SerializedMods) {
+        let keyboard = self.keyboard_handle();
+        let Some(focus) = keyboard.current_focus() else { return };
+
+        let modifiers_state = ModifiersState {
+            shift: modifiers.contains(&Modifier::Shift),
+            ctrl: modifiers.contains(&Modifier::Ctrl),
+            alt: modifiers.contains(&Modifier::Alt),
+            logo: modifiers.contains(&Modifier::Super),
+            serialized,
+            ..Default::default()
+        };
+        let seat = self.seat.clone();
+        focus.modifiers(&seat, self, modifiers_state, SERIAL_COUNTER.next_serial());
+    }
+}
+
+impl GlobalDispatch<ZwpVirtualKeyboardManagerV1, ()> for State {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwpVirtualKeyboardManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, _global_data: &()) -> bool {
+        can_use_virtual_input(&client)
+    }
+}
+
+impl Dispatch<ZwpVirtualKeyboardManagerV1, ()> for State {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &ZwpVirtualKeyboardManagerV1,
+        request: zwp_virtual_keyboard_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zwp_virtual_keyboard_manager_v1::Request::CreateVirtualKeyboard { id, .. } = request
+        {
+            data_init.init(id, Mutex::new(VirtualKeyboard::default()));
+        }
+    }
+}
+
+impl Dispatch<ZwpVirtualKeyboardV1, Mutex<VirtualKeyboard>> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &ZwpVirtualKeyboardV1,
+        request: zwp_virtual_keyboard_v1::Request,
+        data: &Mutex<VirtualKeyboard>,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        let mut virtual_keyboard = data.lock().expect("virtual keyboard lock was poisoned");
+
+        match request {
+            zwp_virtual_keyboard_v1::Request::Keymap { format, fd, size } => {
+                if format != KEYMAP_FORMAT_XKB_V1 {
+                    tracing::warn!("Virtual keyboard sent a keymap in unknown format {format}");
+                    return;
+                }
+
+                if size > MAX_KEYMAP_SIZE {
+                    resource.post_error(
+                        zwp_virtual_keyboard_v1::Error::NoKeymap,
+                        format!("keymap is larger than {MAX_KEYMAP_SIZE} bytes"),
+                    );
+                    return;
+                }
+
+                // The client may have written the keymap through this fd, so read from the start
+                let mut bytes = vec![0; size as usize];
+                if let Err(err) = File::from(fd).read_exact_at(&mut bytes, 0) {
+                    tracing::warn!("Failed to read virtual keyboard keymap: {err}");
+                    return;
+                }
+                let keymap = String::from_utf8_lossy(&bytes)
+                    .trim_end_matches('\0')
+                    .to_string();
+
+                match VirtualKeymap::new(keymap) {
+                    Some(keymap) => virtual_keyboard.keymap = Some(keymap),
+                    None => tracing::warn!("Virtual keyboard sent an invalid keymap"),
+                }
+            }
+            zwp_virtual_keyboard_v1::Request::Key {
+                time,
+                key,
+                state: key_state,
+            } => {
+                let Some(keymap) = &virtual_keyboard.keymap else {
+                    resource.post_error(zwp_virtual_keyboard_v1::Error::NoKeymap, "no keymap set");
+                    return;
+                };
+                let keymap = keymap.keymap.clone();
+                let modifiers = virtual_keyboard.modifiers.clone();
+                drop(virtual_keyboard);
+
+                let event = VirtualKeyEvent {
+                    time,
+                    keycode: key,
+                    state: match key_state {
+                        0 => KeyState::Released,
+                        _ => KeyState::Pressed,
+                    },
+                };
+                state.virtual_key(&keymap, modifiers, event);
+            }
+            zwp_virtual_keyboard_v1::Request::Modifiers {
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+            } => {
+                let Some(keymap) = &virtual_keyboard.keymap else {
+                    resource.post_error(zwp_virtual_keyboard_v1::Error::NoKeymap, "no keymap set");
+                    return;
+                };
+                let modifiers = keymap.modifiers(mods_depressed | mods_latched | mods_locked);
+                virtual_keyboard.modifiers = modifiers.clone();
+                drop(virtual_keyboard);
+
+                let serialized = SerializedMods {
+                    depressed: mods_depressed,
+                    latched: mods_latched,
+                    locked: mods_locked,
+                    layout_locked: group,
+                };
+                state.send_virtual_modifiers(&modifiers, serialized);
+            }
+            _ => (),
+        }
+    }
+}
+
+impl GlobalDispatch<ZwlrVirtualPointerManagerV1, ()> for State {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrVirtualPointerManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, _global_data: &()) -> bool {
+        can_use_virtual_input(&client)
+    }
+}
+
+impl Dispatch<ZwlrVirtualPointerManagerV1, ()> for State {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &ZwlrVirtualPointerManagerV1,
+        request: zwlr_virtual_pointer_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_virtual_pointer_manager_v1::Request::CreateVirtualPointer { id, .. }
+            | zwlr_virtual_pointer_manager_v1::Request::CreateVirtualPointerWithOutput {
+                id, ..
+            } => {
+                data_init.init(id, Mutex::new(VirtualPointer::default()));
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<ZwlrVirtualPointerV1, Mutex<VirtualPointer>> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &ZwlrVirtualPointerV1,
+        request: zwlr_virtual_pointer_v1::Request,
+        data: &Mutex<VirtualPointer>,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        let mut virtual_pointer = data.lock().expect("virtual pointer lock was poisoned");
+
+        match request {
+            zwlr_virtual_pointer_v1::Request::Motion { time, dx, dy } => {
+                drop(virtual_pointer);
+                let event = VirtualMotionEvent { time, dx, dy };
+                state.process_input_event(InputEvent::<VirtualInput>::PointerMotion { event });
+            }
+            zwlr_virtual_pointer_v1::Request::MotionAbsolute {
+                time,
+                x,
+                y,
+                x_extent,
+                y_extent,
+            } => {
+                drop(virtual_pointer);
+                if x_extent == 0 || y_extent == 0 {
+                    return;
+                }
+                let event = VirtualMotionAbsoluteEvent {
+                    time,
+                    x,
+                    y,
+                    x_extent,
+                    y_extent,
+                };
+                state.process_input_event(InputEvent::<VirtualInput>::PointerMotionAbsolute {
+                    event,
+                });
+            }
+            zwlr_virtual_pointer_v1::Request::Button {
+                time,
+                button,
+                state: button_state,
+            } => {
+                drop(virtual_pointer);
+                let event = VirtualButtonEvent {
+                    time,
+                    button,
+                    state: match button_state {
+                        WEnum::Value(wl_pointer::ButtonState::Pressed) => ButtonState::Pressed,
+                        _ => ButtonState::Released,
+                    },
+                };
+                state.process_input_event(InputEvent::<VirtualInput>::PointerButton { event });
+            }
+            zwlr_virtual_pointer_v1::Request::Axis { time, axis, value } => {
+                let Some(axis) = backend_axis(axis) else { return };
+                let pending = virtual_pointer.pending_axis(time);
+                pending.amount[VirtualAxisEvent::index(axis)] = Some(value);
+            }
+            zwlr_virtual_pointer_v1::Request::AxisDiscrete {
+                time,
+                axis,
+                value,
+                discrete,
+            } => {
+                let Some(axis) = backend_axis(axis) else { return };
+                let pending = virtual_pointer.pending_axis(time);
+                pending.amount[VirtualAxisEvent::index(axis)] = Some(value);
+                pending.discrete[VirtualAxisEvent::index(axis)] = Some(discrete as f64);
+            }
+            zwlr_virtual_pointer_v1::Request::AxisStop { time, axis } => {
+                let Some(axis) = backend_axis(axis) else { return };
+                let pending = virtual_pointer.pending_axis(time);
+                pending.amount[VirtualAxisEvent::index(axis)] = Some(0.0);
+            }
+            zwlr_virtual_pointer_v1::Request::AxisSource { axis_source } => {
+                let source = match axis_source {
+                    WEnum::Value(wl_pointer::AxisSource::Finger) => AxisSource::Finger,
+                    WEnum::Value(wl_pointer::AxisSource::Continuous) => AxisSource::Continuous,
+                    WEnum::Value(wl_pointer::AxisSource::WheelTilt) => AxisSource::WheelTilt,
+                    _ => AxisSource::Wheel,
+                };
+                virtual_pointer.pending_axis(0).source = source;
+            }
+            zwlr_virtual_pointer_v1::Request::Frame => {
+                let Some(event) = virtual_pointer.pending_axis.take() else { return };
+                drop(virtual_pointer);
+                state.process_input_event(InputEvent::<VirtualInput>::PointerAxis { event });
+            }
+            _ => (),
+        }
+    }
+}
+
+fn backend_axis(axis: WEnum<wl_pointer::Axis>) -> Option<Axis> {
+    match axis {
+        WEnum::Value(wl_pointer::Axis::HorizontalScroll) => Some(Axis::Horizontal),
+        WEnum::Value(wl_pointer::Axis::VerticalScroll) => Some(Axis::Vertical),
+        _ => None,
+    }
+}
@@ -53,6 +53,12 @@ impl State {
     /// Compute tiled window locations and sizes, size maximized and fullscreen windows correctly,
     /// and send configures and that cool stuff.
     pub fn update_windows(&mut self, output: &Output) {
+        // Windows are on the live config's tags, so laying them out against a staged
+        // config's tags would unmap all of them
+        if self.config.in_staged_context {
+            return;
+        }
+
         let Some(layout) = output.with_state(|state| {
             state.focused_tags().next().map(|tag| tag.layout())
         }) else { return };
@@ -20,6 +20,8 @@ use xdg::BaseDirectories;
 
 mod api;
 mod backend;
+mod cli;
+mod config;
 mod cursor;
 mod focus;
 mod grab;
@@ -48,11 +50,16 @@ struct Backends {
     #[arg(long, group = "backend")]
     /// Run Pinnacle from a tty
     udev: bool,
+    #[arg(long, group = "backend")]
+    /// Run Pinnacle without a GPU or display, using virtual outputs
+    headless: bool,
 }
 
 #[derive(clap::Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<cli::Command>,
     #[command(flatten)]
     backend: Backends,
     #[arg(long)]
@@ -66,6 +73,13 @@ struct Args {
 const PINNACLE_LOG_PREFIX: &str = "pinnacle.log";
 
 fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    // Subcommands talk to an already running instance, so they don't need logging
+    if let Some(command) = args.command {
+        return cli::run(command);
+    }
+
     let xdg_state_dir = XDG_BASE_DIRS.get_state_home();
 
     trim_logs(&xdg_state_dir);
@@ -82,13 +96,17 @@ fn main() -> anyhow::Result<()> {
         .with_writer(writer)
         .init();
 
-    let args = Args::parse();
-
     if smithay::reexports::nix::unistd::Uid::effective().is_root() && !args.allow_root {
         println!("You are trying to run Pinnacle as root.\nThis is NOT recommended.\nTo run Pinnacle as root, pass in the --allow-root flag. Again, this is NOT recommended.");
         return Ok(());
     }
 
+    if args.backend.headless {
+        tracing::info!("Starting headless backend");
+        crate::backend::headless::run_headless()?;
+        return Ok(());
+    }
+
     let in_graphical_env =
         std::env::var("WAYLAND_DISPLAY").is_ok() || std::env::var("DISPLAY").is_ok();
 
@@ -12,6 +12,8 @@ pub struct Metaconfig {
     pub envs: Option<Table>,
     pub reload_keybind: Keybind,
     pub kill_keybind: Keybind,
+    /// The keybind that opens a terminal while the fallback config is running.
+    pub terminal_keybind: Option<Keybind>,
     pub socket_dir: Option<String>,
 }
 
@@ -93,6 +95,8 @@ pub enum Key {
     NumNine = keysyms::KEY_KP_9,
     #[serde(alias = "esc")]
     Escape = keysyms::KEY_Escape,
+    #[serde(alias = "enter")]
+    Return = keysyms::KEY_Return,
 }
 
 pub fn parse(config_dir: &Path) -> anyhow::Result<Metaconfig> {
@@ -13,6 +13,7 @@ use smithay::{
     desktop::{
         layer_map_for_output,
         space::{SpaceRenderElements, SurfaceTree},
+        utils::bbox_from_surface_tree,
         Space,
     },
     input::pointer::{CursorImageAttributes, CursorImageStatus},
@@ -26,7 +27,9 @@ use smithay::{
     wayland::{compositor, input_method::InputMethodHandle, shell::wlr_layer},
 };
 
-use crate::{state::WithState, tag::Tag, window::WindowElement};
+use crate::{
+    input::input_method::popup_location, state::WithState, tag::Tag, window::WindowElement,
+};
 
 use self::pointer::{PointerElement, PointerRenderElement};
 
@@ -138,6 +141,7 @@ pub fn generate_render_elements<R, T>(
     renderer: &mut R,
     output: &Output,
     input_method: &InputMethodHandle,
+    text_input_origin: Option<Point<i32, Logical>>,
     pointer_element: &mut PointerElement<T>,
     pointer_image: Option<&TextureBuffer<T>>,
 ) -> Vec<OutputRenderElements<R, WaylandSurfaceRenderElement<R>>>
@@ -152,21 +156,27 @@ where
     let scale = Scale::from(output.current_scale().fractional_scale());
 
     let mut custom_render_elements: Vec<CustomRenderElements<_>> = Vec::new();
-    // draw input method surface if any
-    let rectangle = input_method.coordinates();
-    let position = Point::from((
-        rectangle.loc.x + rectangle.size.w,
-        rectangle.loc.y + rectangle.size.h,
-    ));
-    input_method.with_surface(|surface| {
-        custom_render_elements.extend(AsRenderElements::<R>::render_elements(
-            &SurfaceTree::from_surface(surface),
-            renderer,
-            position.to_physical_precise_round(scale),
-            scale,
-            1.0,
-        ));
-    });
+    // Draw the input method popup next to the focused client's text cursor, on the output with
+    // the cursor. The cursor rectangle is relative to the focused surface, so it needs moving
+    // into the output.
+    if let Some(text_input_origin) = text_input_origin {
+        let mut cursor = input_method.coordinates();
+        cursor.loc += text_input_origin;
+        if output_geometry.contains(cursor.loc) {
+            cursor.loc -= output_geometry.loc;
+            input_method.with_surface(|surface| {
+                let popup_size = bbox_from_surface_tree(surface, (0, 0)).size;
+                let position = popup_location(cursor, popup_size, output_geometry.size);
+                custom_render_elements.extend(AsRenderElements::<R>::render_elements(
+                    &SurfaceTree::from_surface(surface),
+                    renderer,
+                    position.to_physical_precise_round(scale),
+                    scale,
+                    1.0,
+                ));
+            });
+        }
+    }
 
     if output_geometry.to_f64().contains(pointer_location) {
         let cursor_hotspot = if let CursorImageStatus::Surface(ref surface) = cursor_status {
@@ -4,9 +4,10 @@ mod api_handlers;
 
 use std::{
     cell::RefCell,
+    collections::{HashMap, HashSet},
     os::{fd::AsRawFd, unix::net::UnixStream},
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
+    sync::{atomic::AtomicBool, Arc, Mutex},
     time::Duration,
 };
 
@@ -14,16 +15,16 @@ use crate::{
     api::{
         msg::{
             window_rules::{WindowRule, WindowRuleCondition},
-            CallbackId, ModifierMask, Msg,
+            CallbackId, EventKind, ModifierMask, OutgoingMsg,
         },
-        PinnacleSocketSource,
+        ApiClientId, ClientEvent, PinnacleSocketSource,
     },
-    backend::{udev::Udev, winit::Winit, BackendData},
+    backend::{headless::Headless, udev::Udev, winit::Winit, BackendData},
+    config::{self, ConfigState, StagedConfig},
     cursor::Cursor,
     focus::FocusState,
     grab::resize_grab::ResizeSurfaceState,
     metaconfig::Metaconfig,
-    tag::TagId,
     window::WindowElement,
 };
 use anyhow::Context;
@@ -41,8 +42,11 @@ use smithay::{
     output::Output,
     reexports::{
         calloop::{
-            self, channel::Event, generic::Generic, Interest, LoopHandle, LoopSignal, Mode,
-            PostAction,
+            self,
+            channel::Event,
+            generic::Generic,
+            timer::{TimeoutAction, Timer},
+            Interest, LoopHandle, LoopSignal, Mode, PostAction,
         },
         wayland_server::{
             backend::{ClientData, ClientId, DisconnectReason},
@@ -56,11 +60,18 @@ use smithay::{
         data_device::DataDeviceState,
         dmabuf::DmabufFeedback,
         fractional_scale::FractionalScaleManagerState,
+        input_method::InputMethodManagerState,
+        keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitState,
         output::OutputManagerState,
+        pointer_constraints::PointerConstraintsState,
+        pointer_gestures::PointerGesturesState,
         primary_selection::PrimarySelectionState,
+        relative_pointer::RelativePointerManagerState,
         shell::{wlr_layer::WlrLayerShellState, xdg::XdgShellState},
         shm::ShmState,
         socket::ListeningSocketSource,
+        tablet_manager::TabletManagerState,
+        text_input::TextInputManagerState,
         viewporter::ViewporterState,
     },
     xwayland::{X11Surface, X11Wm, XWayland, XWaylandEvent},
@@ -71,6 +82,7 @@ use crate::input::InputState;
 pub enum Backend {
     Winit(Winit),
     Udev(Udev),
+    Headless(Headless),
 }
 
 impl Backend {
@@ -78,6 +90,7 @@ impl Backend {
         match self {
             Backend::Winit(winit) => winit.seat_name(),
             Backend::Udev(udev) => udev.seat_name(),
+            Backend::Headless(headless) => headless.seat_name(),
         }
     }
 
@@ -85,6 +98,7 @@ impl Backend {
         match self {
             Backend::Winit(winit) => winit.early_import(surface),
             Backend::Udev(udev) => udev.early_import(surface),
+            Backend::Headless(headless) => headless.early_import(surface),
         }
     }
 
@@ -122,6 +136,7 @@ pub struct State {
     pub fractional_scale_manager_state: FractionalScaleManagerState,
     pub primary_selection_state: PrimarySelectionState,
     pub layer_shell_state: WlrLayerShellState,
+    pub keyboard_shortcuts_inhibit_state: KeyboardShortcutsInhibitState,
 
     pub input_state: InputState,
     pub api_state: ApiState,
@@ -134,14 +149,14 @@ pub struct State {
     pub dnd_icon: Option<WlSurface>,
 
     pub windows: Vec<WindowElement>,
-    pub window_rules: Vec<(WindowRuleCondition, WindowRule)>,
+    pub window_rules: Vec<(ApiClientId, WindowRuleCondition, WindowRule)>,
 
     pub async_scheduler: Scheduler<()>,
-    pub config_process: async_process::Child,
+    pub config: ConfigState,
 
     // TODO: move into own struct
     // |     basically just clean this mess up
-    pub output_callback_ids: Vec<CallbackId>,
+    pub output_callback_ids: Vec<(ApiClientId, CallbackId)>,
 
     pub xwayland: XWayland,
     pub xwm: Option<X11Wm>,
@@ -183,10 +198,19 @@ impl State {
         }
 
         loop_handle.insert_source(socket, |stream, _metadata, data| {
-            data.display
-                .handle()
-                .insert_client(stream, Arc::new(ClientState::default()))
+            let mut display_handle = data.display.handle();
+            let client_state = Arc::new(ClientState::default());
+            let client = display_handle
+                .insert_client(stream, client_state.clone())
                 .expect("Could not insert client into loop handle");
+
+            // Flatpak puts this file at the root of every sandbox
+            let sandboxed = client.get_credentials(&display_handle).is_ok_and(|creds| {
+                Path::new(&format!("/proc/{}/root/.flatpak-info", creds.pid)).exists()
+            });
+            client_state
+                .sandboxed
+                .store(sandboxed, std::sync::atomic::Ordering::Relaxed);
         })?;
 
         loop_handle.insert_source(
@@ -201,7 +225,7 @@ impl State {
             },
         )?;
 
-        let (tx_channel, rx_channel) = calloop::channel::channel::<Msg>();
+        let (tx_channel, rx_channel) = calloop::channel::channel::<ClientEvent>();
 
         let config_dir = get_config_dir();
         tracing::debug!("config dir is {:?}", config_dir);
@@ -233,22 +257,33 @@ impl State {
             reload_keybind,
             kill_keybind,
             config_child_handle,
-        } = start_config(metaconfig, &config_dir)?;
-
-        let insert_ret = loop_handle.insert_source(socket_source, |stream, _, data| {
-            if let Some(old_stream) = data
-                .state
-                .api_state
-                .stream
-                .replace(Arc::new(Mutex::new(stream)))
-            {
-                old_stream
-                    .lock()
-                    .expect("Couldn't lock old stream")
-                    .shutdown(std::net::Shutdown::Both)
-                    .expect("Couldn't shutdown old stream");
-            }
+        } = start_config(metaconfig, &config_dir);
+
+        let mut config = ConfigState::new();
+        let staged = config_child_handle.and_then(|(process, token)| {
+            StagedConfig::new(process, token, reload_keybind, kill_keybind, &loop_handle)
         });
+        match staged {
+            Ok(staged) => config.staged = Some(staged),
+            Err(err) => {
+                let reason = format!("{err:#}");
+                loop_handle.insert_idle(move |data| data.state.reload_failed(reason));
+            }
+        }
+
+        loop_handle.insert_source(Timer::from_duration(config::POLL_INTERVAL), |_, _, data| {
+            data.state.poll_config_process();
+            TimeoutAction::ToDuration(config::POLL_INTERVAL)
+        })?;
+
+        let insert_ret =
+            loop_handle.insert_source(socket_source, |(client_id, stream), _, data| {
+                tracing::info!("API client {client_id:?} connected");
+                data.state
+                    .api_state
+                    .clients
+                    .insert(client_id, ApiClient::new(stream));
+            });
 
         if let Err(err) = insert_ret {
             anyhow::bail!("Failed to insert socket source into event loop: {err}");
@@ -265,14 +300,50 @@ impl State {
 
         let mut seat = seat_state.new_wl_seat(&display_handle, backend.seat_name());
         seat.add_pointer();
-        seat.add_keyboard(XkbConfig::default(), 200, 25)?;
+        seat.add_touch();
+        seat.add_keyboard(
+            XkbConfig::default(),
+            crate::input::DEFAULT_REPEAT_DELAY,
+            crate::input::DEFAULT_REPEAT_RATE,
+        )?;
+
+        PointerGesturesState::new::<Self>(&display_handle);
+        TabletManagerState::new::<Self>(&display_handle);
+        RelativePointerManagerState::new::<Self>(&display_handle);
+        PointerConstraintsState::new::<Self>(&display_handle);
+        crate::input::virtual_input::create_globals(&display_handle);
+        TextInputManagerState::new::<Self>(&display_handle);
+        InputMethodManagerState::new::<Self>(&display_handle);
 
         loop_handle.insert_idle(|data| {
             data.state
                 .loop_handle
-                .insert_source(rx_channel, |msg, _, data| match msg {
-                    Event::Msg(msg) => data.state.handle_msg(msg),
-                    Event::Closed => todo!(),
+                .insert_source(rx_channel, |event, _, data| match event {
+                    Event::Msg(ClientEvent::Hello {
+                        client_id,
+                        config_token,
+                    }) => data.state.identify_config_client(client_id, config_token),
+                    Event::Msg(ClientEvent::Msg { client_id, msg }) => {
+                        data.state.handle_msg(client_id, msg)
+                    }
+                    Event::Msg(ClientEvent::InvalidMsg {
+                        client_id,
+                        msg_kind,
+                        reason,
+                    }) => data.state.api_state.send(
+                        client_id,
+                        &OutgoingMsg::Error {
+                            msg_kind,
+                            reason,
+                            callback_id: None,
+                        },
+                    ),
+                    Event::Msg(ClientEvent::Disconnected { client_id }) => {
+                        data.state.remove_api_client(client_id)
+                    }
+                    Event::Closed => {
+                        tracing::error!("API socket closed; no more API messages will be handled")
+                    }
                 })
                 .expect("failed to insert rx_channel into loop");
         });
@@ -341,6 +412,9 @@ impl State {
             ),
             primary_selection_state: PrimarySelectionState::new::<Self>(&display_handle),
             layer_shell_state: WlrLayerShellState::new::<Self>(&display_handle),
+            keyboard_shortcuts_inhibit_state: KeyboardShortcutsInhibitState::new::<Self>(
+                &display_handle,
+            ),
 
             input_state: InputState::new(reload_keybind, kill_keybind),
             api_state: ApiState::new(),
@@ -356,7 +430,7 @@ impl State {
             popup_manager: PopupManager::default(),
 
             async_scheduler: sched,
-            config_process: config_child_handle,
+            config,
 
             windows: vec![],
             window_rules: vec![],
@@ -399,7 +473,7 @@ impl State {
     }
 }
 
-fn get_config_dir() -> PathBuf {
+pub fn get_config_dir() -> PathBuf {
     let config_dir = std::env::var("PINNACLE_CONFIG_DIR")
         .ok()
         .and_then(|s| Some(PathBuf::from(shellexpand::full(&s).ok()?.to_string())));
@@ -409,11 +483,27 @@ fn get_config_dir() -> PathBuf {
 
 /// This should be called *after* you have created the [`PinnacleSocketSource`] to ensure
 /// PINNACLE_SOCKET is set correctly for use in API implementations.
-fn start_config(metaconfig: Metaconfig, config_dir: &Path) -> anyhow::Result<ConfigReturn> {
+fn start_config(metaconfig: Metaconfig, config_dir: &Path) -> ConfigReturn {
     let reload_keybind = metaconfig.reload_keybind;
     let kill_keybind = metaconfig.kill_keybind;
 
-    let mut command = metaconfig.command.split(' ');
+    let reload_mask = ModifierMask::from(reload_keybind.modifiers);
+    let kill_mask = ModifierMask::from(kill_keybind.modifiers);
+
+    ConfigReturn {
+        reload_keybind: (reload_mask, reload_keybind.key as u32),
+        kill_keybind: (kill_mask, kill_keybind.key as u32),
+        config_child_handle: spawn_config(metaconfig.command, metaconfig.envs, config_dir),
+    }
+}
+
+/// Spawn the config process, returning it along with the config token it was given.
+fn spawn_config(
+    command_str: String,
+    envs: Option<toml::map::Map<String, toml::Value>>,
+    config_dir: &Path,
+) -> anyhow::Result<(async_process::Child, String)> {
+    let mut command = command_str.split(' ');
 
     let arg1 = command
         .next()
@@ -421,8 +511,7 @@ fn start_config(metaconfig: Metaconfig, config_dir: &Path) -> anyhow::Result<Con
 
     std::env::set_var("PINNACLE_DIR", std::env::current_dir()?);
 
-    let envs = metaconfig
-        .envs
+    let envs = envs
         .unwrap_or(toml::map::Map::new())
         .into_iter()
         .filter_map(|(key, val)| {
@@ -446,53 +535,37 @@ fn start_config(metaconfig: Metaconfig, config_dir: &Path) -> anyhow::Result<Con
 
     tracing::debug!("Config envs are {:?}", envs);
 
+    let token = config::new_config_token().context("Failed to create config token")?;
+
     // Using async_process's Child instead of std::process because I don't have to spawn my own
     // thread to wait for the child
     let child = async_process::Command::new(arg1)
         .args(command)
         .envs(envs)
+        .env(config::CONFIG_TOKEN_VAR, &token)
         .current_dir(config_dir)
         .stdout(async_process::Stdio::inherit())
         .stderr(async_process::Stdio::inherit())
         .spawn()
-        .expect("failed to spawn config");
+        .with_context(|| format!("Failed to run `{command_str}`"))?;
 
-    tracing::info!("Started config with {}", metaconfig.command);
+    tracing::info!("Started config with {}", command_str);
 
-    let reload_mask = ModifierMask::from(reload_keybind.modifiers);
-    let kill_mask = ModifierMask::from(kill_keybind.modifiers);
-
-    Ok(ConfigReturn {
-        reload_keybind: (reload_mask, reload_keybind.key as u32),
-        kill_keybind: (kill_mask, kill_keybind.key as u32),
-        config_child_handle: child,
-    })
+    Ok((child, token))
 }
 
 struct ConfigReturn {
     reload_keybind: (ModifierMask, u32),
     kill_keybind: (ModifierMask, u32),
-    config_child_handle: async_process::Child,
+    config_child_handle: anyhow::Result<(async_process::Child, String)>,
 }
 
 impl State {
+    /// Start the config again.
+    ///
+    /// The new config is staged, and only replaces the running one once it's ready.
     pub fn restart_config(&mut self) -> anyhow::Result<()> {
         tracing::info!("Restarting config");
-        tracing::debug!("Clearing tags");
-        for output in self.space.outputs() {
-            output.with_state(|state| state.tags.clear());
-        }
-        TagId::reset();
-
-        tracing::debug!("Clearing mouse- and keybinds");
-        self.input_state.keybinds.clear();
-        self.input_state.mousebinds.clear();
-        self.window_rules.clear();
-
-        tracing::debug!("Killing old config");
-        if let Err(err) = self.config_process.kill() {
-            tracing::warn!("Error when killing old config: {err}");
-        }
 
         let config_dir = get_config_dir();
 
@@ -503,13 +576,38 @@ impl State {
             reload_keybind,
             kill_keybind,
             config_child_handle,
-        } = start_config(metaconfig, &config_dir)?;
+        } = start_config(metaconfig, &config_dir);
 
-        self.input_state.reload_keybind = reload_keybind;
-        self.input_state.kill_keybind = kill_keybind;
-        self.config_process = config_child_handle;
+        let (process, token) = config_child_handle?;
+        self.stage_config(process, token, reload_keybind, kill_keybind)
+    }
 
-        Ok(())
+    /// Forget about a disconnected API client, removing everything it set up.
+    pub fn remove_api_client(&mut self, client_id: ApiClientId) {
+        tracing::info!("API client {client_id:?} disconnected");
+        self.api_state.clients.remove(&client_id);
+        self.input_state
+            .keybinds
+            .retain(|_, keybind| keybind.client_id != client_id);
+        self.input_state
+            .mousebinds
+            .retain(|_, (id, _)| *id != client_id);
+        self.input_state
+            .gesture_binds
+            .retain(|_, (id, _)| *id != client_id);
+        self.input_state
+            .key_sequences
+            .retain(|_, (id, _)| *id != client_id);
+        self.input_state
+            .touch_outputs
+            .retain(|_, (id, _)| *id != client_id);
+        self.input_state
+            .tablet_outputs
+            .retain(|_, (id, _)| *id != client_id);
+        self.output_callback_ids.retain(|(id, _)| *id != client_id);
+        self.stop_client_key_repeat(client_id, None);
+
+        self.config_disconnected(client_id);
     }
 }
 
@@ -521,6 +619,8 @@ pub struct CalloopData {
 #[derive(Default)]
 pub struct ClientState {
     pub compositor_state: CompositorClientState,
+    /// Whether the client runs in a sandbox, which keeps it from privileged globals.
+    pub sandboxed: AtomicBool,
 }
 
 impl ClientData for ClientState {
@@ -569,17 +669,49 @@ pub fn take_presentation_feedback(
     output_presentation_feedback
 }
 
-/// State containing the config API's stream.
+/// State containing all connected API clients.
 #[derive(Default)]
 pub struct ApiState {
-    // TODO: this may not need to be in an arc mutex because of the move to async
-    pub stream: Option<Arc<Mutex<UnixStream>>>,
+    pub clients: HashMap<ApiClientId, ApiClient>,
 }
 
 impl ApiState {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Get a handle to the stream of the client with the given id.
+    pub fn stream(&self, client_id: ApiClientId) -> Option<Arc<Mutex<UnixStream>>> {
+        self.clients
+            .get(&client_id)
+            .map(|client| client.stream.clone())
+    }
+
+    /// Send `msg` to the client with the given id, if it's still connected.
+    pub fn send(&self, client_id: ApiClientId, msg: &OutgoingMsg) {
+        let Some(client) = self.clients.get(&client_id) else { return };
+        let mut stream = client.stream.lock().expect("Couldn't lock stream");
+        if let Err(err) = crate::api::send_to_client(&mut stream, msg) {
+            tracing::warn!("Failed to send to client {client_id:?}: {err}");
+        }
+    }
+}
+
+/// A client connected to the Pinnacle socket.
+pub struct ApiClient {
+    // TODO: this may not need to be in an arc mutex because of the move to async
+    pub stream: Arc<Mutex<UnixStream>>,
+    /// The kinds of events the client wants to receive.
+    pub subscriptions: HashSet<EventKind>,
+}
+
+impl ApiClient {
+    pub fn new(stream: UnixStream) -> Self {
+        Self {
+            stream: Arc::new(Mutex::new(stream)),
+            subscriptions: HashSet::new(),
+        }
+    }
 }
 
 pub trait WithState {
@@ -1,4 +1,4 @@
-use std::ffi::OsString;
+use std::{ffi::OsString, os::unix::net::UnixStream, time::Duration};
 
 use async_process::Stdio;
 use futures_lite::AsyncBufReadExt;
@@ -10,76 +10,306 @@ use smithay::{
 };
 
 use crate::{
-    api::msg::{
-        Args, CallbackId, KeyIntOrString, Msg, OutgoingMsg, Request, RequestId, RequestResponse,
+    api::{
+        msg::{
+            Args, CallbackId, DeviceOutputInfo, Event, EventKind, KeyCombo, KeyIntOrString,
+            KeybindInfo, LibinputSetting, MousebindInfo, Msg, OutgoingMsg, Request, RequestId,
+            RequestResponse, XkbLayoutSwitch,
+        },
+        ApiClientId,
     },
     focus::FocusTarget,
-    tag::Tag,
-    window::WindowElement,
+    input::{self, libinput::LibinputRule, DeviceOutputs, Keybind, XkbSettings, DEFAULT_BIND_MODE},
+    tag::{Tag, TagId},
+    window::{window_state::WindowId, WindowElement},
 };
 
 use super::{State, WithState};
 
 impl State {
-    pub fn handle_msg(&mut self, msg: Msg) {
+    /// Handle a message sent by the client with the given id.
+    pub fn handle_msg(&mut self, client_id: ApiClientId, msg: Msg) {
         // tracing::debug!("Got {msg:?}");
+        let kind = msg.kind();
+        let callback_id = msg.callback_id();
+
+        // A config that isn't ready yet only gets to see and change its own state
+        let res = if self.config.is_staged_client(client_id) && !matches!(msg, Msg::ConfigReady) {
+            self.with_staged_config(|state| state.handle_msg_inner(client_id, msg))
+        } else {
+            self.handle_msg_inner(client_id, msg)
+        };
+
+        if let Err(err) = res {
+            tracing::warn!("{kind} from client {client_id:?} failed: {err}");
+            self.api_state.send(
+                client_id,
+                &OutgoingMsg::Error {
+                    msg_kind: kind.to_string(),
+                    reason: err.to_string(),
+                    callback_id,
+                },
+            );
+        }
+    }
+
+    fn handle_msg_inner(&mut self, client_id: ApiClientId, msg: Msg) -> Result<(), MsgError> {
         match msg {
+            Msg::Hello { .. } => {
+                self.api_state.send(client_id, &OutgoingMsg::hello());
+            }
             Msg::SetKeybind {
                 key,
                 modifiers,
                 callback_id,
+                mode,
+                edge,
+                allow_repeat,
             } => {
-                let key = match key {
-                    KeyIntOrString::Int(num) => num,
-                    KeyIntOrString::String(s) => {
-                        if s.chars().count() == 1 {
-                            let Some(ch) = s.chars().next() else { unreachable!() };
-                            let raw = xkbcommon::xkb::Keysym::from_char(ch).raw();
-                            tracing::info!("set keybind: {:?}, {:?} (raw {})", modifiers, ch, raw);
-                            raw
-                        } else {
-                            let raw = xkbcommon::xkb::keysym_from_name(
-                                &s,
-                                xkbcommon::xkb::KEYSYM_NO_FLAGS,
-                            )
-                            .raw();
-                            tracing::info!("set keybind: {:?}, {:?}", modifiers, raw);
-                            raw
-                        }
+                let key = keysym_from_key(key)?;
+                let mode = mode.unwrap_or_else(|| DEFAULT_BIND_MODE.to_string());
+                tracing::info!("set keybind: {:?}, {:?} in mode {}", modifiers, key, mode);
+                let bind = (mode, modifiers.into(), key, edge);
+
+                // Clients can't take over each other's keybinds
+                if let Some(keybind) = self.input_state.keybinds.get(&bind) {
+                    if keybind.client_id != client_id {
+                        return Err(MsgError::KeybindTaken);
                     }
-                };
+                }
 
-                self.input_state
-                    .keybinds
-                    .insert((modifiers.into(), key), callback_id);
+                self.input_state.keybinds.insert(
+                    bind,
+                    Keybind {
+                        client_id,
+                        callback_id,
+                        allow_repeat,
+                    },
+                );
             }
             Msg::SetMousebind {
                 modifiers,
                 button,
                 edge,
                 callback_id,
+                mode,
             } => {
                 // TODO: maybe validate/parse valid codes?
+                let mode = mode.unwrap_or_else(|| DEFAULT_BIND_MODE.to_string());
+                let bind = (mode, modifiers.into(), button, edge);
+
+                if let Some((owner, _)) = self.input_state.mousebinds.get(&bind) {
+                    if *owner != client_id {
+                        return Err(MsgError::MousebindTaken);
+                    }
+                }
+
                 self.input_state
                     .mousebinds
-                    .insert((modifiers.into(), button, edge), callback_id);
+                    .insert(bind, (client_id, callback_id));
             }
-            Msg::CloseWindow { window_id } => {
-                if let Some(window) = window_id.window(self) {
-                    match window {
-                        WindowElement::Wayland(window) => window.toplevel().send_close(),
-                        WindowElement::X11(surface) => {
-                            surface.close().expect("failed to close x11 win");
-                        }
+            Msg::SetGestureBind {
+                gesture,
+                fingers,
+                modifiers,
+                callback_id,
+                mode,
+            } => {
+                let mode = mode.unwrap_or_else(|| DEFAULT_BIND_MODE.to_string());
+                let bind = (mode, modifiers.into(), fingers, gesture);
+
+                if let Some((owner, _)) = self.input_state.gesture_binds.get(&bind) {
+                    if *owner != client_id {
+                        return Err(MsgError::GestureBindTaken);
                     }
                 }
+
+                self.input_state
+                    .gesture_binds
+                    .insert(bind, (client_id, callback_id));
+            }
+            Msg::RemoveKeybind {
+                key,
+                modifiers,
+                mode,
+                edge,
+            } => {
+                let key = keysym_from_key(key)?;
+                let mode = mode.unwrap_or_else(|| DEFAULT_BIND_MODE.to_string());
+                let bind = (mode, modifiers.into(), key, edge);
+
+                // Clients can only remove their own keybinds
+                match self.input_state.keybinds.get(&bind) {
+                    Some(&keybind) if keybind.client_id == client_id => {
+                        self.input_state.keybinds.remove(&bind);
+                        self.stop_client_key_repeat(client_id, Some(keybind.callback_id));
+                    }
+                    _ => return Err(MsgError::KeybindNotFound),
+                }
+            }
+            Msg::RemoveMousebind {
+                modifiers,
+                button,
+                edge,
+                mode,
+            } => {
+                let mode = mode.unwrap_or_else(|| DEFAULT_BIND_MODE.to_string());
+                let bind = (mode, modifiers.into(), button, edge);
+
+                match self.input_state.mousebinds.get(&bind) {
+                    Some((owner, _)) if *owner == client_id => {
+                        self.input_state.mousebinds.remove(&bind);
+                    }
+                    _ => return Err(MsgError::MousebindNotFound),
+                }
+            }
+            Msg::RemoveGestureBind {
+                gesture,
+                fingers,
+                modifiers,
+                mode,
+            } => {
+                let mode = mode.unwrap_or_else(|| DEFAULT_BIND_MODE.to_string());
+                let bind = (mode, modifiers.into(), fingers, gesture);
+
+                match self.input_state.gesture_binds.get(&bind) {
+                    Some((owner, _)) if *owner == client_id => {
+                        self.input_state.gesture_binds.remove(&bind);
+                    }
+                    _ => return Err(MsgError::GestureBindNotFound),
+                }
+            }
+            Msg::SetKeySequence {
+                sequence,
+                callback_id,
+                mode,
+            } => {
+                let sequence = key_sequence_from_combos(sequence)?;
+                let mode = mode.unwrap_or_else(|| DEFAULT_BIND_MODE.to_string());
+                let bind = (mode, sequence);
+
+                if let Some((owner, _)) = self.input_state.key_sequences.get(&bind) {
+                    if *owner != client_id {
+                        return Err(MsgError::KeySequenceTaken);
+                    }
+                }
+
+                self.input_state
+                    .key_sequences
+                    .insert(bind, (client_id, callback_id));
+            }
+            Msg::RemoveKeySequence { sequence, mode } => {
+                let sequence = key_sequence_from_combos(sequence)?;
+                let mode = mode.unwrap_or_else(|| DEFAULT_BIND_MODE.to_string());
+                let bind = (mode, sequence);
+
+                match self.input_state.key_sequences.get(&bind) {
+                    Some((owner, _)) if *owner == client_id => {
+                        self.input_state.key_sequences.remove(&bind);
+                    }
+                    _ => return Err(MsgError::KeySequenceNotFound),
+                }
+            }
+            Msg::SetKeySequenceTimeout { timeout_ms } => {
+                self.input_state.key_sequence_timeout = Duration::from_millis(timeout_ms);
+            }
+            Msg::SetShortcutsInhibitEscape { combo } => {
+                self.input_state.shortcuts_inhibit_escape = match combo {
+                    Some(combo) => Some((combo.modifiers.into(), keysym_from_key(combo.key)?)),
+                    None => None,
+                };
+            }
+            Msg::SetBindMode { mode } => {
+                self.set_bind_mode(mode);
+            }
+            Msg::SetXkbConfig {
+                rules,
+                model,
+                layout,
+                variant,
+                options,
+            } => {
+                let settings = XkbSettings::new(
+                    rules.unwrap_or_default(),
+                    model.unwrap_or_default(),
+                    layout.unwrap_or_default(),
+                    variant.unwrap_or_default(),
+                    options,
+                )
+                .ok_or(MsgError::InvalidXkbConfig)?;
+                self.input_state.xkb_settings = settings;
+                self.apply_xkb_settings();
+            }
+            Msg::SetRepeatRate { rate, delay } => {
+                if rate < 0 || delay < 0 {
+                    return Err(MsgError::InvalidRepeatRate);
+                }
+                self.input_state.repeat_rate = rate;
+                self.input_state.repeat_delay = delay;
+                self.apply_repeat_info();
+            }
+            Msg::SetLibinputSetting {
+                setting,
+                device_name,
+                device_type,
+            } => {
+                if let LibinputSetting::AccelSpeed(speed) = setting {
+                    if !(-1.0..=1.0).contains(&speed) {
+                        return Err(MsgError::InvalidAccelSpeed(speed));
+                    }
+                }
+                self.set_libinput_setting(LibinputRule {
+                    device_name,
+                    device_type,
+                    setting,
+                });
+            }
+            Msg::SetTouchOutput {
+                output_name,
+                device_name,
+            } => set_device_output(
+                &mut self.input_state.touch_outputs,
+                client_id,
+                device_name,
+                output_name,
+            )?,
+            Msg::SetTabletOutput {
+                output_name,
+                device_name,
+            } => set_device_output(
+                &mut self.input_state.tablet_outputs,
+                client_id,
+                device_name,
+                output_name,
+            )?,
+            Msg::SwitchXkbLayout { layout } => {
+                let count = self.input_state.xkb_settings.layout_count();
+                let active = self.input_state.xkb_settings.active_layout;
+                self.input_state.xkb_settings.active_layout = match layout {
+                    XkbLayoutSwitch::Next => (active + 1) % count,
+                    XkbLayoutSwitch::Prev => (active + count - 1) % count,
+                    XkbLayoutSwitch::Index(index) if (index as usize) < count => index as usize,
+                    XkbLayoutSwitch::Index(index) => return Err(MsgError::LayoutNotFound(index)),
+                };
+                self.lock_xkb_layout();
+            }
+            Msg::CloseWindow { window_id } => {
+                let window = window_id
+                    .window(self)
+                    .ok_or(MsgError::WindowNotFound(window_id))?;
+                match window {
+                    WindowElement::Wayland(window) => window.toplevel().send_close(),
+                    WindowElement::X11(surface) => surface
+                        .close()
+                        .map_err(|err| MsgError::CloseX11Window(err.to_string()))?,
+                }
             }
 
             Msg::Spawn {
                 command,
                 callback_id,
             } => {
-                self.handle_spawn(command, callback_id);
+                self.handle_spawn(client_id, command, callback_id)?;
             }
             Msg::SetEnv { key, value } => std::env::set_var(key, value),
 
@@ -88,7 +318,9 @@ impl State {
                 width,
                 height,
             } => {
-                let Some(window) = window_id.window(self) else { return };
+                let window = window_id
+                    .window(self)
+                    .ok_or(MsgError::WindowNotFound(window_id))?;
 
                 // TODO: tiled vs floating
                 // FIXME: this will map unmapped windows at 0,0
@@ -109,18 +341,22 @@ impl State {
                 }
             }
             Msg::MoveWindowToTag { window_id, tag_id } => {
-                let Some(window) = window_id.window(self) else { return };
-                let Some(tag) = tag_id.tag(self) else { return };
+                let window = window_id
+                    .window(self)
+                    .ok_or(MsgError::WindowNotFound(window_id))?;
+                let tag = tag_id.tag(self).ok_or(MsgError::TagNotFound(tag_id))?;
                 window.with_state(|state| {
                     state.tags = vec![tag.clone()];
                 });
-                let Some(output) = tag.output(self) else { return };
+                let Some(output) = tag.output(self) else { return Ok(()) };
                 self.update_windows(&output);
                 // self.re_layout(&output);
             }
             Msg::ToggleTagOnWindow { window_id, tag_id } => {
-                let Some(window) = window_id.window(self) else { return };
-                let Some(tag) = tag_id.tag(self) else { return };
+                let window = window_id
+                    .window(self)
+                    .ok_or(MsgError::WindowNotFound(window_id))?;
+                let tag = tag_id.tag(self).ok_or(MsgError::TagNotFound(tag_id))?;
 
                 window.with_state(|state| {
                     if state.tags.contains(&tag) {
@@ -130,39 +366,45 @@ impl State {
                     }
                 });
 
-                let Some(output) = tag.output(self) else { return };
+                let Some(output) = tag.output(self) else { return Ok(()) };
                 self.update_windows(&output);
                 // self.re_layout(&output);
             }
             Msg::ToggleFloating { window_id } => {
-                let Some(window) = window_id.window(self) else { return };
+                let window = window_id
+                    .window(self)
+                    .ok_or(MsgError::WindowNotFound(window_id))?;
                 window.toggle_floating();
 
-                let Some(output) = window.output(self) else { return };
+                let Some(output) = window.output(self) else { return Ok(()) };
                 self.update_windows(&output);
             }
             Msg::ToggleFullscreen { window_id } => {
-                let Some(window) = window_id.window(self) else { return };
+                let window = window_id
+                    .window(self)
+                    .ok_or(MsgError::WindowNotFound(window_id))?;
                 window.toggle_fullscreen();
 
-                let Some(output) = window.output(self) else { return };
+                let Some(output) = window.output(self) else { return Ok(()) };
                 self.update_windows(&output);
             }
             Msg::ToggleMaximized { window_id } => {
-                let Some(window) = window_id.window(self) else { return };
+                let window = window_id
+                    .window(self)
+                    .ok_or(MsgError::WindowNotFound(window_id))?;
                 window.toggle_maximized();
 
-                let Some(output) = window.output(self) else { return };
+                let Some(output) = window.output(self) else { return Ok(()) };
                 self.update_windows(&output);
             }
             Msg::AddWindowRule { cond, rule } => {
-                self.window_rules.push((cond, rule));
+                self.window_rules.push((client_id, cond, rule));
             }
             Msg::WindowMoveGrab { button } => {
                 // TODO: in the future, there may be movable layer surfaces
                 let Some((FocusTarget::Window(window), _)) =
-                    self.surface_under(self.pointer_location) else { return };
-                let Some(wl_surf) = window.wl_surface() else { return };
+                    self.surface_under(self.pointer_location) else { return Ok(()) };
+                let Some(wl_surf) = window.wl_surface() else { return Ok(()) };
                 let seat = self.seat.clone();
 
                 // We use the server one and not the client because windows like Steam don't provide
@@ -179,8 +421,8 @@ impl State {
                 // TODO: in the future, there may be movable layer surfaces
                 let pointer_loc = self.pointer_location;
                 let Some((FocusTarget::Window(window), window_loc)) =
-                    self.surface_under(pointer_loc) else { return };
-                let Some(wl_surf) = window.wl_surface() else { return };
+                    self.surface_under(pointer_loc) else { return Ok(()) };
+                let Some(wl_surf) = window.wl_surface() else { return Ok(()) };
 
                 let window_geometry = window.geometry();
                 let window_x = window_loc.x as f64;
@@ -233,23 +475,34 @@ impl State {
             // Tags ----------------------------------------
             Msg::ToggleTag { tag_id } => {
                 tracing::debug!("ToggleTag");
-                if let Some(tag) = tag_id.tag(self) {
-                    tag.set_active(!tag.active());
-                    if let Some(output) = tag.output(self) {
-                        self.update_windows(&output);
-                        // self.re_layout(&output);
-                    }
+                let tag = tag_id.tag(self).ok_or(MsgError::TagNotFound(tag_id))?;
+                tag.set_active(!tag.active());
+                self.signal_tag_event(Event::TagActivated {
+                    tag_id: tag.id(),
+                    active: tag.active(),
+                });
+                if let Some(output) = tag.output(self) {
+                    self.update_windows(&output);
+                    // self.re_layout(&output);
                 }
             }
             Msg::SwitchToTag { tag_id } => {
-                let Some(tag) = tag_id.tag(self) else { return };
-                let Some(output) = tag.output(self) else { return };
-                output.with_state(|state| {
+                let tag = tag_id.tag(self).ok_or(MsgError::TagNotFound(tag_id))?;
+                let Some(output) = tag.output(self) else { return Ok(()) };
+                let changed_tags = output.with_state(|state| {
+                    let mut changed_tags = vec![];
                     for op_tag in state.tags.iter_mut() {
-                        op_tag.set_active(false);
+                        let active = op_tag == &tag;
+                        if op_tag.active() != active {
+                            changed_tags.push((op_tag.id(), active));
+                        }
+                        op_tag.set_active(active);
                     }
-                    tag.set_active(true);
+                    changed_tags
                 });
+                for (tag_id, active) in changed_tags {
+                    self.signal_tag_event(Event::TagActivated { tag_id, active });
+                }
                 self.update_windows(&output);
                 // self.re_layout(&output);
             }
@@ -257,72 +510,70 @@ impl State {
                 output_name,
                 tag_names,
             } => {
-                if let Some(output) = self
+                let output = self
                     .space
                     .outputs()
                     .find(|output| output.name() == output_name)
-                {
-                    let new_tags = tag_names.into_iter().map(Tag::new).collect::<Vec<_>>();
-                    output.with_state(|state| {
-                        state.tags.extend(new_tags.clone());
-                        tracing::debug!("tags added, are now {:?}", state.tags);
-                    });
+                    .ok_or(MsgError::OutputNotFound(output_name))?;
 
-                    // replace tags that windows have that are the same id
-                    // (this should only happen on config reload)
-                    for tag in new_tags {
-                        for window in self.windows.iter() {
-                            window.with_state(|state| {
-                                for win_tag in state.tags.iter_mut() {
-                                    if win_tag.id() == tag.id() {
-                                        *win_tag = tag.clone();
-                                    }
-                                }
-                            });
-                        }
-                    }
+                let new_tags = tag_names.into_iter().map(Tag::new).collect::<Vec<_>>();
+                output.with_state(|state| {
+                    state.tags.extend(new_tags.iter().cloned());
+                    tracing::debug!("tags added, are now {:?}", state.tags);
+                });
+
+                let output_name = output.name();
+                for tag in new_tags {
+                    self.signal_tag_event(Event::TagAdded {
+                        tag_id: tag.id(),
+                        output_name: output_name.clone(),
+                    });
                 }
             }
             Msg::RemoveTags { tag_ids } => {
-                let tags = tag_ids.into_iter().filter_map(|tag_id| tag_id.tag(self));
+                // Don't remove any tags if one of them doesn't exist
+                let tags = tag_ids
+                    .into_iter()
+                    .map(|tag_id| tag_id.tag(self).ok_or(MsgError::TagNotFound(tag_id)))
+                    .collect::<Result<Vec<_>, _>>()?;
                 for tag in tags {
                     let Some(output) = tag.output(self) else { continue };
                     output.with_state(|state| {
                         state.tags.retain(|tg| tg != &tag);
                     });
+                    self.signal_tag_event(Event::TagRemoved { tag_id: tag.id() });
                 }
             }
             Msg::SetLayout { tag_id, layout } => {
-                let Some(tag) = tag_id.tag(self) else { return };
+                let tag = tag_id.tag(self).ok_or(MsgError::TagNotFound(tag_id))?;
                 tag.set_layout(layout);
-                let Some(output) = tag.output(self) else { return };
+                self.signal_tag_event(Event::LayoutChanged {
+                    tag_id: tag.id(),
+                    layout,
+                });
+                let Some(output) = tag.output(self) else { return Ok(()) };
                 self.update_windows(&output);
                 // self.re_layout(&output);
             }
 
             Msg::ConnectForAllOutputs { callback_id } => {
-                let stream = self
-                    .api_state
-                    .stream
-                    .as_ref()
-                    .expect("Stream doesn't exist");
-                let mut stream = stream.lock().expect("Couldn't lock stream");
                 for output in self.space.outputs() {
-                    crate::api::send_to_client(
-                        &mut stream,
+                    self.api_state.send(
+                        client_id,
                         &OutgoingMsg::CallCallback {
                             callback_id,
                             args: Some(Args::ConnectForAllOutputs {
                                 output_name: output.name(),
                             }),
                         },
-                    )
-                    .expect("Send to client failed");
+                    );
                 }
-                self.output_callback_ids.push(callback_id);
+                self.output_callback_ids.push((client_id, callback_id));
             }
             Msg::SetOutputLocation { output_name, x, y } => {
-                let Some(output) = output_name.output(self) else { return };
+                let output = output_name
+                    .output(self)
+                    .ok_or_else(|| MsgError::OutputNotFound(output_name.0.clone()))?;
                 let mut loc = output.current_location();
                 if let Some(x) = x {
                     loc.x = x;
@@ -337,28 +588,186 @@ impl State {
                 // self.re_layout(&output);
             }
 
+            Msg::Subscribe { events } => {
+                let Some(client) = self.api_state.clients.get_mut(&client_id) else { return Ok(()) };
+                let newly_connected = events.contains(&EventKind::OutputConnected)
+                    && !client.subscriptions.contains(&EventKind::OutputConnected);
+                client.subscriptions.extend(events);
+
+                // Outputs connected before the subscription would otherwise never be reported
+                if newly_connected {
+                    for output in self.space.outputs() {
+                        self.api_state.send(
+                            client_id,
+                            &OutgoingMsg::Event {
+                                event: Event::OutputConnected {
+                                    output_name: output.name(),
+                                },
+                            },
+                        );
+                    }
+                }
+            }
+            Msg::Unsubscribe { events } => {
+                let Some(client) = self.api_state.clients.get_mut(&client_id) else { return Ok(()) };
+                for event in events {
+                    client.subscriptions.remove(&event);
+                }
+            }
+
             Msg::Quit => {
                 self.loop_signal.stop();
             }
+            Msg::ConfigReady => {
+                // Only a staged config has anything to swap in
+                if self.config.is_staged_client(client_id) {
+                    self.commit_staged_config();
+                }
+            }
 
             Msg::Request {
                 request_id,
                 request,
             } => {
-                self.handle_request(request_id, request);
+                self.handle_request(client_id, request_id, request);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send `event` to every client that subscribed to events of its kind.
+    pub fn signal_event(&self, event: Event) {
+        let kind = event.kind();
+        let msg = OutgoingMsg::Event { event };
+        for (client_id, client) in self.api_state.clients.iter() {
+            if client.subscriptions.contains(&kind) {
+                self.api_state.send(*client_id, &msg);
             }
         }
     }
 
-    fn handle_request(&mut self, request_id: RequestId, request: Request) {
-        let stream = self
-            .api_state
-            .stream
-            .as_ref()
-            .expect("Stream doesn't exist")
-            .clone();
+    /// Send a tag event like [`State::signal_event`], unless it's about a staged config's tags.
+    ///
+    /// Those tags may never go live, so they're only announced once the config is committed.
+    fn signal_tag_event(&self, event: Event) {
+        if !self.config.in_staged_context {
+            self.signal_event(event);
+        }
+    }
+
+    fn handle_request(&mut self, client_id: ApiClientId, request_id: RequestId, request: Request) {
+        let Some(stream) = self.api_state.stream(client_id) else { return };
         let mut stream = stream.lock().expect("Couldn't lock stream");
         match request {
+            Request::GetKeybinds => {
+                let keybinds = self
+                    .input_state
+                    .keybinds
+                    .iter()
+                    .map(|((mode, modifiers, key, edge), keybind)| KeybindInfo {
+                        mode: mode.clone(),
+                        modifiers: modifiers.values(),
+                        key: *key,
+                        key_name: xkbcommon::xkb::keysym_get_name((*key).into()),
+                        edge: *edge,
+                        allow_repeat: keybind.allow_repeat,
+                        callback_id: (keybind.client_id == client_id)
+                            .then_some(keybind.callback_id),
+                    })
+                    .collect::<Vec<_>>();
+                crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::Keybinds { keybinds },
+                    },
+                )
+                .expect("failed to send to client");
+            }
+            Request::GetBindMode => {
+                let mode = self.input_state.bind_mode.clone();
+                crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::BindMode { mode },
+                    },
+                )
+                .expect("failed to send to client");
+            }
+            Request::GetXkbLayout => {
+                let settings = &self.input_state.xkb_settings;
+                let name = settings.active_layout_name().unwrap_or_default();
+                crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::XkbLayout {
+                            index: settings.active_layout as u32,
+                            name: name.to_string(),
+                        },
+                    },
+                )
+                .expect("failed to send to client");
+            }
+            Request::GetInputDevices => {
+                let devices = self.input_devices();
+                crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::InputDevices { devices },
+                    },
+                )
+                .expect("failed to send to client");
+            }
+            Request::GetTouchOutputs => {
+                let mappings = device_output_infos(&self.input_state.touch_outputs);
+                crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::TouchOutputs { mappings },
+                    },
+                )
+                .expect("failed to send to client");
+            }
+            Request::GetTabletOutputs => {
+                let mappings = device_output_infos(&self.input_state.tablet_outputs);
+                crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::TabletOutputs { mappings },
+                    },
+                )
+                .expect("failed to send to client");
+            }
+            Request::GetMousebinds => {
+                let mousebinds = self
+                    .input_state
+                    .mousebinds
+                    .iter()
+                    .map(
+                        |((mode, modifiers, button, edge), &(owner, callback_id))| MousebindInfo {
+                            mode: mode.clone(),
+                            modifiers: modifiers.values(),
+                            button: *button,
+                            edge: *edge,
+                            callback_id: (owner == client_id).then_some(callback_id),
+                        },
+                    )
+                    .collect::<Vec<_>>();
+                crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::Mousebinds { mousebinds },
+                    },
+                )
+                .expect("failed to send to client");
+            }
             Request::GetWindows => {
                 let window_ids = self
                     .windows
@@ -377,17 +786,19 @@ impl State {
                 .expect("Couldn't send to client");
             }
             Request::GetWindowProps { window_id } => {
-                let window = window_id.window(self);
-                let size = window
-                    .as_ref()
-                    .map(|win| (win.geometry().size.w, win.geometry().size.h));
-                let loc = window
-                    .as_ref()
-                    .and_then(|win| self.space.element_location(win))
+                let Some(window) = window_id.window(self) else {
+                    let err = MsgError::WindowNotFound(window_id);
+                    send_request_error(&mut stream, request_id, err);
+                    return;
+                };
+                let size = Some((window.geometry().size.w, window.geometry().size.h));
+                let loc = self
+                    .space
+                    .element_location(&window)
                     .map(|loc| (loc.x, loc.y));
-                let (class, title) = window.as_ref().map_or((None, None), |win| match &win {
+                let (class, title) = match &window {
                     WindowElement::Wayland(_) => {
-                        if let Some(wl_surf) = win.wl_surface() {
+                        if let Some(wl_surf) = window.wl_surface() {
                             compositor::with_states(&wl_surf, |states| {
                                 let lock = states
                                     .data_map
@@ -402,17 +813,15 @@ impl State {
                         }
                     }
                     WindowElement::X11(surface) => (Some(surface.class()), Some(surface.title())),
-                });
-                let focused = window.as_ref().and_then(|win| {
-                    let output = win.output(self)?;
-                    self.current_focus(&output).map(|foc_win| win == &foc_win)
-                });
-                let floating = window
-                    .as_ref()
-                    .map(|win| win.with_state(|state| state.floating_or_tiled.is_floating()));
-                let fullscreen_or_maximized = window
-                    .as_ref()
-                    .map(|win| win.with_state(|state| state.fullscreen_or_maximized));
+                };
+                let focused = window
+                    .output(self)
+                    .and_then(|output| self.current_focus(&output))
+                    .map(|foc_win| window == foc_win);
+                let floating =
+                    Some(window.with_state(|state| state.floating_or_tiled.is_floating()));
+                let fullscreen_or_maximized =
+                    Some(window.with_state(|state| state.fullscreen_or_maximized));
                 crate::api::send_to_client(
                     &mut stream,
                     &OutgoingMsg::RequestResponse {
@@ -517,13 +926,14 @@ impl State {
                 .expect("failed to send to client");
             }
             Request::GetTagProps { tag_id } => {
-                let tag = tag_id.tag(self);
-                let output_name = tag
-                    .as_ref()
-                    .and_then(|tag| tag.output(self))
-                    .map(|output| output.name());
-                let active = tag.as_ref().map(|tag| tag.active());
-                let name = tag.as_ref().map(|tag| tag.name());
+                let Some(tag) = tag_id.tag(self) else {
+                    let err = MsgError::TagNotFound(tag_id);
+                    send_request_error(&mut stream, request_id, err);
+                    return;
+                };
+                let output_name = tag.output(self).map(|output| output.name());
+                let active = Some(tag.active());
+                let name = Some(tag.name());
                 crate::api::send_to_client(
                     &mut stream,
                     &OutgoingMsg::RequestResponse {
@@ -540,15 +950,19 @@ impl State {
         }
     }
 
-    pub fn handle_spawn(&self, command: Vec<String>, callback_id: Option<CallbackId>) {
+    pub fn handle_spawn(
+        &self,
+        client_id: ApiClientId,
+        command: Vec<String>,
+        callback_id: Option<CallbackId>,
+    ) -> Result<(), MsgError> {
         let mut command = command.into_iter();
         let Some(program) = command.next() else {
-            // TODO: notify that command was nothing
-            return;
+            return Err(MsgError::EmptyCommand);
         };
 
         let program = OsString::from(program);
-        let Ok(mut child) = async_process::Command::new(&program)
+        let mut child = async_process::Command::new(&program)
             .envs(
                 [("WAYLAND_DISPLAY", self.socket_name.clone())]
                     .into_iter()
@@ -575,21 +989,15 @@ impl State {
             })
             .args(command)
             .spawn()
-        else {
-            // TODO: notify user that program doesn't exist
-            tracing::warn!("tried to run {}, but it doesn't exist", program.to_string_lossy());
-            return;
-        };
+            .map_err(|source| MsgError::Spawn {
+                program: program.to_string_lossy().to_string(),
+                source,
+            })?;
 
         if let Some(callback_id) = callback_id {
             let stdout = child.stdout.take();
             let stderr = child.stderr.take();
-            let stream_out = self
-                .api_state
-                .stream
-                .as_ref()
-                .expect("Stream doesn't exist")
-                .clone();
+            let Some(stream_out) = self.api_state.stream(client_id) else { return Ok(()) };
             let stream_err = stream_out.clone();
             let stream_exit = stream_out.clone();
 
@@ -692,5 +1100,142 @@ impl State {
                 tracing::error!("Failed to schedule future: {err}");
             }
         }
+
+        Ok(())
+    }
+}
+
+/// Turn a key sent by a client into a keysym.
+fn keysym_from_key(key: KeyIntOrString) -> Result<u32, MsgError> {
+    match key {
+        KeyIntOrString::Int(num) => Ok(num),
+        KeyIntOrString::String(s) => {
+            if s.chars().count() == 1 {
+                let Some(ch) = s.chars().next() else { unreachable!() };
+                Ok(xkbcommon::xkb::Keysym::from_char(ch).raw())
+            } else {
+                let raw =
+                    xkbcommon::xkb::keysym_from_name(&s, xkbcommon::xkb::KEYSYM_NO_FLAGS).raw();
+                if raw == xkbcommon::xkb::keysyms::KEY_NoSymbol {
+                    return Err(MsgError::InvalidKey(s));
+                }
+                Ok(raw)
+            }
+        }
+    }
+}
+
+/// Reply to a request that couldn't be answered with a [`RequestResponse::Error`].
+fn send_request_error(stream: &mut UnixStream, request_id: RequestId, err: MsgError) {
+    crate::api::send_to_client(
+        stream,
+        &OutgoingMsg::RequestResponse {
+            request_id,
+            response: RequestResponse::Error {
+                reason: err.to_string(),
+            },
+        },
+    )
+    .expect("failed to send to client");
+}
+
+/// Turn the key combos of a key sequence sent by a client into keysyms.
+fn key_sequence_from_combos(combos: Vec<KeyCombo>) -> Result<Vec<input::KeyCombo>, MsgError> {
+    if combos.is_empty() {
+        return Err(MsgError::EmptyKeySequence);
+    }
+
+    combos
+        .into_iter()
+        .map(|combo| Ok((combo.modifiers.into(), keysym_from_key(combo.key)?)))
+        .collect()
+}
+
+/// Map a device to an output for a client, or unmap it if `output_name` is `None`.
+///
+/// Clients can't change mappings other clients set.
+fn set_device_output(
+    device_outputs: &mut DeviceOutputs,
+    client_id: ApiClientId,
+    device_name: Option<String>,
+    output_name: Option<String>,
+) -> Result<(), MsgError> {
+    let owner = device_outputs.get(&device_name).map(|&(owner, _)| owner);
+    match (output_name, owner) {
+        (Some(_), Some(owner)) if owner != client_id => Err(MsgError::DeviceOutputTaken),
+        (Some(output_name), _) => {
+            device_outputs.insert(device_name, (client_id, output_name));
+            Ok(())
+        }
+        (None, Some(owner)) if owner == client_id => {
+            device_outputs.remove(&device_name);
+            Ok(())
+        }
+        (None, _) => Err(MsgError::DeviceOutputNotFound),
     }
 }
+
+/// List the outputs devices are mapped to, to send to a client.
+fn device_output_infos(device_outputs: &DeviceOutputs) -> Vec<DeviceOutputInfo> {
+    device_outputs
+        .iter()
+        .map(|(device_name, (_, output_name))| DeviceOutputInfo {
+            device_name: device_name.clone(),
+            output_name: output_name.clone(),
+        })
+        .collect()
+}
+
+/// Why a [`Msg`] couldn't be handled.
+///
+/// These are sent back to the client in an [`OutgoingMsg::Error`].
+#[derive(Debug, thiserror::Error)]
+pub enum MsgError {
+    #[error("window {0:?} doesn't exist")]
+    WindowNotFound(WindowId),
+    #[error("tag {0:?} doesn't exist")]
+    TagNotFound(TagId),
+    #[error("output {0} doesn't exist")]
+    OutputNotFound(String),
+    #[error("failed to close x11 window: {0}")]
+    CloseX11Window(String),
+    #[error("{0} isn't a valid key name")]
+    InvalidKey(String),
+    #[error("no keybind set by this client matches")]
+    KeybindNotFound,
+    #[error("another client already set this keybind")]
+    KeybindTaken,
+    #[error("another client already set this mousebind")]
+    MousebindTaken,
+    #[error("another client already set this gesture bind")]
+    GestureBindTaken,
+    #[error("another client already set this key sequence")]
+    KeySequenceTaken,
+    #[error("no mousebind set by this client matches")]
+    MousebindNotFound,
+    #[error("no gesture bind set by this client matches")]
+    GestureBindNotFound,
+    #[error("no key sequence set by this client matches")]
+    KeySequenceNotFound,
+    #[error("another client already mapped this device to an output")]
+    DeviceOutputTaken,
+    #[error("no output mapping set by this client matches")]
+    DeviceOutputNotFound,
+    #[error("key sequence was empty")]
+    EmptyKeySequence,
+    #[error("xkb couldn't build a keymap from the given names")]
+    InvalidXkbConfig,
+    #[error("key repeat rate and delay can't be negative")]
+    InvalidRepeatRate,
+    #[error("acceleration speed {0} isn't between -1.0 and 1.0")]
+    InvalidAccelSpeed(f64),
+    #[error("there is no layout at index {0}")]
+    LayoutNotFound(u32),
+    #[error("command was empty")]
+    EmptyCommand,
+    #[error("failed to spawn {program}: {source}")]
+    Spawn {
+        program: String,
+        source: std::io::Error,
+    },
+}
@@ -354,74 +354,130 @@ impl PointerTarget<State> for WindowElement {
 
     fn gesture_swipe_begin(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureSwipeBeginEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureSwipeBeginEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_swipe_begin(window, seat, data, event);
+            }
+            WindowElement::X11(surface) => {
+                PointerTarget::gesture_swipe_begin(surface, seat, data, event);
+            }
+        }
     }
 
     fn gesture_swipe_update(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureSwipeUpdateEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureSwipeUpdateEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_swipe_update(window, seat, data, event);
+            }
+            WindowElement::X11(surface) => {
+                PointerTarget::gesture_swipe_update(surface, seat, data, event);
+            }
+        }
     }
 
     fn gesture_swipe_end(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureSwipeEndEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureSwipeEndEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_swipe_end(window, seat, data, event);
+            }
+            WindowElement::X11(surface) => {
+                PointerTarget::gesture_swipe_end(surface, seat, data, event);
+            }
+        }
     }
 
     fn gesture_pinch_begin(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GesturePinchBeginEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GesturePinchBeginEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_pinch_begin(window, seat, data, event);
+            }
+            WindowElement::X11(surface) => {
+                PointerTarget::gesture_pinch_begin(surface, seat, data, event);
+            }
+        }
     }
 
     fn gesture_pinch_update(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GesturePinchUpdateEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GesturePinchUpdateEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_pinch_update(window, seat, data, event);
+            }
+            WindowElement::X11(surface) => {
+                PointerTarget::gesture_pinch_update(surface, seat, data, event);
+            }
+        }
     }
 
     fn gesture_pinch_end(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GesturePinchEndEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GesturePinchEndEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_pinch_end(window, seat, data, event);
+            }
+            WindowElement::X11(surface) => {
+                PointerTarget::gesture_pinch_end(surface, seat, data, event);
+            }
+        }
     }
 
     fn gesture_hold_begin(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureHoldBeginEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureHoldBeginEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_hold_begin(window, seat, data, event);
+            }
+            WindowElement::X11(surface) => {
+                PointerTarget::gesture_hold_begin(surface, seat, data, event);
+            }
+        }
     }
 
     fn gesture_hold_end(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureHoldEndEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureHoldEndEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_hold_end(window, seat, data, event);
+            }
+            WindowElement::X11(surface) => {
+                PointerTarget::gesture_hold_end(surface, seat, data, event);
+            }
+        }
     }
 }
 
@@ -597,7 +653,7 @@ impl Blocker for WindowBlocker {
 impl State {
     pub fn apply_window_rules(&mut self, window: &WindowElement) {
         tracing::debug!("Applying window rules");
-        for (cond, rule) in self.window_rules.iter() {
+        for (_, cond, rule) in self.window_rules.iter() {
             if cond.is_met(self, window) {
                 let WindowRule {
                     output,
@@ -72,6 +72,8 @@ pub struct WindowElementState {
     pub tags: Vec<Tag>,
     pub floating_or_tiled: FloatingOrTiled,
     pub fullscreen_or_maximized: FullscreenOrMaximized,
+    /// The window's title as of its last commit, used to detect title changes.
+    pub title: Option<String>,
 }
 
 /// The state of a window's resize operation.
@@ -360,6 +362,7 @@ impl Default for WindowElementState {
             tags: vec![],
             floating_or_tiled: FloatingOrTiled::Tiled(None),
             fullscreen_or_maximized: FullscreenOrMaximized::Neither,
+            title: None,
         }
     }
 }
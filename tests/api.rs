@@ -0,0 +1,1128 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+mod common;
+
+use common::{Pinnacle, TestClient, TestWindow};
+use serde_json::{json, Value};
+
+// Evdev codes for the virtual keyboard and pointer
+const KEY_ESC: u32 = 1;
+const KEY_T: u32 = 20;
+const KEY_A: u32 = 30;
+const KEY_X: u32 = 45;
+const KEY_B: u32 = 48;
+const BTN_LEFT: u32 = 0x110;
+
+/// Wait until the window with `class` exists and has committed a buffer, returning its id.
+fn wait_for_window(pinnacle: &mut Pinnacle, class: &str) -> u64 {
+    pinnacle.wait_until(|pinnacle| {
+        pinnacle.windows().into_iter().find(|&id| {
+            let props = pinnacle.window_props(id);
+            props["class"] == class && props["size"] != json!([0, 0])
+        })
+    })
+}
+
+/// Open a window and wait until it has keyboard focus, so keys that aren't intercepted reach it.
+fn open_focused_window(pinnacle: &mut Pinnacle, client: &TestClient) -> TestWindow {
+    let window = client.create_window("keys", "keys");
+    wait_for_window(pinnacle, "keys");
+    client.wait_for_keyboard_focus();
+    window
+}
+
+fn wait_for_size(pinnacle: &mut Pinnacle, window_id: u64, size: (i32, i32)) {
+    pinnacle.wait_until(|pinnacle| {
+        (pinnacle.window_props(window_id)["size"] == json!([size.0, size.1])).then_some(())
+    });
+}
+
+#[test]
+fn headless_output_exists() {
+    let mut pinnacle = Pinnacle::start();
+
+    assert_eq!(pinnacle.outputs(), vec!["HEADLESS-1".to_string()]);
+
+    let props = pinnacle.request(json!({ "GetOutputProps": { "output_name": "HEADLESS-1" } }));
+    let props = &props["OutputProps"];
+    assert_eq!(props["res"], json!([1920, 1080]));
+    assert_eq!(props["loc"], json!([0, 0]));
+    assert_eq!(props["focused"], json!(true));
+}
+
+#[test]
+fn add_and_switch_tags() {
+    let mut pinnacle = Pinnacle::start();
+
+    let tags = pinnacle.add_tags(&["1", "2", "3"]);
+    assert_eq!(tags.len(), 3);
+
+    let props = pinnacle.tag_props(tags[0]);
+    assert_eq!(props["name"], "1");
+    assert_eq!(props["output_name"], "HEADLESS-1");
+    assert_eq!(props["active"], json!(true));
+    assert_eq!(pinnacle.tag_props(tags[1])["active"], json!(false));
+
+    pinnacle.send(json!({ "SwitchToTag": { "tag_id": tags[2] } }));
+    assert_eq!(pinnacle.tag_props(tags[0])["active"], json!(false));
+    assert_eq!(pinnacle.tag_props(tags[2])["active"], json!(true));
+
+    pinnacle.send(json!({ "ToggleTag": { "tag_id": tags[1] } }));
+    assert_eq!(pinnacle.tag_props(tags[1])["active"], json!(true));
+    assert_eq!(pinnacle.tag_props(tags[2])["active"], json!(true));
+
+    pinnacle.send(json!({ "RemoveTags": { "tag_ids": [tags[1]] } }));
+    assert_eq!(pinnacle.tags(), vec![tags[0], tags[2]]);
+    let props = pinnacle.request(json!({ "GetTagProps": { "tag_id": tags[1] } }));
+    assert!(props["Error"]["reason"].is_string());
+}
+
+#[test]
+fn window_has_class_and_title() {
+    let mut pinnacle = Pinnacle::start();
+    pinnacle.add_tags(&["1"]);
+
+    let client = pinnacle.client();
+    let _window = client.create_window("test_class", "test title");
+
+    let window_id = wait_for_window(&mut pinnacle, "test_class");
+    let props = pinnacle.window_props(window_id);
+    assert_eq!(props["title"], "test title");
+    assert_eq!(props["floating"], json!(false));
+    assert_eq!(props["fullscreen_or_maximized"], "Neither");
+
+    // A single tiled window takes up the whole output
+    wait_for_size(&mut pinnacle, window_id, (1920, 1080));
+    assert_eq!(pinnacle.window_props(window_id)["loc"], json!([0, 0]));
+}
+
+#[test]
+fn closed_window_is_removed() {
+    let mut pinnacle = Pinnacle::start();
+    pinnacle.add_tags(&["1"]);
+
+    let client = pinnacle.client();
+    let window = client.create_window("test_class", "test title");
+    wait_for_window(&mut pinnacle, "test_class");
+
+    window.close();
+    pinnacle.wait_until(|pinnacle| pinnacle.windows().is_empty().then_some(()));
+}
+
+#[test]
+fn window_rule_makes_window_floating() {
+    let mut pinnacle = Pinnacle::start();
+    pinnacle.add_tags(&["1"]);
+
+    pinnacle.send(json!({
+        "AddWindowRule": {
+            "cond": { "class": ["floater"] },
+            "rule": { "floating_or_tiled": "Floating" },
+        }
+    }));
+
+    let client = pinnacle.client();
+    let _floating = client.create_window("floater", "");
+    let _tiled = client.create_window("tiler", "");
+
+    let floating_id = wait_for_window(&mut pinnacle, "floater");
+    let tiled_id = wait_for_window(&mut pinnacle, "tiler");
+
+    pinnacle.wait_until(|pinnacle| {
+        (pinnacle.window_props(floating_id)["floating"] == json!(true)).then_some(())
+    });
+    assert_eq!(pinnacle.window_props(tiled_id)["floating"], json!(false));
+
+    // The floating window shouldn't take space from the tiled one
+    wait_for_size(&mut pinnacle, tiled_id, (1920, 1080));
+}
+
+#[test]
+fn toggle_floating_retiles() {
+    let mut pinnacle = Pinnacle::start();
+    pinnacle.add_tags(&["1"]);
+
+    let client = pinnacle.client();
+    let _first = client.create_window("first", "");
+    let first_id = wait_for_window(&mut pinnacle, "first");
+    let _second = client.create_window("second", "");
+    let second_id = wait_for_window(&mut pinnacle, "second");
+
+    // Master stack with two windows splits the output in half
+    wait_for_size(&mut pinnacle, first_id, (960, 1080));
+    wait_for_size(&mut pinnacle, second_id, (960, 1080));
+
+    pinnacle.send(json!({ "ToggleFloating": { "window_id": second_id } }));
+    assert_eq!(pinnacle.window_props(second_id)["floating"], json!(true));
+    wait_for_size(&mut pinnacle, first_id, (1920, 1080));
+
+    pinnacle.send(json!({ "ToggleFloating": { "window_id": second_id } }));
+    assert_eq!(pinnacle.window_props(second_id)["floating"], json!(false));
+    wait_for_size(&mut pinnacle, first_id, (960, 1080));
+}
+
+#[test]
+fn move_window_to_tag_retiles() {
+    let mut pinnacle = Pinnacle::start();
+    let tags = pinnacle.add_tags(&["1", "2"]);
+
+    let client = pinnacle.client();
+    let _first = client.create_window("first", "");
+    let first_id = wait_for_window(&mut pinnacle, "first");
+    let _second = client.create_window("second", "");
+    let second_id = wait_for_window(&mut pinnacle, "second");
+
+    wait_for_size(&mut pinnacle, first_id, (960, 1080));
+
+    pinnacle.send(json!({ "MoveWindowToTag": { "window_id": second_id, "tag_id": tags[1] } }));
+    wait_for_size(&mut pinnacle, first_id, (1920, 1080));
+
+    pinnacle.send(json!({ "SwitchToTag": { "tag_id": tags[1] } }));
+    wait_for_size(&mut pinnacle, second_id, (1920, 1080));
+}
+
+#[test]
+fn subscribed_events_are_sent() {
+    let mut pinnacle = Pinnacle::start();
+    let tags = pinnacle.add_tags(&["1", "2"]);
+
+    pinnacle.send(json!({ "Subscribe": { "events": ["WindowOpened", "TagActivated"] } }));
+
+    pinnacle.send(json!({ "SwitchToTag": { "tag_id": tags[1] } }));
+    assert_eq!(
+        pinnacle.recv(),
+        json!({ "Event": { "event": { "TagActivated": { "tag_id": tags[0], "active": false } } } })
+    );
+    assert_eq!(
+        pinnacle.recv(),
+        json!({ "Event": { "event": { "TagActivated": { "tag_id": tags[1], "active": true } } } })
+    );
+
+    let client = pinnacle.client();
+    let _window = client.create_window("test_class", "");
+    let window_id = wait_for_window(&mut pinnacle, "test_class");
+    assert_eq!(
+        pinnacle.recv(),
+        json!({ "Event": { "event": { "WindowOpened": { "window_id": window_id } } } })
+    );
+
+    // Unsubscribed kinds aren't sent
+    pinnacle.send(json!({ "Unsubscribe": { "events": ["TagActivated"] } }));
+    pinnacle.send(json!({ "SwitchToTag": { "tag_id": tags[0] } }));
+    pinnacle.send(json!({ "Subscribe": { "events": ["LayoutChanged"] } }));
+    pinnacle.send(json!({ "SetLayout": { "tag_id": tags[0], "layout": "Dwindle" } }));
+    assert_eq!(
+        pinnacle.recv(),
+        json!({ "Event": { "event": { "LayoutChanged": { "tag_id": tags[0], "layout": "Dwindle" } } } })
+    );
+}
+
+#[test]
+fn output_and_tag_events_are_sent() {
+    let mut pinnacle = Pinnacle::start();
+
+    // Outputs that were already connected are reported on subscription
+    pinnacle.send(json!({ "Subscribe": { "events": ["OutputConnected"] } }));
+    assert_eq!(
+        pinnacle.recv(),
+        json!({ "Event": { "event": { "OutputConnected": { "output_name": "HEADLESS-1" } } } })
+    );
+
+    pinnacle.send(json!({ "Subscribe": { "events": ["TagAdded", "TagRemoved"] } }));
+    let tags = pinnacle.add_tags(&["1"]);
+    assert_eq!(
+        pinnacle.recv(),
+        json!({
+            "Event": { "event": { "TagAdded": { "tag_id": tags[0], "output_name": "HEADLESS-1" } } }
+        })
+    );
+
+    pinnacle.send(json!({ "RemoveTags": { "tag_ids": tags } }));
+    assert_eq!(
+        pinnacle.recv(),
+        json!({ "Event": { "event": { "TagRemoved": { "tag_id": tags[0] } } } })
+    );
+}
+
+#[test]
+fn failed_msgs_reply_with_errors() {
+    let mut pinnacle = Pinnacle::start();
+    let tags = pinnacle.add_tags(&["1"]);
+
+    pinnacle.send(json!({ "MoveWindowToTag": { "window_id": 1000, "tag_id": tags[0] } }));
+    let error = pinnacle.recv();
+    assert_eq!(error["Error"]["msg_kind"], "MoveWindowToTag");
+    assert_eq!(error["Error"]["callback_id"], Value::Null);
+
+    pinnacle.send(json!({ "SwitchToTag": { "tag_id": 1000 } }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "SwitchToTag");
+
+    pinnacle.send(json!({
+        "AddTags": { "output_name": "nonexistent", "tag_names": ["1"] }
+    }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "AddTags");
+
+    // No tags are removed if any of them don't exist
+    pinnacle.send(json!({ "RemoveTags": { "tag_ids": [tags[0], 1000] } }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "RemoveTags");
+    assert_eq!(pinnacle.tags(), tags);
+
+    let props = pinnacle.request(json!({ "GetWindowProps": { "window_id": 1000 } }));
+    assert!(props["Error"]["reason"].is_string());
+
+    pinnacle.send(json!({
+        "Spawn": { "command": ["pinnacle-test-nonexistent-program"], "callback_id": 5 }
+    }));
+    let error = pinnacle.recv();
+    assert_eq!(error["Error"]["msg_kind"], "Spawn");
+    assert_eq!(error["Error"]["callback_id"], 5);
+    assert!(error["Error"]["reason"]
+        .as_str()
+        .is_some_and(|reason| reason.contains("pinnacle-test-nonexistent-program")));
+}
+
+#[test]
+fn multiple_api_clients() {
+    let mut pinnacle = Pinnacle::start();
+    let tags = pinnacle.add_tags(&["1", "2"]);
+
+    let mut other = pinnacle.connect();
+    assert_eq!(other.tags(), tags);
+
+    // Events only go to clients that subscribed
+    other.send(json!({ "Subscribe": { "events": ["TagActivated"] } }));
+    pinnacle.send(json!({ "SwitchToTag": { "tag_id": tags[1] } }));
+    assert_eq!(
+        other.recv(),
+        json!({ "Event": { "event": { "TagActivated": { "tag_id": tags[0], "active": false } } } })
+    );
+    assert_eq!(
+        other.recv(),
+        json!({ "Event": { "event": { "TagActivated": { "tag_id": tags[1], "active": true } } } })
+    );
+
+    // Callbacks and errors go back to the client that sent the message
+    other.send(json!({ "ConnectForAllOutputs": { "callback_id": 7 } }));
+    assert_eq!(
+        other.recv(),
+        json!({
+            "CallCallback": {
+                "callback_id": 7,
+                "args": { "ConnectForAllOutputs": { "output_name": "HEADLESS-1" } },
+            }
+        })
+    );
+    other.send(json!({ "MoveWindowToTag": { "window_id": 1000, "tag_id": tags[0] } }));
+    assert_eq!(other.recv()["Error"]["msg_kind"], "MoveWindowToTag");
+
+    // The config client didn't get any of that, and keeps working after the other one
+    // disconnects
+    drop(other);
+    pinnacle.send(json!({ "SwitchToTag": { "tag_id": 1000 } }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "SwitchToTag");
+    assert_eq!(pinnacle.tags(), tags);
+}
+
+#[test]
+fn handshake_rejects_other_protocol_versions() {
+    let pinnacle = Pinnacle::start();
+
+    let mut client = pinnacle.connect_without_hello();
+    client.send(json!({ "Hello": { "protocol_version": 0 } }));
+    let error = client.recv();
+    assert_eq!(error["Error"]["msg_kind"], "Hello");
+    assert!(error["Error"]["reason"]
+        .as_str()
+        .unwrap()
+        .contains("protocol version 0"));
+    assert!(client.is_closed());
+}
+
+#[test]
+fn handshake_is_required_first() {
+    let mut pinnacle = Pinnacle::start();
+
+    let mut client = pinnacle.connect_without_hello();
+    client.send(json!({ "AddTags": { "output_name": "HEADLESS-1", "tag_names": ["1"] } }));
+    assert_eq!(client.recv()["Error"]["msg_kind"], "Hello");
+    assert!(client.is_closed());
+
+    // The rejected message was never handled, and other clients are unaffected
+    assert_eq!(pinnacle.tags(), Vec::<u64>::new());
+}
+
+#[test]
+fn handshake_lists_features() {
+    let pinnacle = Pinnacle::start();
+
+    let mut client = pinnacle.connect_without_hello();
+    client.send(json!({ "Hello": { "protocol_version": 2 } }));
+    let hello = client.recv();
+    assert_eq!(hello["Hello"]["protocol_version"], 2);
+    let features = hello["Hello"]["features"].as_array().unwrap();
+    let expected = [
+        "events",
+        "config_ready",
+        "bind_modes",
+        "key_sequences",
+        "xkb",
+        "tablet",
+    ];
+    for feature in expected {
+        assert!(
+            features.contains(&json!(feature)),
+            "missing feature {feature}"
+        );
+    }
+}
+
+#[test]
+fn malformed_messages_dont_close_the_connection() {
+    let mut pinnacle = Pinnacle::start();
+
+    // Not MessagePack at all
+    pinnacle.send_frame(&[0xc1]);
+    let error = pinnacle.recv();
+    assert_eq!(error["Error"]["msg_kind"], "Unknown");
+
+    // A real message with the wrong fields still reports its name
+    pinnacle.send(json!({ "SwitchToTag": { "tag": "1" } }));
+    let error = pinnacle.recv();
+    assert_eq!(error["Error"]["msg_kind"], "SwitchToTag");
+
+    // A frame over the size limit is skipped without reading it as a message
+    pinnacle.send_frame(&vec![0u8; 1024 * 1024 + 1]);
+    let error = pinnacle.recv();
+    assert!(error["Error"]["reason"]
+        .as_str()
+        .unwrap()
+        .contains("maximum"));
+
+    let tags = pinnacle.add_tags(&["1"]);
+    assert_eq!(pinnacle.tags(), tags);
+}
+
+#[test]
+fn config_is_restarted_when_it_disconnects() {
+    let mut pinnacle = Pinnacle::start();
+    pinnacle.add_tags(&["1", "2"]);
+    pinnacle.reconnect();
+    // Restarting the config clears everything the old one set up
+    pinnacle.wait_until(|pinnacle| pinnacle.tags().is_empty().then_some(()));
+}
+
+#[test]
+fn restarted_config_replaces_old_one_once_ready() {
+    let mut pinnacle = Pinnacle::start();
+    let old_tags = pinnacle.add_tags(&["1", "2"]);
+
+    let mut other = pinnacle.connect();
+    other.send(json!({
+        "SetKeybind": { "key": { "String": "a" }, "modifiers": ["Super"], "callback_id": 1 }
+    }));
+
+    pinnacle.close_config();
+
+    // A restarted config that dies before it's ready doesn't take the old tags with it
+    let mut config = pinnacle.connect_as_config();
+    config.add_tags(&["3"]);
+    drop(config);
+
+    // Clients that connect while a config is starting aren't taken for it
+    let mut config = pinnacle.connect_as_config();
+    let mut bystander = pinnacle.connect();
+    bystander.send(json!("ConfigReady"));
+    assert_eq!(bystander.tags(), old_tags);
+
+    let new_tags = config.add_tags(&["1"]);
+    assert_eq!(other.tags(), old_tags);
+
+    config.send(json!("ConfigReady"));
+    assert_eq!(config.tags(), new_tags);
+    assert_eq!(other.tags(), new_tags);
+
+    // Binds other clients set survive the swap
+    assert_eq!(
+        other.request(json!("GetKeybinds"))["Keybinds"]["keybinds"][0]["callback_id"],
+        1
+    );
+}
+
+#[test]
+fn staged_config_tag_events_are_sent_once_ready() {
+    let mut pinnacle = Pinnacle::start();
+    let output_name = pinnacle.outputs()[0].clone();
+    let old_tags = pinnacle.add_tags(&["1"]);
+
+    let mut other = pinnacle.connect();
+    other.send(json!({
+        "Subscribe": { "events": ["TagAdded", "TagRemoved", "TagActivated", "LayoutChanged"] }
+    }));
+    other.assert_no_errors();
+
+    pinnacle.close_config();
+
+    // A staged config that dies before it's ready never has its tags announced
+    let mut config = pinnacle.connect_as_config();
+    let dropped_tags = config.add_tags(&["2"]);
+    config.send(json!({ "SetLayout": { "tag_id": dropped_tags[0], "layout": "Dwindle" } }));
+    config.assert_no_errors();
+    drop(config);
+    assert!(other.take_events().is_empty());
+
+    let mut config = pinnacle.connect_as_config();
+    let new_tags = config.add_tags(&["3"]);
+    assert!(other.take_events().is_empty());
+
+    config.send(json!("ConfigReady"));
+    config.assert_no_errors();
+    assert_eq!(
+        other.take_events(),
+        vec![
+            json!({ "event": { "TagRemoved": { "tag_id": old_tags[0] } } }),
+            json!({
+                "event": { "TagAdded": { "tag_id": new_tags[0], "output_name": output_name } }
+            }),
+            json!({ "event": { "TagActivated": { "tag_id": new_tags[0], "active": true } } }),
+        ]
+    );
+}
+
+#[test]
+fn keybinds_can_be_listed_and_removed() {
+    let mut pinnacle = Pinnacle::start();
+
+    pinnacle.send(json!({
+        "SetKeybind": { "key": { "String": "Return" }, "modifiers": ["Super"], "callback_id": 3 }
+    }));
+    pinnacle.send(json!({
+        "SetMousebind": { "modifiers": [], "button": 0x110, "edge": "Press", "callback_id": 4 }
+    }));
+
+    assert_eq!(
+        pinnacle.request(json!("GetKeybinds")),
+        json!({
+            "Keybinds": {
+                "keybinds": [{
+                    "mode": "default",
+                    "modifiers": ["Super"],
+                    "key": 0xff0d,
+                    "key_name": "Return",
+                    "edge": "Press",
+                    "allow_repeat": false,
+                    "callback_id": 3,
+                }]
+            }
+        })
+    );
+
+    // Other clients can see the binds, but not their callbacks, and can't remove them
+    let mut other = pinnacle.connect();
+    assert_eq!(
+        other.request(json!("GetMousebinds")),
+        json!({
+            "Mousebinds": {
+                "mousebinds": [{
+                    "mode": "default",
+                    "modifiers": [],
+                    "button": 0x110,
+                    "edge": "Press",
+                    "callback_id": null,
+                }]
+            }
+        })
+    );
+    other.send(json!({
+        "RemoveKeybind": { "key": { "Int": 0xff0d }, "modifiers": ["Super"] }
+    }));
+    assert_eq!(other.recv()["Error"]["msg_kind"], "RemoveKeybind");
+
+    // ...or replace them
+    other.send(json!({
+        "SetKeybind": { "key": { "String": "Return" }, "modifiers": ["Super"], "callback_id": 9 }
+    }));
+    assert_eq!(other.recv()["Error"]["msg_kind"], "SetKeybind");
+    other.send(json!({
+        "SetMousebind": { "modifiers": [], "button": 0x110, "edge": "Press", "callback_id": 9 }
+    }));
+    assert_eq!(other.recv()["Error"]["msg_kind"], "SetMousebind");
+    assert_eq!(
+        pinnacle.request(json!("GetKeybinds"))["Keybinds"]["keybinds"][0]["callback_id"],
+        3
+    );
+
+    pinnacle.send(json!({
+        "RemoveKeybind": { "key": { "Int": 0xff0d }, "modifiers": ["Super"] }
+    }));
+    pinnacle.send(json!({
+        "RemoveMousebind": { "modifiers": [], "button": 0x110, "edge": "Press" }
+    }));
+    assert_eq!(
+        pinnacle.request(json!("GetKeybinds")),
+        json!({ "Keybinds": { "keybinds": [] } })
+    );
+    assert_eq!(
+        pinnacle.request(json!("GetMousebinds")),
+        json!({ "Mousebinds": { "mousebinds": [] } })
+    );
+
+    pinnacle.send(json!({
+        "RemoveKeybind": { "key": { "String": "Return" }, "modifiers": ["Super"] }
+    }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "RemoveKeybind");
+}
+
+#[test]
+fn bind_modes() {
+    let mut pinnacle = Pinnacle::start();
+    assert_eq!(
+        pinnacle.request(json!("GetBindMode")),
+        json!({ "BindMode": { "mode": "default" } })
+    );
+
+    pinnacle.send(json!({
+        "SetKeybind": {
+            "key": { "String": "h" },
+            "modifiers": [],
+            "callback_id": 1,
+            "mode": "resize",
+        }
+    }));
+    let keybinds = pinnacle.request(json!("GetKeybinds"));
+    assert_eq!(keybinds["Keybinds"]["keybinds"][0]["mode"], "resize");
+
+    pinnacle.send(json!({ "Subscribe": { "events": ["BindModeChanged"] } }));
+    pinnacle.send(json!({ "SetBindMode": { "mode": "resize" } }));
+    assert_eq!(
+        pinnacle.recv(),
+        json!({ "Event": { "event": { "BindModeChanged": { "mode": "resize" } } } })
+    );
+    assert_eq!(
+        pinnacle.request(json!("GetBindMode")),
+        json!({ "BindMode": { "mode": "resize" } })
+    );
+
+    // The keybind only exists in its mode
+    pinnacle.send(json!({
+        "RemoveKeybind": { "key": { "String": "h" }, "modifiers": [] }
+    }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "RemoveKeybind");
+    pinnacle.send(json!({
+        "RemoveKeybind": { "key": { "String": "h" }, "modifiers": [], "mode": "resize" }
+    }));
+    assert_eq!(
+        pinnacle.request(json!("GetKeybinds")),
+        json!({ "Keybinds": { "keybinds": [] } })
+    );
+
+    // So do mousebinds and gesture binds
+    let mousebind = json!({ "modifiers": [], "button": 0x110, "edge": "Press" });
+    let mut set = mousebind.clone();
+    set["callback_id"] = json!(2);
+    set["mode"] = json!("resize");
+    pinnacle.send(json!({ "SetMousebind": set }));
+    let mousebinds = pinnacle.request(json!("GetMousebinds"));
+    assert_eq!(mousebinds["Mousebinds"]["mousebinds"][0]["mode"], "resize");
+    pinnacle.send(json!({ "RemoveMousebind": mousebind }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "RemoveMousebind");
+    let mut remove = mousebind.clone();
+    remove["mode"] = json!("resize");
+    pinnacle.send(json!({ "RemoveMousebind": remove }));
+    assert_eq!(
+        pinnacle.request(json!("GetMousebinds")),
+        json!({ "Mousebinds": { "mousebinds": [] } })
+    );
+
+    let gesture_bind = json!({ "gesture": "Hold", "fingers": 3, "modifiers": [] });
+    let mut set = gesture_bind.clone();
+    set["callback_id"] = json!(3);
+    set["mode"] = json!("resize");
+    pinnacle.send(json!({ "SetGestureBind": set }));
+    pinnacle.send(json!({ "RemoveGestureBind": gesture_bind }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "RemoveGestureBind");
+    let mut remove = gesture_bind.clone();
+    remove["mode"] = json!("resize");
+    pinnacle.send(json!({ "RemoveGestureBind": remove }));
+    pinnacle.assert_no_errors();
+}
+
+#[test]
+fn key_sequences_can_be_set_and_removed() {
+    let mut pinnacle = Pinnacle::start();
+
+    let sequence = json!([
+        { "modifiers": ["Super"], "key": { "String": "a" } },
+        { "modifiers": [], "key": { "String": "t" } },
+    ]);
+    pinnacle.send(json!({ "SetKeySequence": { "sequence": sequence, "callback_id": 1 } }));
+    pinnacle.send(json!({ "SetKeySequenceTimeout": { "timeout_ms": 500 } }));
+
+    pinnacle.send(json!({ "SetKeySequence": { "sequence": [], "callback_id": 2 } }));
+    let error = pinnacle.recv();
+    assert_eq!(error["Error"]["msg_kind"], "SetKeySequence");
+    assert_eq!(error["Error"]["callback_id"], 2);
+
+    pinnacle.send(json!({
+        "SetKeySequence": {
+            "sequence": [{ "modifiers": [], "key": { "String": "not a key" } }],
+            "callback_id": 3,
+        }
+    }));
+    assert_eq!(pinnacle.recv()["Error"]["callback_id"], 3);
+
+    pinnacle.send(json!({ "RemoveKeySequence": { "sequence": sequence } }));
+    pinnacle.assert_no_errors();
+    pinnacle.send(json!({ "RemoveKeySequence": { "sequence": sequence } }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "RemoveKeySequence");
+}
+
+#[test]
+fn shortcuts_inhibit_escape_can_be_set() {
+    let mut pinnacle = Pinnacle::start();
+
+    pinnacle.send(json!({
+        "SetShortcutsInhibitEscape": {
+            "combo": { "modifiers": ["Super"], "key": { "String": "Escape" } }
+        }
+    }));
+    pinnacle.send(json!({ "SetShortcutsInhibitEscape": { "combo": null } }));
+
+    pinnacle.send(json!({
+        "SetShortcutsInhibitEscape": {
+            "combo": { "modifiers": [], "key": { "String": "not a key" } }
+        }
+    }));
+    assert_eq!(
+        pinnacle.recv()["Error"]["msg_kind"],
+        "SetShortcutsInhibitEscape"
+    );
+}
+
+#[test]
+fn keybinds_have_edges() {
+    let mut pinnacle = Pinnacle::start();
+
+    pinnacle.send(json!({
+        "SetKeybind": {
+            "key": { "String": "Super_L" },
+            "modifiers": ["Super"],
+            "callback_id": 1,
+            "edge": "Release",
+        }
+    }));
+    pinnacle.send(json!({
+        "SetKeybind": {
+            "key": { "String": "XF86AudioRaiseVolume" },
+            "modifiers": [],
+            "callback_id": 2,
+            "allow_repeat": true,
+        }
+    }));
+
+    let keybinds = pinnacle.request(json!("GetKeybinds"));
+    let mut keybinds = keybinds["Keybinds"]["keybinds"].as_array().unwrap().clone();
+    keybinds.sort_by_key(|bind| bind["callback_id"].as_u64());
+    assert_eq!(keybinds[0]["edge"], "Release");
+    assert_eq!(keybinds[0]["allow_repeat"], false);
+    assert_eq!(keybinds[1]["edge"], "Press");
+    assert_eq!(keybinds[1]["allow_repeat"], true);
+
+    // Press and release keybinds on the same keys are separate
+    pinnacle.send(json!({
+        "RemoveKeybind": { "key": { "String": "Super_L" }, "modifiers": ["Super"] }
+    }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "RemoveKeybind");
+    pinnacle.send(json!({
+        "RemoveKeybind": {
+            "key": { "String": "Super_L" },
+            "modifiers": ["Super"],
+            "edge": "Release",
+        }
+    }));
+    let keybinds = pinnacle.request(json!("GetKeybinds"));
+    assert_eq!(
+        keybinds["Keybinds"]["keybinds"].as_array().unwrap().len(),
+        1
+    );
+}
+
+#[test]
+fn xkb_layouts_can_be_set_and_switched() {
+    let mut pinnacle = Pinnacle::start();
+    assert_eq!(
+        pinnacle.request(json!("GetXkbLayout"))["XkbLayout"]["index"],
+        0
+    );
+
+    pinnacle.send(json!({ "SetXkbConfig": { "layout": "not-a-layout" } }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "SetXkbConfig");
+
+    pinnacle.send(json!({ "SetXkbConfig": { "layout": "us,de" } }));
+    pinnacle.send(json!({ "SwitchXkbLayout": { "layout": "Next" } }));
+    assert_eq!(
+        pinnacle.request(json!("GetXkbLayout")),
+        json!({ "XkbLayout": { "index": 1, "name": "German" } })
+    );
+
+    pinnacle.send(json!({ "SwitchXkbLayout": { "layout": "Next" } }));
+    assert_eq!(
+        pinnacle.request(json!("GetXkbLayout"))["XkbLayout"]["index"],
+        0
+    );
+
+    pinnacle.send(json!({ "SwitchXkbLayout": { "layout": { "Index": 2 } } }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "SwitchXkbLayout");
+
+    pinnacle.send(json!({ "SetRepeatRate": { "rate": -1, "delay": 300 } }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "SetRepeatRate");
+}
+
+#[test]
+fn libinput_settings_are_accepted() {
+    let mut pinnacle = Pinnacle::start();
+
+    pinnacle.send(json!({
+        "SetLibinputSetting": { "setting": { "Tap": true }, "device_type": "Touchpad" }
+    }));
+    pinnacle.send(json!({
+        "SetLibinputSetting": { "setting": { "AccelSpeed": 0.5 }, "device_name": "Some Mouse" }
+    }));
+    pinnacle.assert_no_errors();
+
+    pinnacle.send(json!({ "SetLibinputSetting": { "setting": { "AccelSpeed": 1.5 } } }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "SetLibinputSetting");
+    pinnacle.send(json!({ "SetLibinputSetting": { "setting": { "ScrollMethod": "Sideways" } } }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "SetLibinputSetting");
+
+    // The headless backend has no libinput devices
+    assert_eq!(
+        pinnacle.request(json!("GetInputDevices")),
+        json!({ "InputDevices": { "devices": [] } })
+    );
+}
+
+#[test]
+fn touch_outputs_can_be_mapped_and_unmapped() {
+    let mut pinnacle = Pinnacle::start();
+    let output_name = pinnacle.outputs()[0].clone();
+
+    pinnacle.send(json!({ "SetTouchOutput": { "output_name": output_name } }));
+    pinnacle.send(json!({
+        "SetTouchOutput": { "output_name": output_name, "device_name": "Some Touchscreen" }
+    }));
+    assert_eq!(
+        pinnacle.touch_outputs(),
+        vec![
+            (None, output_name.clone()),
+            (Some("Some Touchscreen".to_string()), output_name.clone()),
+        ]
+    );
+
+    pinnacle.send(json!({
+        "SetTouchOutput": { "output_name": null, "device_name": "Some Touchscreen" }
+    }));
+    // Mapping to an output that doesn't exist is fine; it may be plugged in later
+    pinnacle.send(json!({ "SetTouchOutput": { "output_name": "HDMI-A-9" } }));
+    assert_eq!(
+        pinnacle.touch_outputs(),
+        vec![(None, "HDMI-A-9".to_string())]
+    );
+
+    pinnacle.send(json!({
+        "SetTouchOutput": { "output_name": null, "device_name": "Some Touchscreen" }
+    }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "SetTouchOutput");
+
+    // Other clients can't take over or unmap a mapping
+    let mut other = pinnacle.connect();
+    other.send(json!({ "SetTouchOutput": { "output_name": output_name } }));
+    assert_eq!(other.recv()["Error"]["msg_kind"], "SetTouchOutput");
+    other.send(json!({ "SetTouchOutput": { "output_name": null } }));
+    assert_eq!(other.recv()["Error"]["msg_kind"], "SetTouchOutput");
+    assert_eq!(other.touch_outputs(), vec![(None, "HDMI-A-9".to_string())]);
+
+    // Mappings go away with the client that set them
+    other.send(json!({
+        "SetTouchOutput": { "output_name": output_name, "device_name": "Other Touchscreen" }
+    }));
+    assert_eq!(other.touch_outputs().len(), 2);
+    drop(other);
+    pinnacle.wait_until(|pinnacle| (pinnacle.touch_outputs().len() == 1).then_some(()));
+    assert_eq!(
+        pinnacle.touch_outputs(),
+        vec![(None, "HDMI-A-9".to_string())]
+    );
+}
+
+#[test]
+fn tablet_outputs_can_be_mapped_and_unmapped() {
+    let mut pinnacle = Pinnacle::start();
+    let output_name = pinnacle.outputs()[0].clone();
+
+    pinnacle.send(json!({
+        "SetTabletOutput": { "output_name": output_name, "device_name": "Some Tablet Pen" }
+    }));
+    pinnacle.send(json!({ "SetTabletOutput": { "output_name": output_name } }));
+    pinnacle.send(json!({ "SetTabletOutput": { "output_name": null } }));
+    assert_eq!(
+        pinnacle.tablet_outputs(),
+        vec![(Some("Some Tablet Pen".to_string()), output_name.clone())]
+    );
+    // Touch mappings are separate
+    assert!(pinnacle.touch_outputs().is_empty());
+
+    pinnacle.send(json!({ "SetTabletOutput": { "output_name": null } }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "SetTabletOutput");
+
+    // Other clients can't take over or unmap a mapping
+    let mut other = pinnacle.connect();
+    let pen = json!("Some Tablet Pen");
+    other.send(json!({ "SetTabletOutput": { "output_name": output_name, "device_name": pen } }));
+    assert_eq!(other.recv()["Error"]["msg_kind"], "SetTabletOutput");
+    other.send(json!({ "SetTabletOutput": { "output_name": null, "device_name": pen } }));
+    assert_eq!(other.recv()["Error"]["msg_kind"], "SetTabletOutput");
+
+    other.send(json!({ "SetTabletOutput": { "output_name": output_name } }));
+    assert_eq!(other.tablet_outputs().len(), 2);
+    drop(other);
+    pinnacle.wait_until(|pinnacle| (pinnacle.tablet_outputs().len() == 1).then_some(()));
+}
+
+#[test]
+fn unbound_gestures_go_to_the_window_under_the_pointer() {
+    let mut pinnacle = Pinnacle::start();
+    pinnacle.add_tags(&["1"]);
+    let client = pinnacle.client();
+    let _window = open_focused_window(&mut pinnacle, &client);
+    client.virtual_pointer().move_by(100.0, 100.0);
+
+    pinnacle.send(json!({
+        "SetGestureBind": {
+            "gesture": { "Swipe": "Left" },
+            "fingers": 4,
+            "modifiers": [],
+            "callback_id": 1,
+        }
+    }));
+    pinnacle.assert_no_errors();
+
+    pinnacle.gesture(json!({ "Swipe": { "fingers": 3, "dx": 100.0, "dy": 0.0 } }));
+    pinnacle.gesture(json!({ "Pinch": { "fingers": 3, "scale": 2.0 } }));
+    pinnacle.gesture(json!({ "Hold": { "fingers": 3 } }));
+    // Gestures are played in order, so the ones above were handled once this one is called
+    pinnacle.gesture(json!({ "Swipe": { "fingers": 4, "dx": -100.0, "dy": 0.0 } }));
+    assert_eq!(pinnacle.recv()["CallCallback"]["callback_id"], 1);
+    assert_eq!(pinnacle.windows().len(), 1);
+}
+
+#[test]
+fn gesture_binds_can_be_set_and_removed() {
+    let mut pinnacle = Pinnacle::start();
+
+    let bind = json!({ "gesture": { "Swipe": "Left" }, "fingers": 3, "modifiers": ["Super"] });
+    let mut set = bind.clone();
+    set["callback_id"] = json!(1);
+    pinnacle.send(json!({ "SetGestureBind": set }));
+
+    let mut other = pinnacle.connect();
+    other.send(json!({ "RemoveGestureBind": bind }));
+    assert_eq!(other.recv()["Error"]["msg_kind"], "RemoveGestureBind");
+
+    pinnacle.send(json!({ "RemoveGestureBind": bind }));
+    pinnacle.assert_no_errors();
+    pinnacle.send(json!({ "RemoveGestureBind": bind }));
+    assert_eq!(pinnacle.recv()["Error"]["msg_kind"], "RemoveGestureBind");
+}
+
+#[test]
+fn keys_trigger_keybinds_on_their_edge() {
+    let mut pinnacle = Pinnacle::start();
+    pinnacle.add_tags(&["1"]);
+    let client = pinnacle.client();
+    let _window = open_focused_window(&mut pinnacle, &client);
+    let keyboard = client.virtual_keyboard();
+
+    pinnacle.send(json!({
+        "SetKeybind": { "key": { "String": "a" }, "modifiers": ["Super"], "callback_id": 1 }
+    }));
+    pinnacle.send(json!({
+        "SetKeybind": {
+            "key": { "String": "x" },
+            "modifiers": [],
+            "callback_id": 2,
+            "edge": "Release",
+        }
+    }));
+    pinnacle.assert_no_errors();
+
+    keyboard.set_super(true);
+    keyboard.tap(KEY_A);
+    keyboard.set_super(false);
+    assert_eq!(pinnacle.take_callbacks(), vec![1]);
+
+    keyboard.press(KEY_X);
+    assert!(pinnacle.take_callbacks().is_empty());
+    keyboard.release(KEY_X);
+    assert_eq!(pinnacle.take_callbacks(), vec![2]);
+
+    // Press keybinds keep their key from the window, release keybinds don't
+    keyboard.tap(KEY_B);
+    assert_eq!(client.take_pressed_keys(), vec![KEY_X, KEY_B]);
+}
+
+#[test]
+fn removed_repeating_keybind_stops_repeating() {
+    let mut pinnacle = Pinnacle::start();
+    pinnacle.add_tags(&["1"]);
+    let client = pinnacle.client();
+    let _window = open_focused_window(&mut pinnacle, &client);
+    let keyboard = client.virtual_keyboard();
+
+    let keybind = json!({ "key": { "String": "a" }, "modifiers": [] });
+    let mut set = keybind.clone();
+    set["callback_id"] = json!(1);
+    set["allow_repeat"] = json!(true);
+    pinnacle.send(json!({ "SetKeybind": set }));
+    pinnacle.assert_no_errors();
+
+    keyboard.press(KEY_A);
+    let mut calls = 0;
+    pinnacle.wait_until(|pinnacle| {
+        calls += pinnacle.take_callbacks().len();
+        (calls > 1).then_some(())
+    });
+
+    pinnacle.send(json!({ "RemoveKeybind": keybind }));
+    pinnacle.assert_no_errors();
+    pinnacle.take_callbacks();
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    assert!(pinnacle.take_callbacks().is_empty());
+
+    keyboard.release(KEY_A);
+    assert!(client.take_pressed_keys().is_empty());
+}
+
+#[test]
+fn bind_modes_swallow_keys_until_escape() {
+    let mut pinnacle = Pinnacle::start();
+    pinnacle.add_tags(&["1"]);
+    let client = pinnacle.client();
+    let _window = open_focused_window(&mut pinnacle, &client);
+    let keyboard = client.virtual_keyboard();
+    let pointer = client.virtual_pointer();
+
+    pinnacle.send(json!({
+        "SetKeybind": {
+            "key": { "String": "b" },
+            "modifiers": [],
+            "callback_id": 1,
+            "mode": "resize",
+        }
+    }));
+    pinnacle.send(json!({
+        "SetMousebind": {
+            "modifiers": [],
+            "button": BTN_LEFT,
+            "edge": "Press",
+            "callback_id": 2,
+            "mode": "resize",
+        }
+    }));
+    pinnacle.assert_no_errors();
+
+    // Binds in other modes don't do anything
+    keyboard.tap(KEY_B);
+    pointer.click(BTN_LEFT);
+    assert!(pinnacle.take_callbacks().is_empty());
+    assert_eq!(client.take_pressed_keys(), vec![KEY_B]);
+
+    pinnacle.send(json!({ "SetBindMode": { "mode": "resize" } }));
+    pinnacle.assert_no_errors();
+    keyboard.tap(KEY_B);
+    keyboard.tap(KEY_X);
+    pointer.click(BTN_LEFT);
+    assert_eq!(pinnacle.take_callbacks(), vec![1, 2]);
+    // Keys that aren't keybinds in the mode are swallowed too
+    assert!(client.take_pressed_keys().is_empty());
+
+    keyboard.tap(KEY_ESC);
+    assert_eq!(
+        pinnacle.request(json!("GetBindMode")),
+        json!({ "BindMode": { "mode": "default" } })
+    );
+    keyboard.tap(KEY_X);
+    assert_eq!(client.take_pressed_keys(), vec![KEY_X]);
+}
+
+#[test]
+fn key_sequences_are_called_on_their_last_key() {
+    let mut pinnacle = Pinnacle::start();
+    pinnacle.add_tags(&["1"]);
+    let client = pinnacle.client();
+    let _window = open_focused_window(&mut pinnacle, &client);
+    let keyboard = client.virtual_keyboard();
+
+    let sequence = json!([
+        { "modifiers": ["Super"], "key": { "String": "a" } },
+        { "modifiers": [], "key": { "String": "t" } },
+    ]);
+    pinnacle.send(json!({ "SetKeySequence": { "sequence": sequence, "callback_id": 1 } }));
+    pinnacle.assert_no_errors();
+
+    keyboard.set_super(true);
+    keyboard.tap(KEY_A);
+    keyboard.set_super(false);
+    assert!(pinnacle.take_callbacks().is_empty());
+    keyboard.tap(KEY_T);
+    assert_eq!(pinnacle.take_callbacks(), vec![1]);
+    assert!(client.take_pressed_keys().is_empty());
+
+    // Another key in between cancels the sequence and goes to the window
+    keyboard.set_super(true);
+    keyboard.tap(KEY_A);
+    keyboard.set_super(false);
+    keyboard.tap(KEY_X);
+    keyboard.tap(KEY_T);
+    assert!(pinnacle.take_callbacks().is_empty());
+    assert_eq!(client.take_pressed_keys(), vec![KEY_X, KEY_T]);
+}
+
+#[test]
+fn shortcuts_inhibitor_passes_keybinds_through_until_escaped() {
+    let mut pinnacle = Pinnacle::start();
+    pinnacle.add_tags(&["1"]);
+    let client = pinnacle.client();
+    let window = open_focused_window(&mut pinnacle, &client);
+    let keyboard = client.virtual_keyboard();
+
+    pinnacle.send(json!({
+        "SetKeybind": { "key": { "String": "a" }, "modifiers": ["Super"], "callback_id": 1 }
+    }));
+    pinnacle.assert_no_errors();
+    client.inhibit_shortcuts(&window);
+
+    keyboard.set_super(true);
+    keyboard.tap(KEY_A);
+    assert!(pinnacle.take_callbacks().is_empty());
+    assert_eq!(client.take_pressed_keys(), vec![KEY_A]);
+
+    // Super+Escape turns the inhibitor off by default
+    keyboard.tap(KEY_ESC);
+    keyboard.tap(KEY_A);
+    keyboard.set_super(false);
+    assert_eq!(pinnacle.take_callbacks(), vec![1]);
+    assert!(client.take_pressed_keys().is_empty());
+}
+
+#[test]
+fn input_method_globals_are_advertised() {
+    let pinnacle = Pinnacle::start();
+    let client = pinnacle.client();
+
+    for interface in ["zwp_input_method_manager_v2", "zwp_text_input_manager_v3"] {
+        assert!(
+            client.globals().iter().any(|global| global == interface),
+            "{interface} wasn't advertised"
+        );
+    }
+}
@@ -0,0 +1,70 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+mod common;
+
+use std::process::{Command, Output};
+
+use common::Pinnacle;
+use serde_json::{json, Value};
+
+fn pinnacle_cmd(pinnacle: &Pinnacle, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_pinnacle"))
+        .args(args)
+        .env("PINNACLE_SOCKET", pinnacle.socket_path())
+        .output()
+        .expect("failed to run pinnacle")
+}
+
+#[test]
+fn request_prints_response_as_json() {
+    let pinnacle = Pinnacle::start();
+
+    let output = pinnacle_cmd(&pinnacle, &["request", "GetOutputs"]);
+    assert!(output.status.success());
+    let response: Value = serde_json::from_slice(&output.stdout).expect("output wasn't json");
+    assert_eq!(
+        response,
+        json!({ "Outputs": { "output_names": ["HEADLESS-1"] } })
+    );
+
+    let output = pinnacle_cmd(
+        &pinnacle,
+        &["request", r#"{ "GetOutputProps": { "output_name": "HEADLESS-1" } }"#],
+    );
+    assert!(output.status.success());
+    let response: Value = serde_json::from_slice(&output.stdout).expect("output wasn't json");
+    assert_eq!(response["OutputProps"]["res"], json!([1920, 1080]));
+}
+
+#[test]
+fn msg_sends_fields() {
+    let mut pinnacle = Pinnacle::start();
+    let tags = pinnacle.add_tags(&["1", "2"]);
+
+    let tag_id = format!("tag_id={}", tags[1]);
+    let output = pinnacle_cmd(&pinnacle, &["msg", "SwitchToTag", &tag_id]);
+    assert!(output.status.success());
+    assert_eq!(pinnacle.tag_props(tags[1])["active"], json!(true));
+    assert_eq!(pinnacle.tag_props(tags[0])["active"], json!(false));
+}
+
+#[test]
+fn failed_msg_exits_with_error() {
+    let pinnacle = Pinnacle::start();
+
+    let output = pinnacle_cmd(&pinnacle, &["msg", "SwitchToTag", "tag_id=1000"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("SwitchToTag"));
+
+    let output = pinnacle_cmd(&pinnacle, &["msg", "NotAMessage"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn quit_exits_successfully() {
+    let mut pinnacle = Pinnacle::start();
+
+    let output = pinnacle_cmd(&pinnacle, &["msg", "Quit"]);
+    assert!(output.status.success());
+    assert!(pinnacle.is_closed());
+}
@@ -0,0 +1,1030 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! A harness for end-to-end tests.
+//!
+//! [`Pinnacle::start`] runs the compositor with the headless backend in its own temporary
+//! runtime directory and connects to its socket as the config client. The config process
+//! is a script that writes its config token to a file and idles, so the test can claim
+//! to be it. More clients can be
+//! connected with [`Pinnacle::connect`]. Messages are built with
+//! [`serde_json::json!`] in the same externally tagged shape the Lua API sends,
+//! and responses are returned as [`serde_json::Value`]s.
+//!
+//! Touchpad gestures can be played with [`Pinnacle::gesture`], which the headless backend
+//! reads from a socket since there's no other way to make them.
+//!
+//! [`TestClient`] is a minimal Wayland client that opens xdg toplevels with shm buffers so
+//! tests can check what the compositor does with real windows. It can also create virtual
+//! keyboards and pointers to send input through the compositor's input handling.
+
+#![allow(dead_code)]
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{Read, Write},
+    os::{
+        fd::AsFd,
+        unix::{fs::PermissionsExt, net::UnixStream},
+    },
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+use wayland_client::{
+    delegate_noop,
+    protocol::{
+        wl_buffer::WlBuffer,
+        wl_callback::{self, WlCallback},
+        wl_compositor::WlCompositor,
+        wl_keyboard::{self, WlKeyboard},
+        wl_pointer,
+        wl_registry::{self, WlRegistry},
+        wl_seat::WlSeat,
+        wl_shm::{self, WlShm},
+        wl_shm_pool::WlShmPool,
+        wl_surface::WlSurface,
+    },
+    Connection, Dispatch, EventQueue, QueueHandle, WEnum,
+};
+use wayland_protocols::{
+    wp::keyboard_shortcuts_inhibit::zv1::client::{
+        zwp_keyboard_shortcuts_inhibit_manager_v1::ZwpKeyboardShortcutsInhibitManagerV1,
+        zwp_keyboard_shortcuts_inhibitor_v1::ZwpKeyboardShortcutsInhibitorV1,
+    },
+    xdg::shell::client::{
+        xdg_surface::{self, XdgSurface},
+        xdg_toplevel::{self, XdgToplevel},
+        xdg_wm_base::{self, XdgWmBase},
+    },
+};
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::{
+    zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1,
+    zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1,
+};
+use wayland_protocols_wlr::virtual_pointer::v1::client::{
+    zwlr_virtual_pointer_manager_v1::ZwlrVirtualPointerManagerV1,
+    zwlr_virtual_pointer_v1::ZwlrVirtualPointerV1,
+};
+use xkbcommon::xkb;
+
+/// How long to wait for the compositor to do something before failing the test.
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+static INSTANCE: AtomicU32 = AtomicU32::new(0);
+
+/// A running headless compositor and a connection to its socket.
+///
+/// This derefs to the [`ApiConnection`] acting as the config client.
+pub struct Pinnacle {
+    child: Child,
+    dir: PathBuf,
+    socket_path: PathBuf,
+    /// The socket the headless backend reads gestures from.
+    gesture_socket_path: PathBuf,
+    config: ApiConnection,
+    /// The token of the config process last connected as.
+    config_token: String,
+}
+
+/// A client connection to the compositor's socket.
+pub struct ApiConnection {
+    stream: UnixStream,
+    request_id: u32,
+    /// Incoming messages that weren't the response being waited on.
+    unread: VecDeque<Value>,
+}
+
+impl Pinnacle {
+    /// Start the compositor with the headless backend and connect to it.
+    pub fn start() -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "pinnacle-test-{}-{}",
+            std::process::id(),
+            INSTANCE.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("failed to create test dir");
+        // libwayland refuses to use a runtime dir other users can access
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))
+            .expect("failed to set test dir permissions");
+
+        // The config process hands its token over to the test, then becomes `cat` reading the
+        // compositor's stdin, so it stays alive until the harness goes away. The test itself
+        // acts as the config client.
+        let script = dir.join("config.sh");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\nprintf '%s' \"$PINNACLE_CONFIG_TOKEN\" > config_token\nexec cat\n",
+        )
+        .expect("failed to write config script");
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o700))
+            .expect("failed to set config script permissions");
+        let metaconfig = format!(
+            r#"
+command = "{}"
+reload_keybind = {{ modifiers = ["Ctrl", "Alt"], key = "r" }}
+kill_keybind = {{ modifiers = ["Ctrl", "Alt", "Shift"], key = "escape" }}
+socket_dir = "{}"
+"#,
+            script.display(),
+            dir.display()
+        );
+        std::fs::write(dir.join("metaconfig.toml"), metaconfig)
+            .expect("failed to write metaconfig");
+
+        let gesture_socket_path = dir.join("gestures");
+        let child = Command::new(env!("CARGO_BIN_EXE_pinnacle"))
+            .arg("--headless")
+            .env("PINNACLE_HEADLESS_GESTURES", &gesture_socket_path)
+            .env("PINNACLE_CONFIG_DIR", &dir)
+            .env("XDG_RUNTIME_DIR", &dir)
+            .env("XDG_STATE_HOME", &dir)
+            .env_remove("WAYLAND_DISPLAY")
+            .env_remove("DISPLAY")
+            .env_remove("PINNACLE_SOCKET")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("failed to start pinnacle");
+
+        let socket_path = wait_for(|| find_in_dir(&dir, |name| name.starts_with("pinnacle_socket")))
+            .expect("pinnacle didn't create its socket");
+        let config_token = wait_for_config_token(&dir, "");
+        let mut config = ApiConnection::connect_with_token(&socket_path, Some(&config_token));
+        config.send(json!("ConfigReady"));
+
+        Self {
+            child,
+            dir,
+            socket_path,
+            gesture_socket_path,
+            config,
+            config_token,
+        }
+    }
+
+    /// The directory the compositor uses as its runtime directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The path of the compositor's socket.
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// Play a touchpad gesture, like `{ "Swipe": { "fingers": 3, "dx": 100.0, "dy": 0.0 } }`.
+    ///
+    /// Gestures are played in the order they're sent.
+    pub fn gesture(&self, gesture: Value) {
+        let mut stream = wait_for(|| UnixStream::connect(&self.gesture_socket_path).ok())
+            .expect("pinnacle didn't create its gesture socket");
+        writeln!(stream, "{gesture}").expect("failed to write gesture");
+    }
+
+    /// Connect another client to the compositor's socket, alongside the config client.
+    pub fn connect(&self) -> ApiConnection {
+        ApiConnection::connect(&self.socket_path)
+    }
+
+    /// Wait for Pinnacle to start a new config process, then connect as it.
+    ///
+    /// The connection is staged until it sends `ConfigReady`.
+    pub fn connect_as_config(&mut self) -> ApiConnection {
+        self.config_token = wait_for_config_token(&self.dir, &self.config_token);
+        ApiConnection::connect_with_token(&self.socket_path, Some(&self.config_token))
+    }
+
+    /// Close the config client's connection without connecting again.
+    ///
+    /// Pinnacle treats this like the config dying and restarts it.
+    pub fn close_config(&mut self) {
+        let _ = self.config.stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    /// Close the config client's connection, then connect as the restarted config.
+    ///
+    /// The restarted config is ready right away, so it replaces everything the old one set up.
+    pub fn reconnect(&mut self) {
+        self.close_config();
+        self.config = self.connect_as_config();
+        self.config.send(json!("ConfigReady"));
+    }
+
+    /// Connect to the compositor's socket without doing the handshake.
+    pub fn connect_without_hello(&self) -> ApiConnection {
+        ApiConnection::connect_without_hello(&self.socket_path)
+    }
+
+    /// Poll requests until `cond` returns `Some`, failing the test after [`TIMEOUT`].
+    pub fn wait_until<T>(&mut self, mut cond: impl FnMut(&mut Self) -> Option<T>) -> T {
+        let start = Instant::now();
+        loop {
+            if let Some(ret) = cond(self) {
+                return ret;
+            }
+            assert!(
+                start.elapsed() < TIMEOUT,
+                "timed out waiting for the compositor"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Connect a Wayland client to the compositor.
+    pub fn client(&self) -> TestClient {
+        let socket_path = wait_for(|| {
+            find_in_dir(&self.dir, |name| {
+                name.starts_with("wayland-") && !name.ends_with(".lock")
+            })
+        })
+        .expect("pinnacle didn't create a wayland socket");
+        TestClient::connect(&socket_path, &self.dir)
+    }
+}
+
+impl Deref for Pinnacle {
+    type Target = ApiConnection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.config
+    }
+}
+
+impl DerefMut for Pinnacle {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.config
+    }
+}
+
+impl ApiConnection {
+    fn connect(socket_path: &Path) -> Self {
+        Self::connect_with_token(socket_path, None)
+    }
+
+    fn connect_with_token(socket_path: &Path, config_token: Option<&str>) -> Self {
+        let mut connection = Self::connect_without_hello(socket_path);
+        connection.send(json!({
+            "Hello": { "protocol_version": 2, "config_token": config_token }
+        }));
+        let hello = connection.recv();
+        assert_eq!(
+            hello["Hello"]["protocol_version"], 2,
+            "handshake failed: {hello}"
+        );
+        connection
+    }
+
+    fn connect_without_hello(socket_path: &Path) -> Self {
+        let stream = UnixStream::connect(socket_path).expect("failed to connect to pinnacle");
+        stream
+            .set_read_timeout(Some(TIMEOUT))
+            .expect("failed to set read timeout");
+
+        Self {
+            stream,
+            request_id: 0,
+            unread: VecDeque::new(),
+        }
+    }
+
+    /// Send a message to the compositor.
+    pub fn send(&mut self, msg: Value) {
+        let bytes = rmp_serde::to_vec_named(&msg).expect("failed to encode msg");
+        self.send_frame(&bytes);
+    }
+
+    /// Send raw bytes to the compositor as one frame.
+    pub fn send_frame(&mut self, bytes: &[u8]) {
+        self.stream
+            .write_all(&(bytes.len() as u32).to_le_bytes())
+            .expect("failed to write to socket");
+        self.stream
+            .write_all(bytes)
+            .expect("failed to write to socket");
+    }
+
+    /// Receive the next message from the compositor.
+    ///
+    /// Panics if nothing arrives within [`TIMEOUT`].
+    pub fn recv(&mut self) -> Value {
+        if let Some(msg) = self.unread.pop_front() {
+            return msg;
+        }
+        self.read_msg()
+    }
+
+    fn read_msg(&mut self) -> Value {
+        let mut len_bytes = [0u8; 4];
+        self.stream
+            .read_exact(&mut len_bytes)
+            .expect("failed to read from socket");
+        let mut msg_bytes = vec![0u8; u32::from_le_bytes(len_bytes) as usize];
+        self.stream
+            .read_exact(&mut msg_bytes)
+            .expect("failed to read from socket");
+        rmp_serde::from_slice(&msg_bytes).expect("failed to decode msg")
+    }
+
+    /// Whether the compositor has closed the connection, once all unread messages are received.
+    pub fn is_closed(&mut self) -> bool {
+        let mut byte = [0u8; 1];
+        self.unread.is_empty() && matches!(self.stream.read(&mut byte), Ok(0))
+    }
+
+    /// Send a request and wait for its response.
+    ///
+    /// This returns the inner value of the `RequestResponse`, e.g. `{ "Tags": { ... } }`.
+    /// Other messages that arrive in the meantime are kept for [`Pinnacle::recv`].
+    pub fn request(&mut self, request: Value) -> Value {
+        self.request_id += 1;
+        let request_id = self.request_id;
+
+        self.send(json!({
+            "Request": {
+                "request_id": request_id,
+                "request": request,
+            }
+        }));
+
+        loop {
+            let msg = self.read_msg();
+            if msg["RequestResponse"]["request_id"] == request_id {
+                return msg["RequestResponse"]["response"].clone();
+            }
+            self.unread.push_back(msg);
+        }
+    }
+
+    /// Get the ids of the callbacks the compositor has called since the last time this was
+    /// called, in order.
+    ///
+    /// A request is sent first so every callback called before it is received.
+    pub fn take_callbacks(&mut self) -> Vec<u64> {
+        self.request(json!("GetBindMode"));
+        let mut callbacks = Vec::new();
+        self.unread.retain(|msg| {
+            let callback_id = msg["CallCallback"]["callback_id"].as_u64();
+            callbacks.extend(callback_id);
+            callback_id.is_none()
+        });
+        callbacks
+    }
+
+    /// Get the events the compositor has sent since the last time this was called, in order.
+    ///
+    /// A request is sent first so every event sent before it is received.
+    pub fn take_events(&mut self) -> Vec<Value> {
+        self.request(json!("GetBindMode"));
+        let mut events = Vec::new();
+        self.unread.retain(|msg| match msg.get("Event") {
+            Some(event) => {
+                events.push(event.clone());
+                false
+            }
+            None => true,
+        });
+        events
+    }
+
+    /// Check that every message sent so far was handled without an error.
+    ///
+    /// Messages are handled in order, so an error from any of them arrives before the
+    /// response to a request sent after them.
+    pub fn assert_no_errors(&mut self) {
+        self.request(json!("GetBindMode"));
+        let errors = self
+            .unread
+            .iter()
+            .filter(|msg| msg.get("Error").is_some())
+            .collect::<Vec<_>>();
+        assert!(errors.is_empty(), "messages failed: {errors:?}");
+    }
+
+    /// Get the ids of all open windows.
+    pub fn windows(&mut self) -> Vec<u64> {
+        ids(&self.request(json!("GetWindows"))["Windows"]["window_ids"])
+    }
+
+    /// Get the properties of a window.
+    pub fn window_props(&mut self, window_id: u64) -> Value {
+        self.request(json!({ "GetWindowProps": { "window_id": window_id } }))["WindowProps"]
+            .clone()
+    }
+
+    /// Get the ids of all tags.
+    pub fn tags(&mut self) -> Vec<u64> {
+        ids(&self.request(json!("GetTags"))["Tags"]["tag_ids"])
+    }
+
+    /// Get the properties of a tag.
+    pub fn tag_props(&mut self, tag_id: u64) -> Value {
+        self.request(json!({ "GetTagProps": { "tag_id": tag_id } }))["TagProps"].clone()
+    }
+
+    /// Get the names of all outputs.
+    pub fn outputs(&mut self) -> Vec<String> {
+        self.request(json!("GetOutputs"))["Outputs"]["output_names"]
+            .as_array()
+            .expect("output_names wasn't an array")
+            .iter()
+            .map(|name| name.as_str().expect("output name wasn't a string").to_string())
+            .collect()
+    }
+
+    /// Get which outputs touch devices are mapped to, as device and output names.
+    pub fn touch_outputs(&mut self) -> Vec<(Option<String>, String)> {
+        device_outputs(&self.request(json!("GetTouchOutputs"))["TouchOutputs"]["mappings"])
+    }
+
+    /// Get which outputs drawing tablets are mapped to, as device and output names.
+    pub fn tablet_outputs(&mut self) -> Vec<(Option<String>, String)> {
+        device_outputs(&self.request(json!("GetTabletOutputs"))["TabletOutputs"]["mappings"])
+    }
+
+    /// Add tags with the given names to the first output, activate the first one,
+    /// and return their ids.
+    pub fn add_tags(&mut self, names: &[&str]) -> Vec<u64> {
+        let output_name = self.outputs().remove(0);
+        let old_tags = self.tags();
+        self.send(json!({
+            "AddTags": {
+                "output_name": output_name,
+                "tag_names": names,
+            }
+        }));
+        let new_tags = self
+            .tags()
+            .into_iter()
+            .filter(|tag| !old_tags.contains(tag))
+            .collect::<Vec<_>>();
+        self.send(json!({ "SwitchToTag": { "tag_id": new_tags[0] } }));
+        new_tags
+    }
+}
+
+impl Drop for Pinnacle {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn ids(value: &Value) -> Vec<u64> {
+    value
+        .as_array()
+        .expect("ids weren't an array")
+        .iter()
+        .map(|id| id.as_u64().expect("id wasn't an integer"))
+        .collect()
+}
+
+/// Read device output mappings, sorted so they can be compared.
+fn device_outputs(value: &Value) -> Vec<(Option<String>, String)> {
+    let mut mappings = value
+        .as_array()
+        .expect("mappings weren't an array")
+        .iter()
+        .map(|mapping| {
+            let device_name = mapping["device_name"].as_str().map(ToString::to_string);
+            let output_name = mapping["output_name"]
+                .as_str()
+                .expect("output name wasn't a string");
+            (device_name, output_name.to_string())
+        })
+        .collect::<Vec<_>>();
+    mappings.sort();
+    mappings
+}
+
+fn find_in_dir(dir: &Path, pred: impl Fn(&str) -> bool) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| pred(&entry.file_name().to_string_lossy()))
+        .map(|entry| entry.path())
+}
+
+/// Wait for the config process to write a token other than `last`.
+fn wait_for_config_token(dir: &Path, last: &str) -> String {
+    wait_for(|| {
+        std::fs::read_to_string(dir.join("config_token"))
+            .ok()
+            .filter(|token| !token.is_empty() && token != last)
+    })
+    .expect("pinnacle didn't start the config")
+}
+
+fn wait_for<T>(mut f: impl FnMut() -> Option<T>) -> Option<T> {
+    let start = Instant::now();
+    while start.elapsed() < TIMEOUT {
+        if let Some(ret) = f() {
+            return Some(ret);
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    None
+}
+
+/// A Wayland client connected to the compositor under test.
+///
+/// Events are dispatched on a separate thread, which answers pings and acks configures by
+/// attaching a buffer of the configured size. The keys the seat's keyboard receives are
+/// logged so tests can check which keys reached the client's windows.
+pub struct TestClient {
+    conn: Connection,
+    qh: QueueHandle<ClientState>,
+    compositor: WlCompositor,
+    wm_base: XdgWmBase,
+    seat: WlSeat,
+    keyboard: WlKeyboard,
+    keyboard_log: Arc<Mutex<KeyboardLog>>,
+    virtual_keyboard_manager: Option<ZwpVirtualKeyboardManagerV1>,
+    virtual_pointer_manager: Option<ZwlrVirtualPointerManagerV1>,
+    shortcuts_inhibit_manager: Option<ZwpKeyboardShortcutsInhibitManagerV1>,
+    /// The interfaces of all globals the compositor advertised.
+    globals: Vec<String>,
+    dir: PathBuf,
+}
+
+struct ClientState {
+    compositor: Option<WlCompositor>,
+    shm: Option<WlShm>,
+    wm_base: Option<XdgWmBase>,
+    seat: Option<WlSeat>,
+    virtual_keyboard_manager: Option<ZwpVirtualKeyboardManagerV1>,
+    virtual_pointer_manager: Option<ZwlrVirtualPointerManagerV1>,
+    shortcuts_inhibit_manager: Option<ZwpKeyboardShortcutsInhibitManagerV1>,
+    globals: Vec<String>,
+    dir: PathBuf,
+}
+
+/// What the seat's keyboard received, shared between the test and the dispatch thread.
+#[derive(Default)]
+struct KeyboardLog {
+    /// Whether one of the client's surfaces has keyboard focus.
+    focused: bool,
+    /// The evdev keycodes of pressed keys, in order.
+    pressed: Vec<u32>,
+}
+
+/// Per-window data shared between the test and the dispatch thread.
+struct WindowData {
+    surface: WlSurface,
+    /// The size from the last toplevel configure, applied on the next surface configure.
+    pending_size: Mutex<(i32, i32)>,
+}
+
+static TEMP_FILE: AtomicU32 = AtomicU32::new(0);
+
+/// `wl_keyboard.keymap_format.xkb_v1`.
+const KEYMAP_FORMAT_XKB_V1: u32 = 1;
+
+impl TestClient {
+    fn connect(socket_path: &Path, dir: &Path) -> Self {
+        let stream = UnixStream::connect(socket_path).expect("failed to connect to wayland socket");
+        let conn = Connection::from_socket(stream).expect("failed to create wayland connection");
+
+        let mut event_queue: EventQueue<ClientState> = conn.new_event_queue();
+        let qh = event_queue.handle();
+        conn.display().get_registry(&qh, ());
+
+        let mut state = ClientState {
+            compositor: None,
+            shm: None,
+            wm_base: None,
+            seat: None,
+            virtual_keyboard_manager: None,
+            virtual_pointer_manager: None,
+            shortcuts_inhibit_manager: None,
+            globals: Vec::new(),
+            dir: dir.to_path_buf(),
+        };
+        event_queue
+            .roundtrip(&mut state)
+            .expect("failed to get wayland globals");
+
+        let compositor = state.compositor.clone().expect("no wl_compositor");
+        let wm_base = state.wm_base.clone().expect("no xdg_wm_base");
+        let seat = state.seat.clone().expect("no wl_seat");
+        assert!(state.shm.is_some(), "no wl_shm");
+
+        let keyboard_log = Arc::<Mutex<KeyboardLog>>::default();
+        let keyboard = seat.get_keyboard(&qh, keyboard_log.clone());
+
+        let client = Self {
+            conn,
+            qh,
+            compositor,
+            wm_base,
+            seat,
+            keyboard,
+            keyboard_log,
+            virtual_keyboard_manager: state.virtual_keyboard_manager.clone(),
+            virtual_pointer_manager: state.virtual_pointer_manager.clone(),
+            shortcuts_inhibit_manager: state.shortcuts_inhibit_manager.clone(),
+            globals: state.globals.clone(),
+            dir: dir.to_path_buf(),
+        };
+
+        std::thread::spawn(move || {
+            while event_queue.blocking_dispatch(&mut state).is_ok() {}
+        });
+
+        client
+    }
+
+    /// The interfaces of the globals the compositor advertised when the client connected.
+    pub fn globals(&self) -> &[String] {
+        &self.globals
+    }
+
+    /// Wait until the compositor has handled every request sent so far and the client has
+    /// handled every event sent in response.
+    pub fn roundtrip(&self) {
+        let done = Arc::new(AtomicBool::new(false));
+        self.conn.display().sync(&self.qh, done.clone());
+        self.conn.flush().expect("failed to flush wayland connection");
+        wait_for(|| done.load(Ordering::SeqCst).then_some(()))
+            .expect("compositor didn't answer a sync");
+    }
+
+    /// Wait until one of the client's surfaces has keyboard focus.
+    pub fn wait_for_keyboard_focus(&self) {
+        wait_for(|| self.keyboard_log().focused.then_some(()))
+            .expect("client didn't get keyboard focus");
+    }
+
+    /// Take the evdev keycodes of the keys the client's windows received since the last time
+    /// this was called.
+    pub fn take_pressed_keys(&self) -> Vec<u32> {
+        std::mem::take(&mut self.keyboard_log().pressed)
+    }
+
+    fn keyboard_log(&self) -> std::sync::MutexGuard<'_, KeyboardLog> {
+        self.keyboard_log
+            .lock()
+            .expect("failed to lock keyboard log")
+    }
+
+    /// Create a virtual keyboard with a US keymap.
+    pub fn virtual_keyboard(&self) -> TestKeyboard<'_> {
+        let manager = self
+            .virtual_keyboard_manager
+            .as_ref()
+            .expect("no zwp_virtual_keyboard_manager_v1");
+        let keyboard = manager.create_virtual_keyboard(&self.seat, &self.qh, ());
+
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let keymap = xkb::Keymap::new_from_names(
+            &context,
+            "",
+            "",
+            "us",
+            "",
+            None,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        )
+        .expect("failed to compile keymap");
+        let mut keymap_bytes = keymap
+            .get_as_string(xkb::KEYMAP_FORMAT_TEXT_V1)
+            .into_bytes();
+        keymap_bytes.push(0);
+
+        let mut file = temp_file(&self.dir, "keymap");
+        file.write_all(&keymap_bytes)
+            .expect("failed to write keymap file");
+        keyboard.keymap(
+            KEYMAP_FORMAT_XKB_V1,
+            file.as_fd(),
+            keymap_bytes.len() as u32,
+        );
+
+        let keyboard = TestKeyboard {
+            client: self,
+            keyboard,
+            super_mask: 1 << keymap.mod_get_index(xkb::MOD_NAME_LOGO),
+        };
+        self.roundtrip();
+        keyboard
+    }
+
+    /// Create a virtual pointer.
+    pub fn virtual_pointer(&self) -> TestPointer<'_> {
+        let manager = self
+            .virtual_pointer_manager
+            .as_ref()
+            .expect("no zwlr_virtual_pointer_manager_v1");
+        let pointer = manager.create_virtual_pointer(Some(&self.seat), &self.qh, ());
+        self.roundtrip();
+        TestPointer {
+            client: self,
+            pointer,
+        }
+    }
+
+    /// Ask for keyboard shortcuts to be inhibited while `window` has keyboard focus.
+    pub fn inhibit_shortcuts(&self, window: &TestWindow) {
+        let manager = self
+            .shortcuts_inhibit_manager
+            .as_ref()
+            .expect("no zwp_keyboard_shortcuts_inhibit_manager_v1");
+        manager.inhibit_shortcuts(&window.surface, &self.seat, &self.qh, ());
+        self.roundtrip();
+    }
+
+    /// Open a toplevel with the given app id and title.
+    pub fn create_window(&self, app_id: &str, title: &str) -> TestWindow {
+        let surface = self.compositor.create_surface(&self.qh, ());
+        let data = Arc::new(WindowData {
+            surface: surface.clone(),
+            pending_size: Mutex::new((0, 0)),
+        });
+        let xdg_surface = self
+            .wm_base
+            .get_xdg_surface(&surface, &self.qh, data.clone());
+        let toplevel = xdg_surface.get_toplevel(&self.qh, data);
+        toplevel.set_app_id(app_id.to_string());
+        toplevel.set_title(title.to_string());
+        surface.commit();
+        self.conn.flush().expect("failed to flush wayland connection");
+
+        TestWindow {
+            conn: self.conn.clone(),
+            surface,
+            xdg_surface,
+            toplevel,
+        }
+    }
+}
+
+/// A virtual keyboard created by a [`TestClient`].
+///
+/// Keys are evdev keycodes. Every method waits for the compositor to handle the input.
+pub struct TestKeyboard<'a> {
+    client: &'a TestClient,
+    keyboard: ZwpVirtualKeyboardV1,
+    /// The modifier mask of Super in the keyboard's keymap.
+    super_mask: u32,
+}
+
+impl TestKeyboard<'_> {
+    pub fn press(&self, key: u32) {
+        self.key(key, wl_keyboard::KeyState::Pressed);
+    }
+
+    pub fn release(&self, key: u32) {
+        self.key(key, wl_keyboard::KeyState::Released);
+    }
+
+    fn key(&self, key: u32, state: wl_keyboard::KeyState) {
+        self.keyboard.key(0, key, state.into());
+        self.client.roundtrip();
+    }
+
+    /// Press and release a key.
+    pub fn tap(&self, key: u32) {
+        self.press(key);
+        self.release(key);
+    }
+
+    /// Hold or let go of Super for the keys after this.
+    pub fn set_super(&self, held: bool) {
+        let depressed = if held { self.super_mask } else { 0 };
+        self.keyboard.modifiers(depressed, 0, 0, 0);
+        self.client.roundtrip();
+    }
+}
+
+/// A virtual pointer created by a [`TestClient`].
+pub struct TestPointer<'a> {
+    client: &'a TestClient,
+    pointer: ZwlrVirtualPointerV1,
+}
+
+impl TestPointer<'_> {
+    /// Move the pointer, then wait for the compositor to handle it.
+    pub fn move_by(&self, dx: f64, dy: f64) {
+        self.pointer.motion(0, dx, dy);
+        self.pointer.frame();
+        self.client.roundtrip();
+    }
+
+    /// Press and release a button where the pointer is, then wait for the compositor to
+    /// handle it.
+    pub fn click(&self, button: u32) {
+        self.pointer
+            .button(0, button, wl_pointer::ButtonState::Pressed);
+        self.pointer
+            .button(0, button, wl_pointer::ButtonState::Released);
+        self.pointer.frame();
+        self.client.roundtrip();
+    }
+}
+
+/// An xdg toplevel opened by a [`TestClient`].
+pub struct TestWindow {
+    conn: Connection,
+    surface: WlSurface,
+    xdg_surface: XdgSurface,
+    toplevel: XdgToplevel,
+}
+
+impl TestWindow {
+    pub fn set_title(&self, title: &str) {
+        self.toplevel.set_title(title.to_string());
+        self.surface.commit();
+        self.conn.flush().expect("failed to flush wayland connection");
+    }
+
+    /// Destroy the toplevel, closing the window.
+    pub fn close(self) {
+        self.toplevel.destroy();
+        self.xdg_surface.destroy();
+        self.surface.destroy();
+        self.conn.flush().expect("failed to flush wayland connection");
+    }
+}
+
+impl Dispatch<WlRegistry, ()> for ClientState {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: wl_registry::Event,
+        _data: &(),
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        let wl_registry::Event::Global {
+            name,
+            interface,
+            version,
+        } = event
+        else {
+            return;
+        };
+
+        match interface.as_str() {
+            "wl_compositor" => {
+                state.compositor = Some(registry.bind(name, version.min(4), qh, ()));
+            }
+            "wl_shm" => state.shm = Some(registry.bind(name, 1, qh, ())),
+            "xdg_wm_base" => state.wm_base = Some(registry.bind(name, 1, qh, ())),
+            "wl_seat" => state.seat = Some(registry.bind(name, version.min(5), qh, ())),
+            "zwp_virtual_keyboard_manager_v1" => {
+                state.virtual_keyboard_manager = Some(registry.bind(name, 1, qh, ()));
+            }
+            "zwlr_virtual_pointer_manager_v1" => {
+                state.virtual_pointer_manager = Some(registry.bind(name, 1, qh, ()));
+            }
+            "zwp_keyboard_shortcuts_inhibit_manager_v1" => {
+                state.shortcuts_inhibit_manager = Some(registry.bind(name, 1, qh, ()));
+            }
+            _ => (),
+        }
+        state.globals.push(interface);
+    }
+}
+
+impl Dispatch<XdgWmBase, ()> for ClientState {
+    fn event(
+        _state: &mut Self,
+        wm_base: &XdgWmBase,
+        event: xdg_wm_base::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            wm_base.pong(serial);
+        }
+    }
+}
+
+impl Dispatch<XdgToplevel, Arc<WindowData>> for ClientState {
+    fn event(
+        _state: &mut Self,
+        _toplevel: &XdgToplevel,
+        event: xdg_toplevel::Event,
+        data: &Arc<WindowData>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let xdg_toplevel::Event::Configure { width, height, .. } = event {
+            *data.pending_size.lock().expect("failed to lock size") = (width, height);
+        }
+    }
+}
+
+impl Dispatch<XdgSurface, Arc<WindowData>> for ClientState {
+    fn event(
+        state: &mut Self,
+        xdg_surface: &XdgSurface,
+        event: xdg_surface::Event,
+        data: &Arc<WindowData>,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        let xdg_surface::Event::Configure { serial } = event else {
+            return;
+        };
+        xdg_surface.ack_configure(serial);
+
+        let (width, height) = match *data.pending_size.lock().expect("failed to lock size") {
+            (0, _) | (_, 0) => (100, 100),
+            size => size,
+        };
+        let stride = width * 4;
+        let pool_size = stride * height;
+
+        let file = temp_file(&state.dir, "buffer");
+        file.set_len(pool_size as u64)
+            .expect("failed to size buffer file");
+
+        let shm = state.shm.as_ref().expect("no wl_shm");
+        let pool = shm.create_pool(file.as_fd(), pool_size, qh, ());
+        let buffer = pool.create_buffer(0, width, height, stride, wl_shm::Format::Argb8888, qh, ());
+        pool.destroy();
+
+        data.surface.attach(Some(&buffer), 0, 0);
+        data.surface.damage_buffer(0, 0, width, height);
+        data.surface.commit();
+    }
+}
+
+impl Dispatch<WlKeyboard, Arc<Mutex<KeyboardLog>>> for ClientState {
+    fn event(
+        _state: &mut Self,
+        _keyboard: &WlKeyboard,
+        event: wl_keyboard::Event,
+        log: &Arc<Mutex<KeyboardLog>>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let mut log = log.lock().expect("failed to lock keyboard log");
+        match event {
+            wl_keyboard::Event::Enter { .. } => log.focused = true,
+            wl_keyboard::Event::Leave { .. } => log.focused = false,
+            wl_keyboard::Event::Key {
+                key,
+                state: WEnum::Value(wl_keyboard::KeyState::Pressed),
+                ..
+            } => log.pressed.push(key),
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<WlCallback, Arc<AtomicBool>> for ClientState {
+    fn event(
+        _state: &mut Self,
+        _callback: &WlCallback,
+        event: wl_callback::Event,
+        done: &Arc<AtomicBool>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_callback::Event::Done { .. } = event {
+            done.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Create a file in `dir` that's unlinked right away, so it's gone once it's closed.
+fn temp_file(dir: &Path, prefix: &str) -> File {
+    let path = dir.join(format!(
+        "{prefix}-{}",
+        TEMP_FILE.fetch_add(1, Ordering::SeqCst)
+    ));
+    let file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .expect("failed to create temp file");
+    let _ = std::fs::remove_file(path);
+    file
+}
+
+delegate_noop!(ClientState: WlCompositor);
+delegate_noop!(ClientState: ignore WlSurface);
+delegate_noop!(ClientState: ignore WlShm);
+delegate_noop!(ClientState: WlShmPool);
+delegate_noop!(ClientState: ignore WlBuffer);
+delegate_noop!(ClientState: ignore WlSeat);
+delegate_noop!(ClientState: ZwpVirtualKeyboardManagerV1);
+delegate_noop!(ClientState: ZwpVirtualKeyboardV1);
+delegate_noop!(ClientState: ZwlrVirtualPointerManagerV1);
+delegate_noop!(ClientState: ZwlrVirtualPointerV1);
+delegate_noop!(ClientState: ZwpKeyboardShortcutsInhibitManagerV1);
+delegate_noop!(ClientState: ignore ZwpKeyboardShortcutsInhibitorV1);